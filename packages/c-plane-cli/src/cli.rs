@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use uuid::Uuid;
+
+use crate::output::OutputFormat;
+
+/// Manage organisations and projects on the control plane
+#[derive(Parser, Debug)]
+#[command(name = "cplane", version)]
+pub struct Cli {
+    /// Profile to use instead of the current one
+    #[arg(long, global = true, env = "CPLANE_PROFILE")]
+    pub profile: Option<String>,
+
+    #[arg(long, short, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+
+    /// Profiles file, defaults to ~/.config/cplane/config.toml
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Store credentials for a server in a profile and make it the current one
+    Login(LoginArgs),
+    /// Remove the credentials from a profile
+    Logout,
+    /// List, switch and inspect profiles
+    #[command(subcommand)]
+    Profile(ProfileCommand),
+    #[command(subcommand, alias = "orgs")]
+    Organisations(OrganisationCommand),
+    #[command(subcommand)]
+    Projects(ProjectCommand),
+}
+
+#[derive(Args, Debug)]
+#[command(group(ArgGroup::new("credentials").required(true).args(["token", "identity"])))]
+pub struct LoginArgs {
+    /// Base URL of the API
+    #[arg(long, env = "CPLANE_URL")]
+    pub url: String,
+
+    /// Identity token, checked by the gateway
+    #[arg(long, env = "CPLANE_TOKEN")]
+    pub token: Option<String>,
+
+    /// Identity id, for a local server running without the gateway
+    #[arg(long)]
+    pub identity: Option<Uuid>,
+
+    /// Default organisation for commands that need one
+    #[arg(long)]
+    pub organisation: Option<Uuid>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ProfileCommand {
+    List,
+    /// Make a profile the current one
+    Use { name: String },
+    /// Show the selected profile without its credentials
+    Show,
+    /// Set the default organisation of the selected profile
+    SetOrganisation { id: Uuid },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum OrganisationCommand {
+    /// Organisations the profile's identity is a member of
+    List {
+        #[arg(long, default_value_t = 1)]
+        page: u64,
+        #[arg(long, default_value_t = 20)]
+        per_page: u64,
+    },
+    /// Cloud servers only, a self-hosted install has just its own organisation
+    Create {
+        name: String,
+        #[arg(long)]
+        description: Option<String>,
+        #[arg(long)]
+        avatar_url: Option<String>,
+    },
+    Get { id: Uuid },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ProjectCommand {
+    List {
+        /// Fetch every page instead of just one
+        #[arg(long, conflicts_with = "page")]
+        all: bool,
+        #[arg(long, default_value_t = 1)]
+        page: u64,
+        #[arg(long, default_value_t = 20)]
+        per_page: u64,
+    },
+    Create {
+        name: String,
+        #[arg(long)]
+        slug: String,
+        /// Defaults to the profile's organisation
+        #[arg(long)]
+        organisation: Option<Uuid>,
+        /// Defaults to the profile's identity
+        #[arg(long)]
+        owner: Option<Uuid>,
+        #[arg(long)]
+        description: Option<String>,
+        #[arg(long)]
+        public: bool,
+    },
+    Get { id: Uuid },
+    Update {
+        id: Uuid,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long, conflicts_with = "clear_description")]
+        description: Option<String>,
+        #[arg(long)]
+        clear_description: bool,
+        #[arg(long)]
+        archived: Option<bool>,
+    },
+    Delete { id: Uuid },
+}
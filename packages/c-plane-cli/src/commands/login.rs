@@ -0,0 +1,33 @@
+use crate::cli::LoginArgs;
+use crate::commands::Context;
+use crate::config::Profile;
+use crate::error::CliError;
+
+/// Saves the profile only once the server accepted the credentials
+pub async fn login(ctx: &mut Context, args: LoginArgs) -> Result<(), CliError> {
+    let profile = Profile {
+        url: args.url,
+        token: args.token,
+        identity: args.identity,
+        organisation: args.organisation,
+    };
+    profile.client().list_projects(1, 1).await?;
+
+    let name = ctx.profile_name.clone();
+    ctx.profiles.profiles.insert(name.clone(), profile);
+    ctx.profiles.current = Some(name.clone());
+    ctx.profiles.save(&ctx.config_path)?;
+
+    eprintln!("Logged in, profile '{}' is now current", name);
+    Ok(())
+}
+
+pub fn logout(ctx: &mut Context) -> Result<(), CliError> {
+    let profile = ctx.profiles.get_mut(&ctx.profile_name)?;
+    profile.token = None;
+    profile.identity = None;
+    ctx.profiles.save(&ctx.config_path)?;
+
+    eprintln!("Removed the credentials of profile '{}'", ctx.profile_name);
+    Ok(())
+}
@@ -0,0 +1,56 @@
+mod login;
+mod organisations;
+mod profile;
+mod projects;
+
+use std::path::PathBuf;
+
+use c_plane_client::Client;
+
+use crate::cli::{Cli, Command};
+use crate::config::{self, Profile, ProfilesFile};
+use crate::error::CliError;
+use crate::output::OutputFormat;
+
+/// What every command needs besides its own arguments
+pub struct Context {
+    pub output: OutputFormat,
+    pub config_path: PathBuf,
+    pub profiles: ProfilesFile,
+    pub profile_name: String,
+}
+
+impl Context {
+    pub fn profile(&self) -> Result<&Profile, CliError> {
+        self.profiles.get(&self.profile_name)
+    }
+
+    /// A client for the selected profile, which must have credentials
+    pub fn client(&self) -> Result<Client, CliError> {
+        let profile = self.profile()?;
+        if profile.auth().is_none() {
+            return Err(CliError::NotLoggedIn(self.profile_name.clone()));
+        }
+        Ok(profile.client())
+    }
+}
+
+pub async fn run(cli: Cli) -> Result<(), CliError> {
+    let config_path = cli.config.unwrap_or_else(config::default_path);
+    let profiles = ProfilesFile::load(&config_path)?;
+    let profile_name = profiles.selected_name(cli.profile.as_deref());
+    let mut ctx = Context {
+        output: cli.output,
+        config_path,
+        profiles,
+        profile_name,
+    };
+
+    match cli.command {
+        Command::Login(args) => login::login(&mut ctx, args).await,
+        Command::Logout => login::logout(&mut ctx),
+        Command::Profile(command) => profile::run(&mut ctx, command),
+        Command::Organisations(command) => organisations::run(&ctx, command).await,
+        Command::Projects(command) => projects::run(&ctx, command).await,
+    }
+}
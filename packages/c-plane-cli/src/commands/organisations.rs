@@ -0,0 +1,35 @@
+use c_plane_client::types::CreateOrganisationRequest;
+
+use crate::cli::OrganisationCommand;
+use crate::commands::Context;
+use crate::error::CliError;
+use crate::output::{print_list, print_one};
+
+pub async fn run(ctx: &Context, command: OrganisationCommand) -> Result<(), CliError> {
+    let client = ctx.client()?;
+
+    match command {
+        OrganisationCommand::List { page, per_page } => {
+            let organisations = client.list_organisations(page, per_page).await?;
+            print_list(ctx.output, &organisations.data)
+        }
+        OrganisationCommand::Create {
+            name,
+            description,
+            avatar_url,
+        } => {
+            let created = client
+                .create_organisation(&CreateOrganisationRequest {
+                    name,
+                    description,
+                    avatar_url,
+                })
+                .await?;
+            print_one(ctx.output, &created.organisation)
+        }
+        OrganisationCommand::Get { id } => {
+            let organisation = client.get_organisation(id).await?;
+            print_one(ctx.output, &organisation)
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use serde::Serialize;
+
+use crate::cli::ProfileCommand;
+use crate::commands::Context;
+use crate::error::CliError;
+use crate::output::{Tabular, print_list, print_one};
+
+/// A profile as shown to the user, with credentials reduced to their kind
+#[derive(Serialize)]
+struct ProfileSummary {
+    name: String,
+    current: bool,
+    url: String,
+    auth: &'static str,
+    organisation: Option<String>,
+}
+
+impl Tabular for ProfileSummary {
+    const HEADERS: &'static [&'static str] = &["current", "name", "url", "auth", "organisation"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            if self.current { "*" } else { "" }.to_string(),
+            self.name.clone(),
+            self.url.clone(),
+            self.auth.to_string(),
+            self.organisation.clone().unwrap_or_else(|| "-".to_string()),
+        ]
+    }
+}
+
+fn summaries(ctx: &Context) -> Vec<ProfileSummary> {
+    ctx.profiles
+        .profiles
+        .iter()
+        .map(|(name, profile)| ProfileSummary {
+            name: name.clone(),
+            current: *name == ctx.profile_name,
+            url: profile.url.clone(),
+            auth: if profile.token.is_some() {
+                "token"
+            } else if profile.identity.is_some() {
+                "identity"
+            } else {
+                "none"
+            },
+            organisation: profile.organisation.map(|id| id.to_string()),
+        })
+        .collect()
+}
+
+pub fn run(ctx: &mut Context, command: ProfileCommand) -> Result<(), CliError> {
+    match command {
+        ProfileCommand::List => print_list(ctx.output, &summaries(ctx)),
+        ProfileCommand::Use { name } => {
+            ctx.profiles.get(&name)?;
+            ctx.profiles.current = Some(name);
+            ctx.profiles.save(&ctx.config_path)
+        }
+        ProfileCommand::Show => {
+            ctx.profile()?;
+            let summary = summaries(ctx)
+                .into_iter()
+                .find(|summary| summary.name == ctx.profile_name)
+                .expect("selected profile exists");
+            print_one(ctx.output, &summary)
+        }
+        ProfileCommand::SetOrganisation { id } => {
+            ctx.profiles.get_mut(&ctx.profile_name)?.organisation = Some(id);
+            ctx.profiles.save(&ctx.config_path)
+        }
+    }
+}
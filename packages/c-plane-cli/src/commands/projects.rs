@@ -0,0 +1,85 @@
+use c_plane_client::types::{CreateProjectRequest, ProjectResponse, UpdateProjectRequest};
+use futures_util::TryStreamExt;
+
+use crate::cli::ProjectCommand;
+use crate::commands::Context;
+use crate::error::CliError;
+use crate::output::{print_list, print_one};
+
+pub async fn run(ctx: &Context, command: ProjectCommand) -> Result<(), CliError> {
+    let client = ctx.client()?;
+    let profile = ctx.profile()?;
+
+    match command {
+        ProjectCommand::List {
+            all,
+            page,
+            per_page,
+        } => {
+            let projects: Vec<ProjectResponse> = if all {
+                client.projects(per_page).try_collect().await?
+            } else {
+                client.list_projects(page, per_page).await?.data
+            };
+            print_list(ctx.output, &projects)
+        }
+        ProjectCommand::Create {
+            name,
+            slug,
+            organisation,
+            owner,
+            description,
+            public,
+        } => {
+            let organisation_id = organisation.or(profile.organisation).ok_or(CliError::MissingValue {
+                flag: "--organisation",
+                hint: "or set a default with `cplane profile set-organisation`",
+            })?;
+            let owner_id = owner.or(profile.identity).ok_or(CliError::MissingValue {
+                flag: "--owner",
+                hint: "the profile has no identity to default to",
+            })?;
+            let project = client
+                .create_project(&CreateProjectRequest {
+                    name,
+                    description,
+                    slug,
+                    organisation_id,
+                    owner_id,
+                    is_public: public,
+                })
+                .await?;
+            print_one(ctx.output, &project)
+        }
+        ProjectCommand::Get { id } => print_one(ctx.output, &client.get_project(id).await?),
+        ProjectCommand::Update {
+            id,
+            name,
+            description,
+            clear_description,
+            archived,
+        } => {
+            let description = if clear_description {
+                Some(None)
+            } else {
+                description.map(Some)
+            };
+            let project = client
+                .update_project(
+                    id,
+                    &UpdateProjectRequest {
+                        name,
+                        description,
+                        is_archived: archived,
+                    },
+                )
+                .await?;
+            print_one(ctx.output, &project)
+        }
+        ProjectCommand::Delete { id } => {
+            client.delete_project(id).await?;
+            eprintln!("Deleted project {}", id);
+            Ok(())
+        }
+    }
+}
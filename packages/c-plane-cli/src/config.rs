@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use c_plane_client::{Auth, Client};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::CliError;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Profiles stored in `~/.config/cplane/config.toml`, one per server and identity
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct ProfilesFile {
+    /// Used when `--profile` isn't given
+    pub current: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub url: String,
+    /// Identity token, checked by the gateway
+    pub token: Option<String>,
+    /// Identity sent as `X-User`, for a local server running without the gateway
+    pub identity: Option<Uuid>,
+    /// Organisation used when a command doesn't name one
+    pub organisation: Option<Uuid>,
+}
+
+impl Profile {
+    pub fn auth(&self) -> Option<Auth> {
+        if let Some(token) = &self.token {
+            Some(Auth::Jwt(token.clone()))
+        } else {
+            self.identity.map(Auth::Identity)
+        }
+    }
+
+    pub fn client(&self) -> Client {
+        let client = Client::new(&self.url);
+        match self.auth() {
+            Some(auth) => client.with_auth(auth),
+            None => client,
+        }
+    }
+}
+
+/// `CPLANE_CONFIG`, else `$XDG_CONFIG_HOME/cplane/config.toml`, else
+/// `~/.config/cplane/config.toml`
+pub fn default_path() -> PathBuf {
+    if let Ok(path) = env::var("CPLANE_CONFIG") {
+        return PathBuf::from(path);
+    }
+    let base = env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(env::var("HOME").unwrap_or_default()).join(".config"));
+    base.join("cplane").join("config.toml")
+}
+
+impl ProfilesFile {
+    /// A missing file is the same as one without profiles
+    pub fn load(path: &Path) -> Result<Self, CliError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(path)
+            .map_err(|e| CliError::Config(format!("could not read {}: {}", path.display(), e)))?;
+        toml::from_str(&contents)
+            .map_err(|e| CliError::Config(format!("{} is not valid: {}", path.display(), e)))
+    }
+
+    /// Written readable by the owner only since profiles hold credentials
+    pub fn save(&self, path: &Path) -> Result<(), CliError> {
+        let io_error = |e: std::io::Error| CliError::Config(format!("could not write {}: {}", path.display(), e));
+        let contents = toml::to_string_pretty(self).map_err(|e| CliError::Config(e.to_string()))?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(path).map_err(io_error)?;
+        file.write_all(contents.as_bytes()).map_err(io_error)
+    }
+
+    /// The profile named on the command line, else the current one, else `default`
+    pub fn selected_name(&self, requested: Option<&str>) -> String {
+        requested
+            .or(self.current.as_deref())
+            .unwrap_or(DEFAULT_PROFILE)
+            .to_string()
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Profile, CliError> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| CliError::UnknownProfile(name.to_string()))
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Result<&mut Profile, CliError> {
+        self.profiles
+            .get_mut(name)
+            .ok_or_else(|| CliError::UnknownProfile(name.to_string()))
+    }
+}
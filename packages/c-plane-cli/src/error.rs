@@ -0,0 +1,40 @@
+use std::fmt;
+
+use c_plane_client::ClientError;
+
+#[derive(Debug)]
+pub enum CliError {
+    /// The profiles file could not be read or written
+    Config(String),
+    UnknownProfile(String),
+    NotLoggedIn(String),
+    /// A value that can come from a flag or the profile was given by neither
+    MissingValue { flag: &'static str, hint: &'static str },
+    Client(ClientError),
+    Output(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Config(msg) => write!(f, "Config error: {}", msg),
+            CliError::UnknownProfile(name) => {
+                write!(f, "Profile '{}' does not exist, see `cplane profile list`", name)
+            }
+            CliError::NotLoggedIn(name) => {
+                write!(f, "Profile '{}' has no credentials, run `cplane login` first", name)
+            }
+            CliError::MissingValue { flag, hint } => write!(f, "{} is required, {}", flag, hint),
+            CliError::Client(err) => write!(f, "{}", err),
+            CliError::Output(msg) => write!(f, "Failed to write output: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<ClientError> for CliError {
+    fn from(err: ClientError) -> Self {
+        CliError::Client(err)
+    }
+}
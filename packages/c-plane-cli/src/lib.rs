@@ -0,0 +1,7 @@
+//! The `cplane` command line, as a library so its parsing and profiles can be tested
+
+pub mod cli;
+pub mod commands;
+pub mod config;
+pub mod error;
+pub mod output;
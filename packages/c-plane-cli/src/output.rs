@@ -0,0 +1,93 @@
+use std::io::{self, Write};
+
+use c_plane_client::types::{OrganisationResponse, ProjectResponse};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::error::CliError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns for reading
+    Table,
+    /// The API's JSON, for scripts
+    Json,
+}
+
+/// Something that can be printed as a table row
+pub trait Tabular {
+    const HEADERS: &'static [&'static str];
+
+    fn row(&self) -> Vec<String>;
+}
+
+pub fn print_list<T: Serialize + Tabular>(format: OutputFormat, items: &[T]) -> Result<(), CliError> {
+    match format {
+        OutputFormat::Json => print_json(&items),
+        OutputFormat::Table => {
+            let rows: Vec<Vec<String>> = items.iter().map(Tabular::row).collect();
+            print_table(T::HEADERS, &rows)
+        }
+    }
+}
+
+pub fn print_one<T: Serialize + Tabular>(format: OutputFormat, item: &T) -> Result<(), CliError> {
+    print_list(format, std::slice::from_ref(item))
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), CliError> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| CliError::Output(e.to_string()))?;
+    writeln!(io::stdout(), "{}", json).map_err(|e| CliError::Output(e.to_string()))
+}
+
+fn print_table(headers: &[&str], rows: &[Vec<String>]) -> Result<(), CliError> {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = io::stdout().lock();
+    let headers: Vec<String> = headers.iter().map(|h| h.to_uppercase()).collect();
+    for row in std::iter::once(&headers).chain(rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        writeln!(out, "{}", line.join("  ").trim_end()).map_err(|e| CliError::Output(e.to_string()))?;
+    }
+    Ok(())
+}
+
+fn or_dash(value: Option<&str>) -> String {
+    value.unwrap_or("-").to_string()
+}
+
+impl Tabular for OrganisationResponse {
+    const HEADERS: &'static [&'static str] = &["id", "name", "description", "created"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.name.clone(),
+            or_dash(self.description.as_deref()),
+            self.created_at.format("%Y-%m-%d %H:%M").to_string(),
+        ]
+    }
+}
+
+impl Tabular for ProjectResponse {
+    const HEADERS: &'static [&'static str] = &["id", "name", "organisation", "archived", "updated"];
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.name.clone(),
+            self.organisation_id.to_string(),
+            self.is_archived.to_string(),
+            self.updated_at.format("%Y-%m-%d %H:%M").to_string(),
+        ]
+    }
+}
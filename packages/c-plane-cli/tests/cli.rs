@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Command as Process, Output};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use c_plane_cli::cli::{Cli, Command, OrganisationCommand, ProjectCommand};
+use c_plane_cli::config::{Profile, ProfilesFile};
+use c_plane_cli::output::OutputFormat;
+use clap::Parser;
+use clap::error::ErrorKind;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+    Cli::try_parse_from(std::iter::once("cplane").chain(args.iter().copied()))
+}
+
+#[test]
+fn login_takes_a_token_or_an_identity() {
+    let identity = Uuid::new_v4();
+    let cli = parse(&["login", "--url", "http://api", "--identity", &identity.to_string()]).unwrap();
+    let Command::Login(args) = cli.command else {
+        panic!("parsed as {:?}", cli.command);
+    };
+    assert_eq!(args.identity, Some(identity));
+    assert_eq!(args.token, None);
+
+    let err = parse(&["login", "--url", "http://api"]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingRequiredArgument);
+    let err = parse(&["login", "--url", "http://api", "--api-key", "key"]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnknownArgument, "user routes take no API keys");
+}
+
+#[test]
+fn listing_flags_and_global_options_are_parsed() {
+    let cli = parse(&["orgs", "list", "--per-page", "5", "-o", "json", "--profile", "staging"]).unwrap();
+    assert_eq!(cli.output, OutputFormat::Json);
+    assert_eq!(cli.profile.as_deref(), Some("staging"));
+    assert!(matches!(
+        cli.command,
+        Command::Organisations(OrganisationCommand::List { page: 1, per_page: 5 })
+    ));
+
+    let cli = parse(&["projects", "list", "--all"]).unwrap();
+    assert!(matches!(cli.command, Command::Projects(ProjectCommand::List { all: true, .. })));
+
+    let err = parse(&["projects", "list", "--all", "--page", "2"]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ArgumentConflict);
+    let id = Uuid::new_v4().to_string();
+    let err = parse(&["projects", "update", &id, "--description", "x", "--clear-description"]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ArgumentConflict);
+}
+
+#[test]
+fn profiles_survive_a_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nested").join("config.toml");
+    assert_eq!(ProfilesFile::load(&path).unwrap(), ProfilesFile::default(), "a missing file has no profiles");
+
+    let profiles = ProfilesFile {
+        current: Some("local".to_string()),
+        profiles: BTreeMap::from([
+            (
+                "local".to_string(),
+                Profile {
+                    url: "http://localhost:8080".to_string(),
+                    token: None,
+                    identity: Some(Uuid::new_v4()),
+                    organisation: Some(Uuid::new_v4()),
+                },
+            ),
+            (
+                "prod".to_string(),
+                Profile {
+                    url: "https://api.example.com".to_string(),
+                    token: Some("secret".to_string()),
+                    identity: None,
+                    organisation: None,
+                },
+            ),
+        ]),
+    };
+    profiles.save(&path).unwrap();
+    let loaded = ProfilesFile::load(&path).unwrap();
+    assert_eq!(loaded, profiles);
+    assert_eq!(loaded.selected_name(None), "local");
+    assert_eq!(loaded.selected_name(Some("prod")), "prod");
+    assert!(loaded.get("missing").is_err());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600, "profiles hold credentials");
+    }
+
+    std::fs::write(&path, "current = [").unwrap();
+    assert!(ProfilesFile::load(&path).is_err());
+}
+
+/// Request line and `X-User` header of each request the stub served
+type Requests = Arc<Mutex<Vec<(String, Option<String>)>>>;
+
+/// Stands in for the API, answering every GET with an empty page of projects or a
+/// page with one organisation
+fn stub_server(organisation: Value) -> (String, Requests) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Requests::default();
+    let recorded = requests.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut stream = stream;
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut user = None;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("x-user")
+                {
+                    user = Some(value.trim().to_string());
+                }
+            }
+            let request_line = request_line.trim_end().to_string();
+            let data = if request_line.starts_with("GET /organisations") {
+                vec![organisation.clone()]
+            } else {
+                vec![]
+            };
+            let body = json!({
+                "data": data,
+                "pagination": { "per_page": 20, "has_next": false, "has_prev": false },
+            })
+            .to_string();
+            recorded.lock().unwrap().push((request_line, user));
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+    });
+    (url, requests)
+}
+
+fn cplane(config: &Path, args: &[&str]) -> Output {
+    let output = Process::new(env!("CARGO_BIN_EXE_cplane"))
+        .arg("--config")
+        .arg(config)
+        .args(args)
+        .env_remove("CPLANE_PROFILE")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    output
+}
+
+#[test]
+fn logs_in_and_lists_organisations_as_the_identity() {
+    let identity = Uuid::new_v4();
+    let organisation_id = Uuid::new_v4();
+    let (url, requests) = stub_server(json!({
+        "id": organisation_id,
+        "name": "Stub Co",
+        "description": null,
+        "avatar_url": null,
+        "is_active": true,
+        "created_at": "2026-01-02T03:04:05Z",
+        "updated_at": "2026-01-02T03:04:05Z",
+        "created_by": identity,
+    }));
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.toml");
+
+    cplane(&config, &["login", "--url", &url, "--identity", &identity.to_string()]);
+    let profiles = ProfilesFile::load(&config).unwrap();
+    assert_eq!(profiles.current.as_deref(), Some("default"));
+    assert_eq!(profiles.get("default").unwrap().identity, Some(identity));
+
+    let output = cplane(&config, &["organisations", "list", "-o", "json"]);
+    let listed: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(listed[0]["id"], json!(organisation_id));
+    assert_eq!(listed[0]["name"], "Stub Co");
+
+    let output = cplane(&config, &["orgs", "list"]);
+    let table = String::from_utf8(output.stdout).unwrap();
+    assert!(table.starts_with("ID"), "{}", table);
+    assert!(table.contains("Stub Co"), "{}", table);
+
+    let requests = requests.lock().unwrap();
+    let lines: Vec<&str> = requests.iter().map(|(line, _)| line.as_str()).collect();
+    assert_eq!(
+        lines,
+        [
+            "GET /projects?page=1&per_page=1 HTTP/1.1",
+            "GET /organisations?page=1&per_page=20 HTTP/1.1",
+            "GET /organisations?page=1&per_page=20 HTTP/1.1",
+        ]
+    );
+    for (_, user) in requests.iter() {
+        assert_eq!(user.as_deref(), Some(identity.to_string().as_str()));
+    }
+}
@@ -0,0 +1,425 @@
+use std::future::Future;
+use std::time::Duration;
+
+use futures_util::{Stream, TryStreamExt, stream};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+
+use crate::error::ClientError;
+use crate::types::{
+    CreateOrganisationRequest, CreateOrganisationResponse, CreateProjectRequest, ErrorResponse,
+    InvoiceResponse, MemberResponse, OrganisationResponse, OrganisationUsageResponse, PaginatedResponse,
+    ProjectResponse, SearchResponse, SubscriptionResponse, TransferProjectRequest,
+    UpdateProjectRequest, UsageReportRequest, UsageReportResponse,
+};
+
+/// How requests are authenticated
+#[derive(Debug, Clone)]
+pub enum Auth {
+    /// A user's identity token, checked by the gateway in front of the API
+    Jwt(String),
+    /// A service key sent as `X-API-KEY`: the Kratos hook key or, for
+    /// [`Client::report_usage`], the metering key. No user route accepts it, those
+    /// need `Jwt` or `Identity`.
+    ApiKey(String),
+    /// An identity id sent as `X-User`, only for trusted callers that talk to the
+    /// API directly behind the gateway
+    Identity(Uuid),
+}
+
+/// Retries for idempotent requests that failed to connect or hit a temporarily
+/// unavailable server. Creating requests are never retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Exponential backoff for the given retry, starting at 0
+    fn backoff(&self, retry: u32) -> Duration {
+        self.base_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    auth: Option<Auth>,
+    retry: RetryPolicy,
+}
+
+impl Client {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            auth: None,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Uses a preconfigured `reqwest` client, e.g. with custom timeouts or proxies
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// A page of the organisations the caller is a member of, newest first
+    pub async fn list_organisations(
+        &self,
+        page: u64,
+        per_page: u64,
+    ) -> Result<PaginatedResponse<OrganisationResponse>, ClientError> {
+        let response = self
+            .send(Method::GET, "/organisations", |req| {
+                req.query(&[("page", page), ("per_page", per_page)])
+            })
+            .await?;
+        decode(response).await
+    }
+
+    /// Only cloud servers create organisations, a self-hosted one answers 404
+    pub async fn create_organisation(
+        &self,
+        request: &CreateOrganisationRequest,
+    ) -> Result<CreateOrganisationResponse, ClientError> {
+        let response = self
+            .send(Method::POST, "/organisations/", |req| req.json(request))
+            .await?;
+        decode(response).await
+    }
+
+    pub async fn get_organisation(&self, id: Uuid) -> Result<OrganisationResponse, ClientError> {
+        let response = self
+            .send(Method::GET, &format!("/organisations/{}", id), |req| req)
+            .await?;
+        decode(response).await
+    }
+
+    /// A page of the organisation's active members, most recently joined first
+    pub async fn list_members(
+        &self,
+        id: Uuid,
+        page: u64,
+        per_page: u64,
+    ) -> Result<PaginatedResponse<MemberResponse>, ClientError> {
+        let response = self
+            .send(Method::GET, &format!("/organisations/{}/members", id), |req| {
+                req.query(&[("page", page), ("per_page", per_page)])
+            })
+            .await?;
+        decode(response).await
+    }
+
+    /// The organisation's plan and how much of it is used
+    pub async fn organisation_usage(&self, id: Uuid) -> Result<OrganisationUsageResponse, ClientError> {
+        let response = self
+            .send(Method::GET, &format!("/organisations/{}/usage", id), |req| req)
+            .await?;
+        decode(response).await
+    }
+
+    /// Deletes an organisation, which its owners can restore until the retention period ends
+    pub async fn delete_organisation(&self, id: Uuid) -> Result<(), ClientError> {
+        let response = self
+            .send(Method::DELETE, &format!("/organisations/{}", id), |req| req)
+            .await?;
+        check(response).await.map(|_| ())
+    }
+
+    pub async fn restore_organisation(&self, id: Uuid) -> Result<OrganisationResponse, ClientError> {
+        let response = self
+            .send(Method::POST, &format!("/organisations/{}/restore", id), |req| req)
+            .await?;
+        decode(response).await
+    }
+
+    /// Whether the organisation can deploy or is suspended over a failed payment. Only
+    /// served by cloud builds.
+    pub async fn subscription(&self, organisation_id: Uuid) -> Result<SubscriptionResponse, ClientError> {
+        let response = self
+            .send(Method::GET, &format!("/billing/{}/subscription", organisation_id), |req| req)
+            .await?;
+        decode(response).await
+    }
+
+    /// A page of the organisation's invoices, newest first. Only served by cloud builds.
+    pub async fn list_invoices(
+        &self,
+        organisation_id: Uuid,
+        page: u64,
+        per_page: u64,
+    ) -> Result<PaginatedResponse<InvoiceResponse>, ClientError> {
+        let response = self
+            .send(Method::GET, &format!("/billing/{}/invoices", organisation_id), |req| {
+                req.query(&[("page", page), ("per_page", per_page)])
+            })
+            .await?;
+        decode(response).await
+    }
+
+    /// Charges an unpaid invoice again, lifting a suspension once nothing is left unpaid
+    pub async fn pay_invoice(
+        &self,
+        organisation_id: Uuid,
+        invoice_id: Uuid,
+    ) -> Result<InvoiceResponse, ClientError> {
+        let path = format!("/billing/{}/invoices/{}/pay", organisation_id, invoice_id);
+        let response = self.send(Method::POST, &path, |req| req).await?;
+        decode(response).await
+    }
+
+    /// Sends usage measured by an agent, authenticated with the metering API key
+    pub async fn report_usage(
+        &self,
+        request: &UsageReportRequest,
+    ) -> Result<UsageReportResponse, ClientError> {
+        let response = self
+            .send(Method::POST, "/metering/usage", |req| req.json(request))
+            .await?;
+        decode(response).await
+    }
+
+    /// A single page of the caller's projects, see [`Client::projects`] to walk all of them
+    pub async fn list_projects(
+        &self,
+        page: u64,
+        per_page: u64,
+    ) -> Result<PaginatedResponse<ProjectResponse>, ClientError> {
+        let response = self
+            .send(Method::GET, "/projects", |req| {
+                req.query(&[("page", page), ("per_page", per_page)])
+            })
+            .await?;
+        decode(response).await
+    }
+
+    /// The page of projects after `cursor`, a `next_cursor` from an earlier page
+    pub async fn list_projects_after(
+        &self,
+        cursor: &str,
+        per_page: u64,
+    ) -> Result<PaginatedResponse<ProjectResponse>, ClientError> {
+        let response = self
+            .send(Method::GET, "/projects", |req| {
+                req.query(&[("after", cursor)]).query(&[("per_page", per_page)])
+            })
+            .await?;
+        decode(response).await
+    }
+
+    /// Every project of the caller, fetching further pages as the stream is consumed
+    pub fn projects(
+        &self,
+        per_page: u64,
+    ) -> impl Stream<Item = Result<ProjectResponse, ClientError>> + '_ {
+        paginate(move |cursor| async move {
+            match cursor {
+                Some(cursor) => self.list_projects_after(&cursor, per_page).await,
+                None => self.list_projects(1, per_page).await,
+            }
+        })
+    }
+
+    pub async fn create_project(
+        &self,
+        request: &CreateProjectRequest,
+    ) -> Result<ProjectResponse, ClientError> {
+        let response = self
+            .send(Method::POST, "/projects", |req| req.json(request))
+            .await?;
+        decode(response).await
+    }
+
+    pub async fn get_project(&self, id: Uuid) -> Result<ProjectResponse, ClientError> {
+        let response = self
+            .send(Method::GET, &format!("/projects/{}", id), |req| req)
+            .await?;
+        decode(response).await
+    }
+
+    pub async fn update_project(
+        &self,
+        id: Uuid,
+        request: &UpdateProjectRequest,
+    ) -> Result<ProjectResponse, ClientError> {
+        let response = self
+            .send(Method::PUT, &format!("/projects/{}", id), |req| req.json(request))
+            .await?;
+        decode(response).await
+    }
+
+    pub async fn delete_project(&self, id: Uuid) -> Result<(), ClientError> {
+        let response = self
+            .send(Method::DELETE, &format!("/projects/{}", id), |req| req)
+            .await?;
+        check(response).await.map(|_| ())
+    }
+
+    /// Moves a project to another organisation, which takes Admin or Owner in both
+    pub async fn transfer_project(
+        &self,
+        id: Uuid,
+        request: &TransferProjectRequest,
+    ) -> Result<ProjectResponse, ClientError> {
+        let response = self
+            .send(Method::POST, &format!("/projects/{}/transfer", id), |req| req.json(request))
+            .await?;
+        decode(response).await
+    }
+
+    pub async fn restore_project(&self, id: Uuid) -> Result<ProjectResponse, ClientError> {
+        let response = self
+            .send(Method::POST, &format!("/projects/{}/restore", id), |req| req)
+            .await?;
+        decode(response).await
+    }
+
+    /// Organisations and projects of the caller matching `q`, each word also matching as
+    /// a prefix
+    pub async fn search(&self, q: &str, limit: u64) -> Result<SearchResponse, ClientError> {
+        let response = self
+            .send(Method::GET, "/search", |req| {
+                req.query(&[("q", q)]).query(&[("limit", limit)])
+            })
+            .await?;
+        decode(response).await
+    }
+
+    /// Sends the request built by `build`, retrying idempotent methods per the retry
+    /// policy. Error statuses are returned as responses for the caller to decode.
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        build: impl Fn(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Response, ClientError> {
+        let url = format!("{}{}", self.base_url, path);
+        let idempotent = matches!(method, Method::GET | Method::PUT | Method::DELETE);
+        let mut retry = 0;
+
+        loop {
+            let request = build(self.authenticate(self.http.request(method.clone(), &url)));
+            let retries_left = idempotent && retry < self.retry.max_retries;
+
+            let delay = match request.send().await {
+                Ok(response) if retries_left && is_transient(response.status()) => {
+                    retry_after(&response).unwrap_or_else(|| self.retry.backoff(retry))
+                }
+                Ok(response) => return Ok(response),
+                Err(err) if retries_left && (err.is_connect() || err.is_timeout()) => {
+                    self.retry.backoff(retry)
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            tokio::time::sleep(delay.min(self.retry.max_backoff)).await;
+            retry += 1;
+        }
+    }
+
+    fn authenticate(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.auth {
+            Some(Auth::Jwt(token)) => request.bearer_auth(token),
+            Some(Auth::ApiKey(key)) => request.header("X-API-KEY", key),
+            Some(Auth::Identity(id)) => request.header("X-User", id.to_string()),
+            None => request,
+        }
+    }
+}
+
+/// Statuses worth retrying because the server may recover on its own
+fn is_transient(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// The server's `Retry-After` hint, in seconds
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// Turns error statuses into `ClientError`s, decoding the body as `ErrorResponse`
+/// when possible
+async fn check(response: Response) -> Result<Response, ClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await?;
+    match serde_json::from_str::<ErrorResponse>(&body) {
+        Ok(error) => Err(ClientError::Api {
+            status: status.as_u16(),
+            error: Box::new(error),
+        }),
+        Err(_) => Err(ClientError::UnexpectedResponse {
+            status: status.as_u16(),
+            body,
+        }),
+    }
+}
+
+async fn decode<T: DeserializeOwned>(response: Response) -> Result<T, ClientError> {
+    let bytes = check(response).await?.bytes().await?;
+    serde_json::from_slice(&bytes).map_err(|e| ClientError::Decode(e.to_string()))
+}
+
+/// Flattens the pages returned by `fetch`, starting with the first page (`None`) and
+/// following `next_cursor` until a page has none
+fn paginate<T, F, Fut>(fetch: F) -> impl Stream<Item = Result<T, ClientError>>
+where
+    F: Fn(Option<String>) -> Fut,
+    Fut: Future<Output = Result<PaginatedResponse<T>, ClientError>>,
+{
+    stream::try_unfold((fetch, Some(None)), |(fetch, cursor)| async move {
+        let Some(cursor) = cursor else {
+            return Ok::<_, ClientError>(None);
+        };
+        let response = fetch(cursor).await?;
+        let next = response.pagination.next_cursor.map(Some);
+        let items = stream::iter(response.data.into_iter().map(Ok));
+        Ok(Some((items, (fetch, next))))
+    })
+    .try_flatten()
+}
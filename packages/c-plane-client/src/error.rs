@@ -0,0 +1,62 @@
+use std::fmt;
+
+use crate::types::ErrorResponse;
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The server rejected the request with its usual error body
+    Api { status: u16, error: Box<ErrorResponse> },
+    /// An error status whose body isn't an `ErrorResponse`, e.g. from a proxy
+    UnexpectedResponse { status: u16, body: String },
+    /// A successful response whose body doesn't match the expected type
+    Decode(String),
+    /// The request never got a response
+    Http(reqwest::Error),
+}
+
+impl ClientError {
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ClientError::Api { status, .. } | ClientError::UnexpectedResponse { status, .. } => {
+                Some(*status)
+            }
+            ClientError::Decode(_) => None,
+            ClientError::Http(err) => err.status().map(|status| status.as_u16()),
+        }
+    }
+
+    /// The server's stable error code, e.g. `project_not_found`
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            ClientError::Api { error, .. } => Some(&error.code),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Api { status, error } => {
+                write!(f, "{} ({}): {}", error.code, status, error.detail)?;
+                if let Some(request_id) = &error.request_id {
+                    write!(f, " [request {}]", request_id)?;
+                }
+                Ok(())
+            }
+            ClientError::UnexpectedResponse { status, body } => {
+                write!(f, "Unexpected response ({}): {}", status, body)
+            }
+            ClientError::Decode(msg) => write!(f, "Failed to decode response: {}", msg),
+            ClientError::Http(err) => write!(f, "Request failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(err: reqwest::Error) -> Self {
+        ClientError::Http(err)
+    }
+}
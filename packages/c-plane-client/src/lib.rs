@@ -0,0 +1,8 @@
+//! Async client for the control plane API
+
+mod client;
+mod error;
+pub mod types;
+
+pub use client::{Auth, Client, RetryPolicy};
+pub use error::ClientError;
@@ -0,0 +1,393 @@
+//! Request and response bodies of the API, shared with the server so both sides
+//! always agree on the wire format
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use uuid::Uuid;
+
+/// Body of every error response, an RFC 7807 problem document served as
+/// `application/problem+json`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorResponse {
+    /// URI identifying the problem type, `urn:c-plane:problem:{code}`
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// Summary of the status, the same for every occurrence
+    pub title: String,
+    pub status: u16,
+    /// Explanation of this occurrence
+    pub detail: String,
+    /// Identifies this occurrence, `urn:c-plane:request:{request_id}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    /// Stable machine readable code, the part of the response to match on:
+    ///
+    /// - 400 `malformed_json`
+    /// - 401 `unauthorized`
+    /// - 402 `subscription_suspended`
+    /// - 403 `forbidden`, `not_a_member`, `insufficient_role`, `insufficient_permissions`,
+    ///   `account_deactivated`, `project_limit_exceeded`, `member_limit_exceeded`
+    /// - 404 `not_found`, `project_not_found`, `organisation_not_found`, `user_not_found`
+    /// - 409 `conflict`, `slug_already_exists`, `email_already_exists`, `project_archived`,
+    ///   `cannot_archive_public_project`, `owner_cannot_leave_project`,
+    ///   `cannot_remove_last_owner`, `cannot_delete_instance_organisation`
+    /// - 413 `payload_too_large`
+    /// - 415 `unsupported_media_type`
+    /// - 422 `validation_failed`, `invalid_body`, `invalid_slug`
+    /// - 429 `rate_limited`
+    /// - 500 `internal_error`, `configuration_error`
+    /// - 502 `upstream_error`
+    /// - 503 `timeout`, `upstream_unavailable`
+    /// - 504 `upstream_timeout`
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// The failing fields of `validation_failed`, each with `field`, `code` and `message`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub errors: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PaginatedResponse<T> {
+    pub data: Vec<T>,
+    pub pagination: PaginationMeta,
+}
+
+/// Where a page sits in the listing. Pages requested by number carry the totals,
+/// pages requested with a cursor skip counting and only carry the cursors.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PaginationMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u64>,
+    pub per_page: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_pages: Option<u64>,
+    pub has_next: bool,
+    pub has_prev: bool,
+    /// Pass as `after` to get the next page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// Pass as `before` to get the previous page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_cursor: Option<String>,
+}
+
+impl<T> PaginatedResponse<T> {
+    /// A page requested by number, out of `total` items
+    pub fn new(data: Vec<T>, total: u64, page: u64, per_page: u64) -> Self {
+        let total_pages = if total == 0 {
+            1
+        } else {
+            total.div_ceil(per_page)
+        };
+
+        Self {
+            data,
+            pagination: PaginationMeta {
+                total: Some(total),
+                page: Some(page),
+                per_page,
+                total_pages: Some(total_pages),
+                has_next: page < total_pages,
+                has_prev: page > 1,
+                next_cursor: None,
+                prev_cursor: None,
+            },
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> PaginatedResponse<U> {
+        PaginatedResponse {
+            data: self.data.into_iter().map(f).collect(),
+            pagination: self.pagination,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum OrganisationRole {
+    Owner,
+    Admin,
+    Member,
+    Viewer,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrganisationResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub avatar_url: Option<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrganisationMemberResponse {
+    pub id: Uuid,
+    pub organisation_id: Uuid,
+    pub role: OrganisationRole,
+    pub is_active: bool,
+    pub joined_at: DateTime<Utc>,
+    pub invitation_accepted_at: DateTime<Utc>,
+}
+
+/// An active member with the name and email of their identity, left out while
+/// Kratos can't be reached
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MemberResponse {
+    pub identity_id: Uuid,
+    pub role: OrganisationRole,
+    pub joined_at: DateTime<Utc>,
+    pub name: Option<String>,
+    pub email: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Plan {
+    Free,
+    Team,
+    Enterprise,
+    /// Limits agreed for one organisation
+    Custom,
+}
+
+/// What an organisation may hold at most, `null` meaning no limit
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PlanLimits {
+    pub projects: Option<u32>,
+    pub members: Option<u32>,
+    pub deployments: Option<u32>,
+    pub agents: Option<u32>,
+    pub api_keys: Option<u32>,
+    pub log_retention_days: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ResourceUsage {
+    pub used: u64,
+    /// `null` when the plan has no limit
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrganisationUsageResponse {
+    pub plan: Plan,
+    pub limits: PlanLimits,
+    /// Deleted projects don't count
+    pub projects: ResourceUsage,
+    pub members: ResourceUsage,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateOrganisationRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateOrganisationResponse {
+    pub organisation: OrganisationResponse,
+    pub organisation_member: OrganisationMemberResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateProjectRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub slug: String,
+    pub organisation_id: Uuid,
+    pub owner_id: Uuid,
+    pub is_public: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UpdateProjectRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Omit to keep the description, `null` clears it
+    #[serde(default, deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<String>))]
+    pub description: Option<Option<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_archived: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TransferProjectRequest {
+    /// The organisation to move the project to
+    pub organisation_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ProjectResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub organisation_id: Uuid,
+    pub owner_id: Uuid,
+    /// Archived projects are read-only until they are unarchived
+    pub is_archived: bool,
+    pub is_public: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SearchResultType {
+    Organisation,
+    Project,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchResult {
+    #[serde(rename = "type")]
+    pub result_type: SearchResultType,
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    /// The organisation itself for organisation results
+    pub organisation_id: Uuid,
+    /// Higher is more relevant, only comparable within one response
+    pub rank: f32,
+}
+
+/// Best matches first
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchResponse {
+    pub results: Vec<SearchResult>,
+}
+
+/// Tells an explicit `null` (`Some(None)`) apart from a missing field (`None`)
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// What a usage record measures. Runtime and CPU time are billed by the hour, storage
+/// by the GB-month.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum UsageMetric {
+    /// Seconds the deployment was running
+    RuntimeSeconds,
+    /// CPU time the deployment used, in seconds
+    CpuSeconds,
+    /// Storage held, in GB times the hours it was held
+    StorageGbHours,
+}
+
+/// Usage of one deployment since the agent's previous record for it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UsageRecordRequest {
+    /// Chosen by the agent, a record resent with the same id is only counted once
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub deployment_id: Uuid,
+    pub metric: UsageMetric,
+    pub quantity: i64,
+    /// When the usage happened, which decides the month it is billed in
+    pub recorded_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UsageReportRequest {
+    pub records: Vec<UsageRecordRequest>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UsageReportResponse {
+    /// Records that hadn't been reported before
+    pub accepted: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    Active,
+    /// A payment failed, deploys are refused until the invoice is paid
+    Suspended,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SubscriptionResponse {
+    pub status: SubscriptionStatus,
+    pub suspended_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    /// Not charged yet
+    Open,
+    /// A charge is in progress
+    Processing,
+    Paid,
+    /// The charge failed, see `failure_reason`
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct InvoiceLineResponse {
+    pub metric: UsageMetric,
+    /// Total reported for the period, in the metric's unit
+    pub quantity: i64,
+    /// Price of one billed unit
+    pub unit_price_cents: i64,
+    pub amount_cents: i64,
+}
+
+/// A month of usage, billed on the first of the following month
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct InvoiceResponse {
+    pub id: Uuid,
+    pub organisation_id: Uuid,
+    pub period_start: DateTime<Utc>,
+    /// Exclusive
+    pub period_end: DateTime<Utc>,
+    pub currency: String,
+    pub total_cents: i64,
+    pub status: InvoiceStatus,
+    pub failure_reason: Option<String>,
+    pub lines: Vec<InvoiceLineResponse>,
+    pub created_at: DateTime<Utc>,
+}
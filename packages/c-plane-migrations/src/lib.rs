@@ -1,6 +1,19 @@
 pub use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DatabaseBackend;
 
+// Shipped migrations are left exactly as they were
+#[allow(unused_imports)]
 mod m20250921_134502_organisation_organisation_member_projects;
+mod m20261017_090000_sqlite_initial_schema;
+mod m20261018_090000_jobs;
+mod m20261019_090000_rename_organisation_role_type;
+mod m20261020_090000_project_list_indexes;
+mod m20261021_090000_search_vectors;
+mod m20261022_090000_soft_delete;
+mod m20261023_090000_project_visibility;
+mod m20261024_090000_organisation_plans;
+mod m20261025_090000_billing;
+mod m20261026_090000_audit_log;
 
 pub struct Migrator;
 
@@ -8,7 +21,45 @@ pub struct Migrator;
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
         vec![
-            Box::new(m20250921_134502_organisation_organisation_member_projects::Migration),
+            Box::new(PostgresOnly(m20250921_134502_organisation_organisation_member_projects::Migration)),
+            Box::new(m20261017_090000_sqlite_initial_schema::Migration),
+            Box::new(m20261018_090000_jobs::Migration),
+            Box::new(m20261019_090000_rename_organisation_role_type::Migration),
+            Box::new(m20261020_090000_project_list_indexes::Migration),
+            Box::new(m20261021_090000_search_vectors::Migration),
+            Box::new(m20261022_090000_soft_delete::Migration),
+            Box::new(m20261023_090000_project_visibility::Migration),
+            Box::new(m20261024_090000_organisation_plans::Migration),
+            Box::new(m20261025_090000_billing::Migration),
+            Box::new(m20261026_090000_audit_log::Migration),
         ]
     }
 }
+
+/// Runs a migration written before SQLite was supported on Postgres only, and
+/// records it as applied elsewhere. Shipped migrations stay as they were; a later
+/// migration creates the SQLite counterpart.
+struct PostgresOnly<M>(M);
+
+impl<M: MigrationName> MigrationName for PostgresOnly<M> {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+}
+
+#[async_trait::async_trait]
+impl<M: MigrationTrait> MigrationTrait for PostgresOnly<M> {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DatabaseBackend::Postgres {
+            return Ok(());
+        }
+        self.0.up(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DatabaseBackend::Postgres {
+            return Ok(());
+        }
+        self.0.down(manager).await
+    }
+}
@@ -0,0 +1,118 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DatabaseBackend;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Only Postgres has gen_random_uuid(), the application always sets ids anyway
+        let id_default = match manager.get_database_backend() {
+            DatabaseBackend::Postgres => "DEFAULT gen_random_uuid()",
+            _ => "",
+        };
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Job::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Job::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .extra(id_default.to_owned()),
+                    )
+                    .col(ColumnDef::new(Job::Kind).string().not_null())
+                    .col(ColumnDef::new(Job::Payload).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(Job::Status)
+                            .string_len(16)
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .col(ColumnDef::new(Job::Attempts).integer().not_null().default(0))
+                    .col(ColumnDef::new(Job::MaxAttempts).integer().not_null())
+                    .col(ColumnDef::new(Job::UniqueKey).string())
+                    .col(ColumnDef::new(Job::LastError).text())
+                    .col(
+                        ColumnDef::new(Job::RunAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(Job::LockedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(Job::LockedBy).string())
+                    .col(
+                        ColumnDef::new(Job::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(Job::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Workers poll on (status, run_at); keep that lookup cheap as the table grows.
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_jobs_status_run_at")
+                    .table(Job::Table)
+                    .col(Job::Status)
+                    .col(Job::RunAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // A unique key only has to be unique among jobs that have not finished yet,
+        // so the same key can be enqueued again once the previous job is done.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                CREATE UNIQUE INDEX idx_jobs_unique_key_active
+                    ON jobs (unique_key)
+                    WHERE status IN ('pending', 'running');
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Job::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Job {
+    #[sea_orm(iden = "jobs")]
+    Table,
+    Id,
+    Kind,
+    Payload,
+    Status,
+    Attempts,
+    MaxAttempts,
+    UniqueKey,
+    LastError,
+    RunAt,
+    LockedAt,
+    LockedBy,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DatabaseBackend;
+
+/// The initial migration creates the role type under the name `enum`, which the
+/// `organisation_role` entity enum can't find. Backends without enum types are
+/// left untouched.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DatabaseBackend::Postgres {
+            return Ok(());
+        }
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                DO $$
+                BEGIN
+                    IF EXISTS (SELECT 1 FROM pg_type WHERE typname = 'enum')
+                        AND NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'organisation_role')
+                    THEN
+                        ALTER TYPE "enum" RENAME TO organisation_role;
+                    END IF;
+                END
+                $$;
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
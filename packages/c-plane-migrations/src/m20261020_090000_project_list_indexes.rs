@@ -0,0 +1,54 @@
+use sea_orm_migration::prelude::*;
+
+/// Projects are listed per organisation and sorted by the columns the list endpoint
+/// accepts in `sort`, each of which gets an index here
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const INDEXES: [(&str, Project); 3] = [
+    ("idx_project_organisation_created_at", Project::CreatedAt),
+    ("idx_project_organisation_updated_at", Project::UpdatedAt),
+    ("idx_project_organisation_name", Project::Name),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (name, column) in INDEXES {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(Project::Table)
+                        .col(Project::OrganisationId)
+                        .col(column)
+                        .col(Project::Id)
+                        .if_not_exists()
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (name, _) in INDEXES {
+            manager
+                .drop_index(Index::drop().name(name).table(Project::Table).to_owned())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum Project {
+    Table,
+    Id,
+    Name,
+    OrganisationId,
+    CreatedAt,
+    UpdatedAt,
+}
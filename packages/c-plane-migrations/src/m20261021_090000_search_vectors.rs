@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DatabaseBackend;
+
+/// Full-text search columns for `/search`, generated from the name (weighted higher)
+/// and the description so they never go stale. The `simple` configuration doesn't
+/// stem, which suits names and prefix matching. Other backends search with `LIKE`
+/// instead and get no columns.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const TABLES: [&str; 2] = ["organisation", "project"];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DatabaseBackend::Postgres {
+            return Ok(());
+        }
+
+        for table in TABLES {
+            manager
+                .get_connection()
+                .execute_unprepared(&format!(
+                    r#"
+                    ALTER TABLE {table} ADD COLUMN IF NOT EXISTS search_vector tsvector
+                        GENERATED ALWAYS AS (
+                            setweight(to_tsvector('simple', coalesce(name, '')), 'A')
+                            || setweight(to_tsvector('simple', coalesce(description, '')), 'B')
+                        ) STORED;
+                    CREATE INDEX IF NOT EXISTS idx_{table}_search_vector
+                        ON {table} USING GIN (search_vector);
+                    "#
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DatabaseBackend::Postgres {
+            return Ok(());
+        }
+
+        for table in TABLES {
+            manager
+                .get_connection()
+                .execute_unprepared(&format!(
+                    "ALTER TABLE {table} DROP COLUMN IF EXISTS search_vector;"
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+}
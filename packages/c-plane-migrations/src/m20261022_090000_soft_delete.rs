@@ -0,0 +1,78 @@
+use sea_orm_migration::prelude::*;
+
+/// Deleted organisations and projects stay in place with `deleted_at` and `deleted_by`
+/// set until the purge job removes them after the retention period. The index serves
+/// that job, normal queries filter on the column within an organisation.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const TABLES: [(Tables, &str); 2] = [
+    (Tables::Organisation, "idx_organisation_deleted_at"),
+    (Tables::Project, "idx_project_deleted_at"),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (table, index) in TABLES {
+            // SQLite only takes one column per ALTER TABLE
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .add_column_if_not_exists(
+                            ColumnDef::new(Columns::DeletedAt).timestamp_with_time_zone(),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .add_column_if_not_exists(ColumnDef::new(Columns::DeletedBy).uuid())
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .create_index(
+                    Index::create()
+                        .name(index)
+                        .table(table)
+                        .col(Columns::DeletedAt)
+                        .if_not_exists()
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (table, index) in TABLES {
+            manager
+                .drop_index(Index::drop().name(index).table(table).to_owned())
+                .await?;
+            for column in [Columns::DeletedAt, Columns::DeletedBy] {
+                manager
+                    .alter_table(Table::alter().table(table).drop_column(column).to_owned())
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum Tables {
+    Organisation,
+    Project,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum Columns {
+    DeletedAt,
+    DeletedBy,
+}
@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+/// Stores whether a project is public, which `CreateProjectRequest` has always asked
+/// for. Public projects can't be archived.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Project::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Project::IsPublic).boolean().not_null().default(false),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(Table::alter().table(Project::Table).drop_column(Project::IsPublic).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    IsPublic,
+}
@@ -0,0 +1,50 @@
+use sea_orm_migration::prelude::*;
+
+/// The plan each organisation is on, which sets its quotas. Organisations on the
+/// `custom` plan carry their own limits as JSON.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only takes one column per ALTER TABLE
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Organisation::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(Organisation::Plan)
+                            .string_len(16)
+                            .not_null()
+                            .default("free"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Organisation::Table)
+                    .add_column_if_not_exists(ColumnDef::new(Organisation::CustomLimits).json_binary())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Organisation::Plan, Organisation::CustomLimits] {
+            manager
+                .alter_table(Table::alter().table(Organisation::Table).drop_column(column).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Organisation {
+    Table,
+    Plan,
+    CustomLimits,
+}
@@ -0,0 +1,257 @@
+use sea_orm_migration::prelude::*;
+
+/// Metered usage reported by agents, the monthly invoices built from it and the
+/// subscription status that payments keep active. Only the `cloud` build uses these
+/// tables, but they are created everywhere so a database can move between builds.
+///
+/// Invoices and usage records are financial history and outlive the organisation they
+/// belong to: purging it clears their `organisation_id` instead of deleting them.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Subscription::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Subscription::OrganisationId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Subscription::Status)
+                            .string_len(16)
+                            .not_null()
+                            .default("active"),
+                    )
+                    .col(ColumnDef::new(Subscription::SuspendedAt).timestamp_with_time_zone())
+                    .col(
+                        ColumnDef::new(Subscription::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_subscription_organisation")
+                            .from(Subscription::Table, Subscription::OrganisationId)
+                            .to(Organisation::Table, Organisation::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Projects are left without a foreign key, usage stays billable after the
+        // project it ran in is purged
+        manager
+            .create_table(
+                Table::create()
+                    .table(UsageRecord::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(UsageRecord::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(UsageRecord::OrganisationId).uuid().null())
+                    .col(ColumnDef::new(UsageRecord::ProjectId).uuid().not_null())
+                    .col(ColumnDef::new(UsageRecord::DeploymentId).uuid().not_null())
+                    .col(ColumnDef::new(UsageRecord::Metric).string_len(32).not_null())
+                    .col(ColumnDef::new(UsageRecord::Quantity).big_integer().not_null())
+                    .col(
+                        ColumnDef::new(UsageRecord::RecordedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UsageRecord::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_usage_record_organisation")
+                            .from(UsageRecord::Table, UsageRecord::OrganisationId)
+                            .to(Organisation::Table, Organisation::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The monthly aggregation sums one period across all organisations
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_usage_record_recorded_at")
+                    .table(UsageRecord::Table)
+                    .col(UsageRecord::RecordedAt)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Invoice::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Invoice::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(Invoice::OrganisationId).uuid().null())
+                    .col(
+                        ColumnDef::new(Invoice::PeriodStart)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Invoice::PeriodEnd)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Invoice::Currency).string_len(3).not_null())
+                    .col(ColumnDef::new(Invoice::TotalCents).big_integer().not_null())
+                    .col(ColumnDef::new(Invoice::Status).string_len(16).not_null())
+                    .col(ColumnDef::new(Invoice::PaymentReference).string())
+                    .col(ColumnDef::new(Invoice::FailureReason).text())
+                    .col(
+                        ColumnDef::new(Invoice::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(Invoice::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_invoice_organisation")
+                            .from(Invoice::Table, Invoice::OrganisationId)
+                            .to(Organisation::Table, Organisation::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // One invoice per organisation and period, so a rerun of the aggregation
+        // can't bill twice
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_invoice_organisation_period")
+                    .table(Invoice::Table)
+                    .col(Invoice::OrganisationId)
+                    .col(Invoice::PeriodStart)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(InvoiceLine::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(InvoiceLine::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(InvoiceLine::InvoiceId).uuid().not_null())
+                    .col(ColumnDef::new(InvoiceLine::Metric).string_len(32).not_null())
+                    .col(ColumnDef::new(InvoiceLine::Quantity).big_integer().not_null())
+                    .col(ColumnDef::new(InvoiceLine::UnitPriceCents).big_integer().not_null())
+                    .col(ColumnDef::new(InvoiceLine::AmountCents).big_integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_invoice_line_invoice")
+                            .from(InvoiceLine::Table, InvoiceLine::InvoiceId)
+                            .to(Invoice::Table, Invoice::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(InvoiceLine::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Invoice::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(UsageRecord::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Subscription::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Organisation {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Subscription {
+    Table,
+    OrganisationId,
+    Status,
+    SuspendedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum UsageRecord {
+    Table,
+    Id,
+    OrganisationId,
+    ProjectId,
+    DeploymentId,
+    Metric,
+    Quantity,
+    RecordedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Invoice {
+    Table,
+    Id,
+    OrganisationId,
+    PeriodStart,
+    PeriodEnd,
+    Currency,
+    TotalCents,
+    Status,
+    PaymentReference,
+    FailureReason,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum InvoiceLine {
+    Table,
+    Id,
+    InvoiceId,
+    Metric,
+    Quantity,
+    UnitPriceCents,
+    AmountCents,
+}
@@ -0,0 +1,82 @@
+use sea_orm_migration::prelude::*;
+
+/// Who changed what in an organisation. An entry belongs to one organisation, so an
+/// action touching two of them, such as a project transfer, leaves one entry in each.
+/// Projects are left without a foreign key, the history outlives a purged project.
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLog::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(AuditLog::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(AuditLog::OrganisationId).uuid().not_null())
+                    .col(ColumnDef::new(AuditLog::ActorId).uuid().not_null())
+                    .col(ColumnDef::new(AuditLog::Action).string_len(32).not_null())
+                    .col(ColumnDef::new(AuditLog::ProjectId).uuid())
+                    .col(ColumnDef::new(AuditLog::Details).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(AuditLog::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_audit_log_organisation")
+                            .from(AuditLog::Table, AuditLog::OrganisationId)
+                            .to(Organisation::Table, Organisation::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // An organisation's history is read newest first
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_log_organisation_created_at")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::OrganisationId)
+                    .col(AuditLog::CreatedAt)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLog::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Organisation {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum AuditLog {
+    Table,
+    Id,
+    OrganisationId,
+    ActorId,
+    Action,
+    ProjectId,
+    Details,
+    CreatedAt,
+}
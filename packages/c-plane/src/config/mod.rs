@@ -1,43 +1,508 @@
+mod source;
+#[cfg(test)]
+mod tests;
+
 use std::env;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+use subtle::ConstantTimeEq;
+#[cfg(not(feature = "cloud"))]
+use uuid::Uuid;
 
 use crate::errors::{AppError, ConfigError};
+use crate::utils::logger::LogFormat;
+use source::Source;
+
+/// Config file read when neither `--config` nor `CONFIG_FILE` is given, if it exists
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
 
-#[derive(Clone)]
+/// Settings loaded from an optional TOML file whose tables mirror these sections,
+/// with environment variables taking precedence
+#[derive(Clone, Serialize)]
 pub struct Config {
-    pub database_url: String,
-    pub server_host: String,
-    pub server_port: u16,
-    pub kratos_api_key: String,
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub smtp: Option<SmtpConfig>,
+    pub valkey: Option<ValkeyConfig>,
+    pub jobs: JobsConfig,
+    pub rate_limit: RateLimitConfig,
+    pub cache: CacheConfig,
+    pub retention: RetentionConfig,
+    pub telemetry: TelemetryConfig,
+    pub features: FeaturesConfig,
+    #[cfg(feature = "cloud")]
+    pub billing: BillingConfig,
+    #[cfg(not(feature = "cloud"))]
+    pub instance: InstanceConfig,
 }
 
-pub fn load_config() -> Result<Config, AppError> {
-    dotenvy::dotenv().ok();
+impl Config {
+    /// The effective config as `--print-config` shows it, with secrets redacted
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string_pretty(self)
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    /// Origins allowed to call the API from a browser, CORS is disabled when empty
+    pub cors_allowed_origins: Vec<String>,
+    pub request_timeout_secs: u64,
+    pub shutdown_timeout_secs: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct DatabaseConfig {
+    /// `postgres://` or `sqlite://`, the scheme selects the backend
+    #[serde(serialize_with = "serialize_url")]
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub connect_timeout_secs: u64,
+    pub acquire_timeout_secs: u64,
+    pub idle_timeout_secs: u64,
+    /// Postgres `statement_timeout` for every pooled connection, 0 disables it.
+    /// Ignored on SQLite.
+    pub statement_timeout_ms: u64,
+    /// Statements running longer than this are logged as warnings, 0 disables it
+    pub slow_statement_threshold_ms: u64,
+    /// Log every statement through sqlx in addition to the timing callback
+    pub log_statements: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct AuthConfig {
+    /// Shared with Kratos to authenticate its webhooks
+    pub kratos_api_key: Secret,
+    pub kratos_admin_url: String,
+    /// Where the keys that sign forwarded identity tokens are published
+    pub jwks_url: Option<String>,
+}
 
-    let database_url = env::var("DATABASE_URL").map_err(|_| ConfigError::MissingDatabaseUrl)?;
-    if database_url.trim().is_empty() {
-        return Err(AppError::Config(ConfigError::MissingDatabaseUrl));
+#[derive(Clone, Serialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    pub from_address: String,
+}
+
+/// Shared state across instances, kept in memory per instance without it
+#[derive(Clone, Serialize)]
+pub struct ValkeyConfig {
+    /// `redis://` or `valkey://`, or `rediss://` and `valkeys://` over TLS, with
+    /// optional percent-encoded credentials and database number
+    #[serde(serialize_with = "serialize_url")]
+    pub url: String,
+    /// Commands taking longer are abandoned
+    pub timeout_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct JobsConfig {
+    pub workers: usize,
+}
+
+/// Token buckets per client IP, and per user or known API key on top. A bucket holds
+/// a minute's budget and refills continuously.
+#[derive(Clone, Serialize)]
+pub struct RateLimitConfig {
+    pub requests_per_minute: u32,
+    /// Budget of the routes under `expensive_paths`, counted separately
+    pub expensive_requests_per_minute: u32,
+    pub expensive_paths: Vec<String>,
+    /// Take the client IP from `Forwarded`/`X-Forwarded-For`, only safe behind a proxy
+    /// that sets them
+    pub trust_forwarded_for: bool,
+}
+
+/// How long lookups stay cached, in Valkey when configured and per instance
+/// otherwise. A TTL of 0 turns that cache off.
+#[derive(Clone, Serialize)]
+pub struct CacheConfig {
+    /// Memberships and roles, dropped early when the organisation is deleted
+    pub membership_ttl_secs: u64,
+    /// Identity details from Kratos, which has no way to tell us they changed
+    pub identity_ttl_secs: u64,
+}
+
+impl CacheConfig {
+    pub fn membership_ttl(&self) -> Duration {
+        Duration::from_secs(self.membership_ttl_secs)
     }
 
-    let kratos_api_key = env::var("KRATOS_API_KEY").map_err(|_| ConfigError::MissingKratosApiKey)?;
-    if kratos_api_key.trim().is_empty() {
-        return Err(AppError::Config(ConfigError::MissingKratosApiKey));
+    pub fn identity_ttl(&self) -> Duration {
+        Duration::from_secs(self.identity_ttl_secs)
     }
+}
 
-    let server_host = env::var("SERVER_HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
+#[derive(Clone, Serialize)]
+pub struct RetentionConfig {
+    /// Days a deleted organisation or project can be restored before it is purged
+    pub deleted_days: u32,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TelemetryConfig {
+    pub log_format: LogFormat,
+    /// OpenTelemetry export stays disabled unless an endpoint is configured
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+    /// Bearer token required to scrape `/metrics`, left open when unset
+    pub metrics_token: Option<Secret>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct FeaturesConfig {
+    pub metrics_endpoint: bool,
+    pub background_jobs: bool,
+    pub rate_limiting: bool,
+}
+
+/// Usage metering and invoicing, only in the `cloud` build
+#[cfg(feature = "cloud")]
+#[derive(Clone, Serialize)]
+pub struct BillingConfig {
+    /// Sent as `X-API-KEY` by agents reporting usage
+    pub metering_api_key: Secret,
+    #[serde(flatten)]
+    pub prices: Prices,
+}
+
+/// What a unit of each metric costs, in cents of `currency`
+#[cfg(feature = "cloud")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, serde::Deserialize)]
+pub struct Prices {
+    /// ISO 4217 code, e.g. `eur`
+    pub currency: String,
+    pub runtime_hour_cents: u32,
+    pub cpu_hour_cents: u32,
+    pub storage_gb_month_cents: u32,
+}
+
+/// The single tenant of a self-hosted install, only in builds without `cloud`
+#[cfg(not(feature = "cloud"))]
+#[derive(Clone, Serialize)]
+pub struct InstanceConfig {
+    /// The organisation every registered identity joins, created on first start
+    pub organisation_id: Uuid,
+    pub organisation_name: String,
+    /// The identity registering with this email becomes the organisation's Owner,
+    /// everyone else joins as a Member
+    pub owner_email: String,
+}
 
-    let server_port = env::var("SERVER_PORT")
-        .unwrap_or_else(|_| "8080".to_string())
-        .parse()
-        .map_err(|_| {
-            ConfigError::InvalidServerPort(
-                env::var("SERVER_PORT").unwrap_or_else(|_| "invalid".to_string()),
-            )
-        })?;
+/// A sensitive value that never shows up in `Debug` output or `--print-config`
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
 
-    Ok(Config {
-        database_url,
-        server_host,
-        server_port,
-        kratos_api_key
-    })
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Compares in constant time, so the response time leaks nothing about the secret
+    pub fn matches(&self, candidate: &str) -> bool {
+        self.0.as_bytes().ct_eq(candidate.as_bytes()).into()
+    }
+}
+
+impl std::str::FromStr for Secret {
+    type Err = std::convert::Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(Secret(value.to_string()))
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret([redacted])")
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str("[redacted]")
+    }
+}
+
+/// Shows the connection URL with its password masked
+fn serialize_url<S: Serializer>(url: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    let redacted = match (url.find("://"), url.rfind('@')) {
+        (Some(scheme_end), Some(at)) if at > scheme_end => {
+            let credentials = &url[scheme_end + 3..at];
+            match credentials.split_once(':') {
+                Some((user, _)) => format!("{}{}:[redacted]{}", &url[..scheme_end + 3], user, &url[at..]),
+                None => url.to_string(),
+            }
+        }
+        _ => url.to_string(),
+    };
+    serializer.serialize_str(&redacted)
+}
+
+/// Command line flags of the server binary
+#[derive(Debug, Default)]
+pub struct Args {
+    pub config_path: Option<PathBuf>,
+    /// Print the effective config with secrets redacted, then exit
+    pub print_config: bool,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self, ConfigError> {
+        let mut args = Args::default();
+        let mut iter = env::args().skip(1);
+
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--print-config" => args.print_config = true,
+                "--config" => {
+                    let path = iter.next().ok_or(ConfigError::InvalidArgument(arg))?;
+                    args.config_path = Some(PathBuf::from(path));
+                }
+                _ => match arg.strip_prefix("--config=") {
+                    Some(path) => args.config_path = Some(PathBuf::from(path)),
+                    None => return Err(ConfigError::InvalidArgument(arg)),
+                },
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// Loads and validates the config, reporting every problem at once. The file is
+/// `path`, else `CONFIG_FILE`, else `config.toml` when present; without one only
+/// the environment is used.
+pub fn load_config(path: Option<&Path>) -> Result<Config, AppError> {
+    dotenvy::dotenv().ok();
+
+    let path = path
+        .map(Path::to_path_buf)
+        .or_else(|| env::var("CONFIG_FILE").ok().map(PathBuf::from))
+        .or_else(|| Some(PathBuf::from(DEFAULT_CONFIG_FILE)).filter(|path| path.exists()));
+    from_source(Source::load(path.as_deref()))
+}
+
+fn from_source(mut source: Source) -> Result<Config, AppError> {
+    let server = ServerConfig {
+        host: source.or("server.host", "SERVER_HOST", "0.0.0.0".to_string()),
+        port: source.or("server.port", "SERVER_PORT", 8080),
+        cors_allowed_origins: source.list("server.cors_allowed_origins", "CORS_ALLOWED_ORIGINS"),
+        request_timeout_secs: source.or("server.request_timeout_secs", "SERVER_REQUEST_TIMEOUT_SECS", 30),
+        shutdown_timeout_secs: source.or("server.shutdown_timeout_secs", "SERVER_SHUTDOWN_TIMEOUT_SECS", 30),
+    };
+
+    let database = DatabaseConfig {
+        url: source.required("database.url", "DATABASE_URL"),
+        max_connections: source.or("database.max_connections", "DATABASE_MAX_CONNECTIONS", 10),
+        min_connections: source.or("database.min_connections", "DATABASE_MIN_CONNECTIONS", 1),
+        connect_timeout_secs: source.or("database.connect_timeout_secs", "DATABASE_CONNECT_TIMEOUT_SECS", 10),
+        acquire_timeout_secs: source.or("database.acquire_timeout_secs", "DATABASE_ACQUIRE_TIMEOUT_SECS", 5),
+        idle_timeout_secs: source.or("database.idle_timeout_secs", "DATABASE_IDLE_TIMEOUT_SECS", 600),
+        statement_timeout_ms: source.or("database.statement_timeout_ms", "DATABASE_STATEMENT_TIMEOUT_MS", 30_000),
+        slow_statement_threshold_ms: source.or(
+            "database.slow_statement_threshold_ms",
+            "DATABASE_SLOW_STATEMENT_THRESHOLD_MS",
+            500,
+        ),
+        log_statements: source.or("database.log_statements", "DATABASE_LOG_STATEMENTS", false),
+    };
+
+    let auth = AuthConfig {
+        kratos_api_key: source.required("auth.kratos_api_key", "KRATOS_API_KEY"),
+        kratos_admin_url: source.or("auth.kratos_admin_url", "KRATOS_ADMIN_URL", "http://kratos:4434".to_string()),
+        jwks_url: source.optional("auth.jwks_url", "JWKS_URL"),
+    };
+
+    // SMTP is optional as a whole, but a configured host needs a sender address
+    let smtp_host: Option<String> = source.optional("smtp.host", "SMTP_HOST");
+    let smtp_port = source.or("smtp.port", "SMTP_PORT", 587);
+    let smtp_username = source.optional("smtp.username", "SMTP_USERNAME");
+    let smtp_password = source.optional("smtp.password", "SMTP_PASSWORD");
+    let smtp_from: Option<String> = source.optional("smtp.from_address", "SMTP_FROM_ADDRESS");
+    let smtp = smtp_host.map(|host| SmtpConfig {
+        host,
+        port: smtp_port,
+        username: smtp_username,
+        password: smtp_password,
+        from_address: smtp_from.unwrap_or_else(|| {
+            source.errors.push(ConfigError::MissingValue {
+                key: "smtp.from_address",
+                env: "SMTP_FROM_ADDRESS",
+            });
+            String::new()
+        }),
+    });
+
+    let valkey_url: Option<String> = source.optional("valkey.url", "VALKEY_URL");
+    let valkey_timeout_ms = source.or("valkey.timeout_ms", "VALKEY_TIMEOUT_MS", 250);
+    let valkey = valkey_url.map(|url| ValkeyConfig {
+        url,
+        timeout_ms: valkey_timeout_ms,
+    });
+
+    let jobs = JobsConfig {
+        workers: source.or("jobs.workers", "JOB_WORKERS", 4),
+    };
+
+    let rate_limit = RateLimitConfig {
+        requests_per_minute: source.or("rate_limit.requests_per_minute", "RATE_LIMIT_REQUESTS_PER_MINUTE", 600),
+        expensive_requests_per_minute: source.or(
+            "rate_limit.expensive_requests_per_minute",
+            "RATE_LIMIT_EXPENSIVE_REQUESTS_PER_MINUTE",
+            60,
+        ),
+        expensive_paths: match source.list("rate_limit.expensive_paths", "RATE_LIMIT_EXPENSIVE_PATHS") {
+            paths if paths.is_empty() => vec!["/search".to_string()],
+            paths => paths,
+        },
+        trust_forwarded_for: source.or("rate_limit.trust_forwarded_for", "RATE_LIMIT_TRUST_FORWARDED_FOR", false),
+    };
+
+    let cache = CacheConfig {
+        membership_ttl_secs: source.or("cache.membership_ttl_secs", "CACHE_MEMBERSHIP_TTL_SECS", 60),
+        identity_ttl_secs: source.or("cache.identity_ttl_secs", "CACHE_IDENTITY_TTL_SECS", 300),
+    };
+
+    let retention = RetentionConfig {
+        deleted_days: source.or("retention.deleted_days", "RETENTION_DELETED_DAYS", 30),
+    };
+
+    let telemetry = TelemetryConfig {
+        log_format: source.or("telemetry.log_format", "LOG_FORMAT", LogFormat::Pretty),
+        otlp_endpoint: source.optional("telemetry.otlp_endpoint", "OTEL_EXPORTER_OTLP_ENDPOINT"),
+        service_name: source.or("telemetry.service_name", "OTEL_SERVICE_NAME", "c-plane".to_string()),
+        metrics_token: source.optional("telemetry.metrics_token", "METRICS_TOKEN"),
+    };
+
+    let features = FeaturesConfig {
+        metrics_endpoint: source.or("features.metrics_endpoint", "FEATURE_METRICS_ENDPOINT", true),
+        background_jobs: source.or("features.background_jobs", "FEATURE_BACKGROUND_JOBS", true),
+        rate_limiting: source.or("features.rate_limiting", "FEATURE_RATE_LIMITING", true),
+    };
+
+    #[cfg(feature = "cloud")]
+    let billing = BillingConfig {
+        metering_api_key: source.required("billing.metering_api_key", "BILLING_METERING_API_KEY"),
+        prices: Prices {
+            currency: source.or("billing.currency", "BILLING_CURRENCY", "eur".to_string()),
+            runtime_hour_cents: source.or("billing.runtime_hour_cents", "BILLING_RUNTIME_HOUR_CENTS", 1),
+            cpu_hour_cents: source.or("billing.cpu_hour_cents", "BILLING_CPU_HOUR_CENTS", 4),
+            storage_gb_month_cents: source.or(
+                "billing.storage_gb_month_cents",
+                "BILLING_STORAGE_GB_MONTH_CENTS",
+                10,
+            ),
+        },
+    };
+
+    #[cfg(not(feature = "cloud"))]
+    let instance = InstanceConfig {
+        organisation_id: source.required("instance.organisation_id", "INSTANCE_ORGANISATION_ID"),
+        organisation_name: source.or(
+            "instance.organisation_name",
+            "INSTANCE_ORGANISATION_NAME",
+            "Default".to_string(),
+        ),
+        owner_email: source.required("instance.owner_email", "INSTANCE_OWNER_EMAIL"),
+    };
+
+    let config = Config {
+        server,
+        database,
+        auth,
+        smtp,
+        valkey,
+        jobs,
+        rate_limit,
+        cache,
+        retention,
+        telemetry,
+        features,
+        #[cfg(feature = "cloud")]
+        billing,
+        #[cfg(not(feature = "cloud"))]
+        instance,
+    };
+    validate(&config, &mut source);
+    source.finish()?;
+    Ok(config)
+}
+
+/// Checks that hold across values, on top of what parsing already covers
+fn validate(config: &Config, source: &mut Source) {
+    if config.server.port == 0 {
+        source.invalid("server.port", config.server.port, "must be between 1 and 65535");
+    }
+    for origin in &config.server.cors_allowed_origins {
+        if origin != "*" && !origin.starts_with("http://") && !origin.starts_with("https://") {
+            source.invalid(
+                "server.cors_allowed_origins",
+                origin,
+                "origins must start with http:// or https://, or be *",
+            );
+        }
+    }
+    if config.database.max_connections == 0 {
+        source.invalid("database.max_connections", 0, "must be at least 1");
+    }
+    if config.database.min_connections > config.database.max_connections {
+        source.invalid(
+            "database.min_connections",
+            config.database.min_connections,
+            format!("must not exceed max_connections ({})", config.database.max_connections),
+        );
+    }
+    if config.features.background_jobs && config.jobs.workers == 0 {
+        source.invalid("jobs.workers", 0, "must be at least 1 while background jobs are enabled");
+    }
+    if let Some(valkey) = &config.valkey {
+        let schemes = ["redis://", "rediss://", "valkey://", "valkeys://"];
+        if !schemes.iter().any(|scheme| valkey.url.starts_with(scheme)) {
+            source.invalid(
+                "valkey.url",
+                &valkey.url,
+                "must start with redis://, rediss://, valkey:// or valkeys://",
+            );
+        }
+    }
+    if config.rate_limit.requests_per_minute == 0 {
+        source.invalid("rate_limit.requests_per_minute", 0, "must be at least 1");
+    }
+    if config.rate_limit.expensive_requests_per_minute == 0 {
+        source.invalid("rate_limit.expensive_requests_per_minute", 0, "must be at least 1");
+    }
+    if config.retention.deleted_days == 0 {
+        source.invalid("retention.deleted_days", 0, "must be at least 1");
+    }
+    #[cfg(feature = "cloud")]
+    {
+        let currency = &config.billing.prices.currency;
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_lowercase()) {
+            source.invalid("billing.currency", currency, "must be a lowercase ISO 4217 code");
+        }
+    }
+    #[cfg(not(feature = "cloud"))]
+    if config.instance.organisation_name.trim().is_empty() {
+        source.invalid("instance.organisation_name", &config.instance.organisation_name, "must not be blank");
+    }
+    #[cfg(not(feature = "cloud"))]
+    if !config.instance.owner_email.is_empty() && !config.instance.owner_email.contains('@') {
+        source.invalid("instance.owner_email", &config.instance.owner_email, "must be an email address");
+    }
+    if let Some(smtp) = &config.smtp
+        && !smtp.from_address.is_empty()
+        && !smtp.from_address.contains('@')
+    {
+        source.invalid("smtp.from_address", &smtp.from_address, "must be an email address");
+    }
 }
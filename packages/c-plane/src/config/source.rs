@@ -0,0 +1,202 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::errors::ConfigError;
+
+/// A value from the config file, with arrays kept apart from single values
+#[derive(Debug, Clone, PartialEq)]
+enum FileValue {
+    Single(String),
+    List(Vec<String>),
+}
+
+/// Looks up an environment variable by name
+type Env = Box<dyn Fn(&str) -> Option<String>>;
+
+/// Raw config values from an optional TOML file, overlaid by environment variables.
+/// Lookups never fail; problems are collected so they can be reported together.
+pub struct Source {
+    path: Option<String>,
+    file: BTreeMap<String, FileValue>,
+    env: Env,
+    used: BTreeSet<&'static str>,
+    pub errors: Vec<ConfigError>,
+}
+
+impl Source {
+    /// Reads `path`, if any, overlaid by the process environment
+    pub fn load(path: Option<&Path>) -> Self {
+        Self::with_env(path, |name| env::var(name).ok())
+    }
+
+    /// Reads `path`, if any, overlaid by the variables `env` returns
+    pub fn with_env(path: Option<&Path>, env: impl Fn(&str) -> Option<String> + 'static) -> Self {
+        let mut source = Source {
+            path: path.map(|path| path.display().to_string()),
+            file: BTreeMap::new(),
+            env: Box::new(env),
+            used: BTreeSet::new(),
+            errors: Vec::new(),
+        };
+
+        let Some(path) = path else {
+            return source;
+        };
+        let display = path.display().to_string();
+
+        match fs::read_to_string(path) {
+            Ok(contents) => match contents.parse::<toml::Table>() {
+                Ok(table) => flatten("", &toml::Value::Table(table), &mut source.file),
+                Err(e) => source.errors.push(ConfigError::ParseFile {
+                    path: display,
+                    reason: e.to_string(),
+                }),
+            },
+            Err(e) => source.errors.push(ConfigError::ReadFile {
+                path: display,
+                reason: e.to_string(),
+            }),
+        }
+        source
+    }
+
+    /// The environment variable wins over the file; blank values count as unset
+    fn raw(&mut self, key: &'static str, env: &'static str) -> Option<String> {
+        self.used.insert(key);
+        let value = match (self.env)(env) {
+            Some(value) => value,
+            None => match self.file.get(key) {
+                Some(FileValue::Single(value)) => value.clone(),
+                Some(FileValue::List(items)) => {
+                    let value = format!("[{}]", items.join(", "));
+                    self.invalid(key, value, "must be a single value, not an array");
+                    return None;
+                }
+                None => return None,
+            },
+        };
+        Some(value).filter(|value| !value.trim().is_empty())
+    }
+
+    pub fn optional<T>(&mut self, key: &'static str, env: &'static str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self.raw(key, env)?;
+        match value.trim().parse() {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                self.invalid(key, value, e);
+                None
+            }
+        }
+    }
+
+    pub fn or<T>(&mut self, key: &'static str, env: &'static str, default: T) -> T
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.optional(key, env).unwrap_or(default)
+    }
+
+    /// Records a missing value and falls back to `T::default()`, which is never used
+    /// because loading fails as a whole
+    pub fn required<T>(&mut self, key: &'static str, env: &'static str) -> T
+    where
+        T: FromStr + Default,
+        T::Err: Display,
+    {
+        if self.raw(key, env).is_none() {
+            self.errors.push(ConfigError::MissingValue { key, env });
+            return T::default();
+        }
+        self.optional(key, env).unwrap_or_default()
+    }
+
+    /// Comma separated in the environment, an array in the file. Items are taken
+    /// from the file as they are, so they may contain commas.
+    pub fn list(&mut self, key: &'static str, env: &'static str) -> Vec<String> {
+        self.used.insert(key);
+        if let Some(value) = (self.env)(env) {
+            return value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect();
+        }
+        match self.file.get(key) {
+            Some(FileValue::List(items)) => items.clone(),
+            Some(FileValue::Single(value)) => {
+                let value = value.clone();
+                self.invalid(key, value, "must be an array");
+                Vec::new()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    pub fn invalid(&mut self, key: &'static str, value: impl Display, reason: impl Display) {
+        self.errors.push(ConfigError::InvalidValue {
+            key,
+            value: value.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Fails with every collected problem, including file keys nothing asked for
+    pub fn finish(mut self) -> Result<(), ConfigError> {
+        if let Some(path) = &self.path {
+            for key in self.file.keys() {
+                if !self.used.contains(key.as_str()) {
+                    self.errors.push(ConfigError::UnknownKey {
+                        path: path.clone(),
+                        key: key.clone(),
+                    });
+                }
+            }
+        }
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(self.errors))
+        }
+    }
+}
+
+/// Flattens nested tables into dotted keys, e.g. `[database] url` becomes `database.url`
+fn flatten(prefix: &str, value: &toml::Value, out: &mut BTreeMap<String, FileValue>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&key, value, out);
+            }
+        }
+        toml::Value::Array(items) => {
+            let items = items.iter().map(scalar).collect();
+            out.insert(prefix.to_string(), FileValue::List(items));
+        }
+        other => {
+            out.insert(prefix.to_string(), FileValue::Single(scalar(other)));
+        }
+    }
+}
+
+/// Strings without their quotes, anything else as written in TOML
+fn scalar(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
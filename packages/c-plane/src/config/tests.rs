@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use tempfile::NamedTempFile;
+
+use super::source::Source;
+use super::{Config, from_source};
+use crate::errors::{AppError, ConfigError};
+
+/// Keys every build requires, so each test only spells out what it is about
+#[cfg(not(feature = "cloud"))]
+const REQUIRED: &str = r#"
+[auth]
+kratos_api_key = "kratos-secret"
+
+[instance]
+organisation_id = "00000000-0000-0000-0000-000000000001"
+owner_email = "owner@example.com"
+"#;
+
+#[cfg(feature = "cloud")]
+const REQUIRED: &str = r#"
+[auth]
+kratos_api_key = "kratos-secret"
+
+[billing]
+metering_api_key = "metering-secret"
+"#;
+
+/// Loads `file`, appended to the required keys, overlaid by `env` only
+fn load(file: &str, env: &[(&str, &str)]) -> Result<Config, AppError> {
+    let mut config_file = NamedTempFile::new().unwrap();
+    write!(config_file, "{}\n{}", file, REQUIRED).unwrap();
+    let env: HashMap<String, String> = env
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    from_source(Source::with_env(Some(config_file.path()), move |name| env.get(name).cloned()))
+}
+
+fn problems(result: Result<Config, AppError>) -> Vec<String> {
+    match result {
+        Err(AppError::Config(ConfigError::Invalid(errors))) => {
+            errors.iter().map(ToString::to_string).collect()
+        }
+        Err(other) => panic!("unexpected error: {}", other),
+        Ok(_) => panic!("the config was accepted"),
+    }
+}
+
+#[test]
+fn the_environment_overrides_the_file() {
+    let config = load(
+        r#"
+        [server]
+        host = "127.0.0.1"
+        port = 9000
+
+        [database]
+        url = "postgres://file/cplane"
+        "#,
+        &[("SERVER_PORT", "9100"), ("DATABASE_MAX_CONNECTIONS", "3")],
+    )
+    .unwrap();
+
+    assert_eq!(config.server.host, "127.0.0.1", "kept from the file");
+    assert_eq!(config.server.port, 9100);
+    assert_eq!(config.database.url, "postgres://file/cplane");
+    assert_eq!(config.database.max_connections, 3);
+    assert_eq!(config.database.min_connections, 1, "defaulted");
+}
+
+#[test]
+fn file_arrays_are_kept_as_arrays() {
+    let file = r#"
+        [database]
+        url = "sqlite::memory:"
+
+        [server]
+        cors_allowed_origins = ["https://a.example", "https://b.example"]
+
+        [rate_limit]
+        expensive_paths = ["/search", "/reports/a,b"]
+        "#;
+
+    let config = load(file, &[]).unwrap();
+    assert_eq!(config.server.cors_allowed_origins, ["https://a.example", "https://b.example"]);
+    assert_eq!(config.rate_limit.expensive_paths, ["/search", "/reports/a,b"], "commas stay inside items");
+
+    let config = load(file, &[("CORS_ALLOWED_ORIGINS", "https://c.example, ,https://d.example")]).unwrap();
+    assert_eq!(config.server.cors_allowed_origins, ["https://c.example", "https://d.example"]);
+}
+
+#[test]
+fn arrays_and_single_values_are_not_mixed_up() {
+    let problems = problems(load(
+        r#"
+        [database]
+        url = ["sqlite::memory:"]
+
+        [server]
+        cors_allowed_origins = "https://a.example"
+        "#,
+        &[],
+    ));
+
+    assert_eq!(
+        problems,
+        [
+            "server.cors_allowed_origins has invalid value 'https://a.example': must be an array",
+            "database.url has invalid value '[sqlite::memory:]': must be a single value, not an array",
+            "database.url is required, set it in the config file or via DATABASE_URL",
+        ]
+    );
+}
+
+#[test]
+fn every_problem_is_reported_together() {
+    let problems = problems(load(
+        r#"
+        [server]
+        port = "eighty"
+        colour = "blue"
+
+        [database]
+        max_connections = 2
+        min_connections = 5
+        "#,
+        &[("JOB_WORKERS", "many")],
+    ));
+
+    let expected = [
+        "server.port has invalid value 'eighty'",
+        "database.url is required",
+        "jobs.workers has invalid value 'many'",
+        "database.min_connections has invalid value '5': must not exceed max_connections (2)",
+        "unknown key 'server.colour'",
+    ];
+    assert_eq!(problems.len(), expected.len(), "{:#?}", problems);
+    for (problem, expected) in problems.iter().zip(expected) {
+        assert!(problem.contains(expected), "{:?} should mention {:?}", problem, expected);
+    }
+}
+
+#[test]
+fn printed_config_redacts_secrets() {
+    let config = load(
+        r#"
+        [database]
+        url = "postgres://cplane:db-password@db:5432/cplane"
+
+        [smtp]
+        host = "smtp.example"
+        password = "smtp-password"
+        from_address = "noreply@example.com"
+
+        [telemetry]
+        metrics_token = "metrics-token"
+        "#,
+        &[],
+    )
+    .unwrap();
+
+    let printed = config.to_toml().unwrap();
+    for secret in ["db-password", "smtp-password", "metrics-token", "kratos-secret", "metering-secret"] {
+        assert!(!printed.contains(secret), "{} is printed:\n{}", secret, printed);
+    }
+    assert!(printed.contains("postgres://cplane:[redacted]@db:5432/cplane"), "{}", printed);
+    assert!(printed.contains("smtp.example"), "other values are printed as they are");
+}
@@ -2,28 +2,43 @@ use std::fmt;
 
 #[derive(Debug)]
 pub enum ConfigError {
-    MissingDatabaseUrl,
-    MissingKratosApiKey,
-    InvalidServerPort(String),
+    InvalidArgument(String),
+    ReadFile { path: String, reason: String },
+    ParseFile { path: String, reason: String },
+    UnknownKey { path: String, key: String },
+    MissingValue { key: &'static str, env: &'static str },
+    InvalidValue { key: &'static str, value: String, reason: String },
+    /// Every problem found while loading, reported together
+    Invalid(Vec<ConfigError>),
 }
 
 impl fmt::Display for ConfigError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ConfigError::MissingDatabaseUrl => {
-                write!(
-                    f,
-                    "DATABASE_URL environment variable is required and cannot be empty"
-                )
+            ConfigError::InvalidArgument(arg) => {
+                write!(f, "Unknown or incomplete argument '{}'", arg)
             }
-            ConfigError::MissingKratosApiKey => {
-                write!(
-                    f,
-                    "KRATOS_API_KEY environment variable is required and cannot be empty"
-                )
+            ConfigError::ReadFile { path, reason } => {
+                write!(f, "Could not read config file {}: {}", path, reason)
             }
-            ConfigError::InvalidServerPort(port) => {
-                write!(f, "SERVER_PORT '{}' is not a valid port number", port)
+            ConfigError::ParseFile { path, reason } => {
+                write!(f, "Config file {} is not valid TOML: {}", path, reason)
+            }
+            ConfigError::UnknownKey { path, key } => {
+                write!(f, "Config file {} contains unknown key '{}'", path, key)
+            }
+            ConfigError::MissingValue { key, env } => {
+                write!(f, "{} is required, set it in the config file or via {}", key, env)
+            }
+            ConfigError::InvalidValue { key, value, reason } => {
+                write!(f, "{} has invalid value '{}': {}", key, value, reason)
+            }
+            ConfigError::Invalid(errors) => {
+                write!(f, "{} configuration problem(s):", errors.len())?;
+                for error in errors {
+                    write!(f, "\n  - {}", error)?;
+                }
+                Ok(())
             }
         }
     }
@@ -1,3 +1,4 @@
+use actix_web::http::StatusCode;
 use std::fmt;
 
 #[derive(Debug)]
@@ -22,3 +23,25 @@ impl fmt::Display for DatabaseError {
 }
 
 impl std::error::Error for DatabaseError {}
+
+impl DatabaseError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            DatabaseError::ConstraintViolation(_) => StatusCode::CONFLICT,
+            DatabaseError::Timeout => StatusCode::SERVICE_UNAVAILABLE,
+            DatabaseError::ConnectionFailed(_)
+            | DatabaseError::QueryFailed(_)
+            | DatabaseError::TransactionFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            DatabaseError::ConstraintViolation(_) => "conflict",
+            DatabaseError::Timeout => "timeout",
+            DatabaseError::ConnectionFailed(_)
+            | DatabaseError::QueryFailed(_)
+            | DatabaseError::TransactionFailed(_) => "internal_error",
+        }
+    }
+}
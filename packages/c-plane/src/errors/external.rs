@@ -1,3 +1,4 @@
+use actix_web::http::StatusCode;
 use std::fmt;
 
 #[derive(Debug)]
@@ -5,6 +6,7 @@ pub enum ExternalError {
     OryApiError(String),
     EmailServiceError(String),
     PaymentProviderError(String),
+    ValkeyError(String),
     NetworkTimeout,
     ServiceUnavailable(String),
 }
@@ -17,6 +19,7 @@ impl fmt::Display for ExternalError {
             ExternalError::PaymentProviderError(msg) => {
                 write!(f, "Payment provider error: {}", msg)
             }
+            ExternalError::ValkeyError(msg) => write!(f, "Valkey error: {}", msg),
             ExternalError::NetworkTimeout => write!(f, "Network timeout"),
             ExternalError::ServiceUnavailable(service) => {
                 write!(f, "Service unavailable: {}", service)
@@ -26,3 +29,27 @@ impl fmt::Display for ExternalError {
 }
 
 impl std::error::Error for ExternalError {}
+
+impl ExternalError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ExternalError::NetworkTimeout => StatusCode::GATEWAY_TIMEOUT,
+            ExternalError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ExternalError::OryApiError(_)
+            | ExternalError::EmailServiceError(_)
+            | ExternalError::PaymentProviderError(_)
+            | ExternalError::ValkeyError(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ExternalError::NetworkTimeout => "upstream_timeout",
+            ExternalError::ServiceUnavailable(_) => "upstream_unavailable",
+            ExternalError::OryApiError(_)
+            | ExternalError::EmailServiceError(_)
+            | ExternalError::PaymentProviderError(_)
+            | ExternalError::ValkeyError(_) => "upstream_error",
+        }
+    }
+}
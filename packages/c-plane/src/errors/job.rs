@@ -0,0 +1,38 @@
+use std::fmt;
+
+use super::AppError;
+
+#[derive(Debug)]
+pub enum JobError {
+    UnknownKind(String),
+    InvalidPayload(String),
+    InvalidSchedule(String),
+    ExecutionFailed(String),
+    Panicked(String),
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::UnknownKind(kind) => write!(f, "No handler registered for job kind: {}", kind),
+            JobError::InvalidPayload(msg) => write!(f, "Invalid job payload: {}", msg),
+            JobError::InvalidSchedule(msg) => write!(f, "Invalid job schedule: {}", msg),
+            JobError::ExecutionFailed(msg) => write!(f, "Job execution failed: {}", msg),
+            JobError::Panicked(msg) => write!(f, "Job panicked: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for JobError {}
+
+impl From<AppError> for JobError {
+    fn from(err: AppError) -> Self {
+        JobError::ExecutionFailed(err.to_string())
+    }
+}
+
+impl From<sea_orm::DbErr> for JobError {
+    fn from(err: sea_orm::DbErr) -> Self {
+        JobError::ExecutionFailed(err.to_string())
+    }
+}
@@ -1,29 +1,44 @@
 pub mod config;
 pub mod database;
 pub mod external;
+pub mod job;
 pub mod organisation;
 pub mod project;
 pub mod user;
+pub mod validation;
 
 pub use config::ConfigError;
 pub use database::DatabaseError;
 pub use external::ExternalError;
+pub use job::JobError;
 pub use organisation::OrganisationError;
 pub use project::ProjectError;
 pub use user::UserError;
+pub use validation::{FieldError, ValidationError};
 
+pub use c_plane_client::types::ErrorResponse;
+
+use actix_web::http::StatusCode;
+use actix_web::http::header::{CONTENT_TYPE, RETRY_AFTER};
 use actix_web::{HttpResponse, ResponseError};
-use serde::Serialize;
+use sea_orm::SqlErr;
 use std::fmt;
 
+use crate::utils::logger::current_request_id;
+
+/// Content type of every error response
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
 #[derive(Debug)]
 pub enum AppError {
     Project(ProjectError),
     User(UserError),
     Organisation(OrganisationError),
+    Validation(ValidationError),
 
     Database(DatabaseError),
     External(ExternalError),
+    Job(JobError),
 
     Config(ConfigError),
 
@@ -31,35 +46,95 @@ pub enum AppError {
     Forbidden(String),
     NotFound(String),
     Conflict(String),
+    /// The caller used up its request budget and may retry after this many seconds
+    RateLimited { retry_after_secs: u64 },
 
     Internal(String),
 }
 
-#[derive(Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
-    pub message: String,
-    pub details: Option<serde_json::Value>,
-}
-
 impl From<sea_orm::DbErr> for AppError {
     fn from(err: sea_orm::DbErr) -> Self {
         match err {
             sea_orm::DbErr::RecordNotFound(_) => AppError::NotFound("Record not found".to_string()),
+            sea_orm::DbErr::ConnectionAcquire(sea_orm::ConnAcquireErr::Timeout) => {
+                AppError::Database(DatabaseError::Timeout)
+            }
             sea_orm::DbErr::ConnectionAcquire(_) => {
                 AppError::Database(DatabaseError::ConnectionFailed(err.to_string()))
             }
+            _ if is_statement_timeout(&err) => AppError::Database(DatabaseError::Timeout),
+            _ if matches!(
+                err.sql_err(),
+                Some(SqlErr::UniqueConstraintViolation(_) | SqlErr::ForeignKeyConstraintViolation(_))
+            ) =>
+            {
+                AppError::Database(DatabaseError::ConstraintViolation(err.to_string()))
+            }
             _ => AppError::Database(DatabaseError::QueryFailed(err.to_string())),
         }
     }
 }
 
+impl From<sea_orm::TransactionError<sea_orm::DbErr>> for AppError {
+    fn from(err: sea_orm::TransactionError<sea_orm::DbErr>) -> Self {
+        match err {
+            sea_orm::TransactionError::Connection(e) | sea_orm::TransactionError::Transaction(e) => {
+                transaction_failed(e)
+            }
+        }
+    }
+}
+
+/// For transactions whose closure already fails with an `AppError`
+impl From<sea_orm::TransactionError<AppError>> for AppError {
+    fn from(err: sea_orm::TransactionError<AppError>) -> Self {
+        match err {
+            sea_orm::TransactionError::Connection(e) => transaction_failed(e),
+            sea_orm::TransactionError::Transaction(e) => e,
+        }
+    }
+}
+
+/// Timeouts keep their own error so callers get a 503 instead of a 500
+fn transaction_failed(err: sea_orm::DbErr) -> AppError {
+    match AppError::from(err) {
+        AppError::Database(DatabaseError::QueryFailed(msg)) => {
+            AppError::Database(DatabaseError::TransactionFailed(msg))
+        }
+        other => other,
+    }
+}
+
+/// Postgres cancels statements exceeding `statement_timeout` with SQLSTATE 57014
+fn is_statement_timeout(err: &sea_orm::DbErr) -> bool {
+    match err {
+        sea_orm::DbErr::Exec(sea_orm::RuntimeErr::SqlxError(e))
+        | sea_orm::DbErr::Query(sea_orm::RuntimeErr::SqlxError(e)) => e
+            .as_database_error()
+            .and_then(|e| e.code())
+            .is_some_and(|code| code == "57014"),
+        _ => false,
+    }
+}
+
 impl From<ProjectError> for AppError {
     fn from(err: ProjectError) -> Self {
         AppError::Project(err)
     }
 }
 
+impl From<ValidationError> for AppError {
+    fn from(err: ValidationError) -> Self {
+        AppError::Validation(err)
+    }
+}
+
+impl From<JobError> for AppError {
+    fn from(err: JobError) -> Self {
+        AppError::Job(err)
+    }
+}
+
 impl From<ConfigError> for AppError {
     fn from(err: ConfigError) -> Self {
         AppError::Config(err)
@@ -72,13 +147,18 @@ impl fmt::Display for AppError {
             AppError::Project(err) => write!(f, "Project error: {}", err),
             AppError::User(err) => write!(f, "User error: {}", err),
             AppError::Organisation(err) => write!(f, "Organisation error: {}", err),
+            AppError::Validation(err) => write!(f, "Validation error: {}", err),
             AppError::Database(err) => write!(f, "Database error: {}", err),
             AppError::External(err) => write!(f, "External error: {}", err),
+            AppError::Job(err) => write!(f, "Job error: {}", err),
             AppError::Config(err) => write!(f, "Config error: {}", err),
             AppError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
             AppError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
             AppError::NotFound(msg) => write!(f, "Not found: {}", msg),
             AppError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            AppError::RateLimited { retry_after_secs } => {
+                write!(f, "Rate limited, retry after {}s", retry_after_secs)
+            }
             AppError::Internal(msg) => write!(f, "Internal error: {}", msg),
         }
     }
@@ -86,48 +166,112 @@ impl fmt::Display for AppError {
 
 impl std::error::Error for AppError {}
 
+impl AppError {
+    /// Stable code clients match on, documented on `ErrorResponse::code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Project(err) => err.code(),
+            AppError::User(err) => err.code(),
+            AppError::Organisation(err) => err.code(),
+            AppError::Validation(err) => err.code(),
+            AppError::Database(err) => err.code(),
+            AppError::External(err) => err.code(),
+            AppError::Job(_) | AppError::Internal(_) => "internal_error",
+            AppError::Config(_) => "configuration_error",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::RateLimited { .. } => "rate_limited",
+        }
+    }
+
+    /// What the client is told, internal details stay in the logs
+    fn detail(&self) -> String {
+        match self {
+            AppError::Project(err) => err.to_string(),
+            AppError::User(err) => err.to_string(),
+            AppError::Organisation(err) => err.to_string(),
+            AppError::Validation(ValidationError::InvalidFields(_)) => {
+                "The request has invalid fields".to_string()
+            }
+            AppError::Validation(err) => err.to_string(),
+            AppError::Database(DatabaseError::Timeout) => {
+                "The database did not respond in time".to_string()
+            }
+            AppError::Database(DatabaseError::ConstraintViolation(_)) => {
+                "The request conflicts with existing data".to_string()
+            }
+            AppError::External(_) => "An upstream service failed".to_string(),
+            AppError::Config(_) => "Service configuration error".to_string(),
+            AppError::Database(_) | AppError::Job(_) | AppError::Internal(_) => {
+                "An internal error occurred".to_string()
+            }
+            AppError::Unauthorized(msg)
+            | AppError::Forbidden(msg)
+            | AppError::NotFound(msg)
+            | AppError::Conflict(msg) => msg.clone(),
+            AppError::RateLimited { retry_after_secs } => format!(
+                "Too many requests, retry in {} seconds",
+                retry_after_secs
+            ),
+        }
+    }
+
+    /// The problem document for this error, tagged with the current request id
+    pub fn problem(&self) -> ErrorResponse {
+        let status = self.status_code();
+        let request_id = current_request_id();
+        ErrorResponse {
+            problem_type: format!("urn:c-plane:problem:{}", self.code()),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: self.detail(),
+            instance: request_id.as_ref().map(|id| format!("urn:c-plane:request:{}", id)),
+            code: self.code().to_string(),
+            request_id,
+            errors: match self {
+                AppError::Validation(ValidationError::InvalidFields(errors)) => {
+                    serde_json::to_value(errors).ok()
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
 impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::Project(err) => err.status(),
+            AppError::User(err) => err.status(),
+            AppError::Organisation(err) => err.status(),
+            AppError::Validation(err) => err.status(),
+            AppError::Database(err) => err.status(),
+            AppError::External(err) => err.status(),
+            AppError::Job(_) | AppError::Config(_) | AppError::Internal(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
     fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
         match self {
-            AppError::Project(_) | AppError::User(_) | AppError::Organisation(_) => {
-                HttpResponse::BadRequest().json(ErrorResponse {
-                    error: "validation_error".to_string(),
-                    message: self.to_string(),
-                    details: None,
-                })
+            AppError::Database(DatabaseError::Timeout) => {
+                response.insert_header((RETRY_AFTER, "1"));
             }
-            AppError::Database(_) | AppError::External(_) | AppError::Internal(_) => {
-                HttpResponse::InternalServerError().json(ErrorResponse {
-                    error: "internal_error".to_string(),
-                    message: "An internal error occurred".to_string(),
-                    details: None,
-                })
+            AppError::RateLimited { retry_after_secs } => {
+                response.insert_header((RETRY_AFTER, retry_after_secs.to_string()));
             }
-            AppError::Config(_) => HttpResponse::InternalServerError().json(ErrorResponse {
-                error: "configuration_error".to_string(),
-                message: "Service configuration error".to_string(),
-                details: None,
-            }),
-            AppError::Unauthorized(msg) => HttpResponse::Unauthorized().json(ErrorResponse {
-                error: "unauthorized".to_string(),
-                message: msg.clone(),
-                details: None,
-            }),
-            AppError::Forbidden(msg) => HttpResponse::Forbidden().json(ErrorResponse {
-                error: "forbidden".to_string(),
-                message: msg.clone(),
-                details: None,
-            }),
-            AppError::NotFound(msg) => HttpResponse::NotFound().json(ErrorResponse {
-                error: "not_found".to_string(),
-                message: msg.clone(),
-                details: None,
-            }),
-            AppError::Conflict(msg) => HttpResponse::Conflict().json(ErrorResponse {
-                error: "conflict".to_string(),
-                message: msg.clone(),
-                details: None,
-            }),
+            _ => {}
         }
+        let body = serde_json::to_string(&self.problem()).unwrap_or_default();
+        response.insert_header((CONTENT_TYPE, PROBLEM_JSON)).body(body)
     }
 }
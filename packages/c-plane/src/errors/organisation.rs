@@ -1,3 +1,4 @@
+use actix_web::http::StatusCode;
 use std::fmt;
 use uuid::Uuid;
 
@@ -7,6 +8,9 @@ pub enum OrganisationError {
     UserNotMember(Uuid),
     InsufficientRole { required: String, current: String },
     CannotRemoveLastOwner,
+    CannotDeleteInstanceOrganisation,
+    MemberLimitExceeded { current: u32, limit: u32 },
+    SubscriptionSuspended(Uuid),
 }
 
 impl fmt::Display for OrganisationError {
@@ -24,8 +28,43 @@ impl fmt::Display for OrganisationError {
                 )
             }
             OrganisationError::CannotRemoveLastOwner => write!(f, "Cannot remove the last owner"),
+            OrganisationError::CannotDeleteInstanceOrganisation => {
+                write!(f, "Cannot delete the instance's organisation")
+            }
+            OrganisationError::MemberLimitExceeded { current, limit } => {
+                write!(f, "Member limit exceeded: {}/{}", current, limit)
+            }
+            OrganisationError::SubscriptionSuspended(id) => {
+                write!(f, "Subscription of organisation {} is suspended", id)
+            }
         }
     }
 }
 
 impl std::error::Error for OrganisationError {}
+
+impl OrganisationError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            OrganisationError::OrganisationNotFound(_) => StatusCode::NOT_FOUND,
+            OrganisationError::UserNotMember(_)
+            | OrganisationError::InsufficientRole { .. }
+            | OrganisationError::MemberLimitExceeded { .. } => StatusCode::FORBIDDEN,
+            OrganisationError::CannotRemoveLastOwner
+            | OrganisationError::CannotDeleteInstanceOrganisation => StatusCode::CONFLICT,
+            OrganisationError::SubscriptionSuspended(_) => StatusCode::PAYMENT_REQUIRED,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            OrganisationError::OrganisationNotFound(_) => "organisation_not_found",
+            OrganisationError::UserNotMember(_) => "not_a_member",
+            OrganisationError::InsufficientRole { .. } => "insufficient_role",
+            OrganisationError::CannotRemoveLastOwner => "cannot_remove_last_owner",
+            OrganisationError::CannotDeleteInstanceOrganisation => "cannot_delete_instance_organisation",
+            OrganisationError::MemberLimitExceeded { .. } => "member_limit_exceeded",
+            OrganisationError::SubscriptionSuspended(_) => "subscription_suspended",
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use actix_web::http::StatusCode;
 use std::fmt;
 use uuid::Uuid;
 
@@ -6,6 +7,7 @@ pub enum ProjectError {
     InvalidSlug(String),
     SlugAlreadyExists(String),
     ProjectNotFound(Uuid),
+    ProjectArchived(Uuid),
     CannotArchivePublicProject,
     OwnerCannotLeaveProject,
     ProjectLimitExceeded { current: u32, limit: u32 },
@@ -17,6 +19,9 @@ impl fmt::Display for ProjectError {
             ProjectError::InvalidSlug(slug) => write!(f, "Invalid slug: {}", slug),
             ProjectError::SlugAlreadyExists(slug) => write!(f, "Slug already exists: {}", slug),
             ProjectError::ProjectNotFound(id) => write!(f, "Project not found: {}", id),
+            ProjectError::ProjectArchived(id) => {
+                write!(f, "Project {} is archived and can't be changed until it is unarchived", id)
+            }
             ProjectError::CannotArchivePublicProject => write!(f, "Cannot archive public project"),
             ProjectError::OwnerCannotLeaveProject => {
                 write!(f, "Project owner cannot leave project")
@@ -29,3 +34,29 @@ impl fmt::Display for ProjectError {
 }
 
 impl std::error::Error for ProjectError {}
+
+impl ProjectError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ProjectError::InvalidSlug(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ProjectError::ProjectNotFound(_) => StatusCode::NOT_FOUND,
+            ProjectError::SlugAlreadyExists(_)
+            | ProjectError::ProjectArchived(_)
+            | ProjectError::CannotArchivePublicProject
+            | ProjectError::OwnerCannotLeaveProject => StatusCode::CONFLICT,
+            ProjectError::ProjectLimitExceeded { .. } => StatusCode::FORBIDDEN,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProjectError::InvalidSlug(_) => "invalid_slug",
+            ProjectError::SlugAlreadyExists(_) => "slug_already_exists",
+            ProjectError::ProjectNotFound(_) => "project_not_found",
+            ProjectError::ProjectArchived(_) => "project_archived",
+            ProjectError::CannotArchivePublicProject => "cannot_archive_public_project",
+            ProjectError::OwnerCannotLeaveProject => "owner_cannot_leave_project",
+            ProjectError::ProjectLimitExceeded { .. } => "project_limit_exceeded",
+        }
+    }
+}
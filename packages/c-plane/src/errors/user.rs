@@ -1,3 +1,4 @@
+use actix_web::http::StatusCode;
 use std::fmt;
 use uuid::Uuid;
 
@@ -21,3 +22,24 @@ impl fmt::Display for UserError {
 }
 
 impl std::error::Error for UserError {}
+
+impl UserError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            UserError::UserNotFound(_) => StatusCode::NOT_FOUND,
+            UserError::EmailAlreadyExists(_) => StatusCode::CONFLICT,
+            UserError::AccountDeactivated | UserError::InsufficientPermissions => {
+                StatusCode::FORBIDDEN
+            }
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            UserError::UserNotFound(_) => "user_not_found",
+            UserError::EmailAlreadyExists(_) => "email_already_exists",
+            UserError::AccountDeactivated => "account_deactivated",
+            UserError::InsufficientPermissions => "insufficient_permissions",
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::fmt;
+
+use actix_web::http::StatusCode;
+use serde::Serialize;
+
+/// A single failing field, `code` is stable for clients to match on
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub enum ValidationError {
+    /// Every rule that failed, reported together
+    InvalidFields(Vec<FieldError>),
+    /// The body is not JSON at all
+    MalformedBody(String),
+    /// The body is JSON but doesn't match the expected shape
+    InvalidBody(String),
+    UnsupportedContentType,
+    BodyTooLarge { limit: usize },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::InvalidFields(errors) => {
+                write!(f, "{} field(s) failed validation", errors.len())
+            }
+            ValidationError::MalformedBody(reason) => write!(f, "Malformed JSON body: {}", reason),
+            ValidationError::InvalidBody(reason) => write!(f, "Invalid request body: {}", reason),
+            ValidationError::UnsupportedContentType => {
+                write!(f, "Expected a body with content type application/json")
+            }
+            ValidationError::BodyTooLarge { limit } => {
+                write!(f, "Request body exceeds the limit of {} bytes", limit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl ValidationError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ValidationError::InvalidFields(_) | ValidationError::InvalidBody(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ValidationError::MalformedBody(_) => StatusCode::BAD_REQUEST,
+            ValidationError::UnsupportedContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ValidationError::BodyTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ValidationError::InvalidFields(_) => "validation_failed",
+            ValidationError::MalformedBody(_) => "malformed_json",
+            ValidationError::InvalidBody(_) => "invalid_body",
+            ValidationError::UnsupportedContentType => "unsupported_media_type",
+            ValidationError::BodyTooLarge { .. } => "payload_too_large",
+        }
+    }
+}
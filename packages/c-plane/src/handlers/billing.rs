@@ -0,0 +1,213 @@
+#![cfg(feature = "cloud")]
+
+use actix_web::{HttpRequest, HttpResponse, Result, get, post, web};
+use c_plane_client::types::{
+    InvoiceLineResponse, InvoiceResponse, InvoiceStatus as InvoiceStatusResponse,
+    SubscriptionResponse, SubscriptionStatus as SubscriptionStatusResponse,
+    UsageMetric as MetricResponse,
+};
+use utoipa::OpenApi;
+use uuid::Uuid;
+
+use crate::errors::{AppError, ErrorResponse};
+use crate::middleware::auth::{AuthMiddleware, UserId};
+use crate::models::entities::{
+    InvoiceLineModel, InvoiceModel, InvoiceStatus, SubscriptionModel, SubscriptionStatus,
+    UsageMetric,
+};
+use crate::services::billing::{
+    charge_invoice, get_invoice, get_subscription, invoice_lines, list_invoices,
+};
+use crate::services::organisations::{cached_member, get_organisation, require_owner};
+use crate::state::State;
+use crate::utils::logger::record_organisation_id;
+use crate::utils::pagination::{PaginatedResponse, PaginationQuery, paginated};
+
+impl From<UsageMetric> for MetricResponse {
+    fn from(metric: UsageMetric) -> Self {
+        match metric {
+            UsageMetric::RuntimeSeconds => MetricResponse::RuntimeSeconds,
+            UsageMetric::CpuSeconds => MetricResponse::CpuSeconds,
+            UsageMetric::StorageGbHours => MetricResponse::StorageGbHours,
+        }
+    }
+}
+
+impl From<InvoiceLineModel> for InvoiceLineResponse {
+    fn from(line: InvoiceLineModel) -> Self {
+        Self {
+            metric: MetricResponse::from(line.metric),
+            quantity: line.quantity,
+            unit_price_cents: line.unit_price_cents,
+            amount_cents: line.amount_cents,
+        }
+    }
+}
+
+/// Invoices are only served under the organisation they belong to, which is still
+/// around while they can be requested
+fn invoice_response(
+    organisation_id: Uuid,
+    (invoice, lines): (InvoiceModel, Vec<InvoiceLineModel>),
+) -> InvoiceResponse {
+    InvoiceResponse {
+        id: invoice.id,
+        organisation_id,
+        period_start: invoice.period_start,
+        period_end: invoice.period_end,
+        currency: invoice.currency,
+        total_cents: invoice.total_cents,
+        status: match invoice.status {
+            InvoiceStatus::Open => InvoiceStatusResponse::Open,
+            InvoiceStatus::Processing => InvoiceStatusResponse::Processing,
+            InvoiceStatus::Paid => InvoiceStatusResponse::Paid,
+            InvoiceStatus::Failed => InvoiceStatusResponse::Failed,
+        },
+        failure_reason: invoice.failure_reason,
+        lines: lines.into_iter().map(InvoiceLineResponse::from).collect(),
+        created_at: invoice.created_at,
+    }
+}
+
+/// Organisations without a subscription row are active
+fn subscription_response(subscription: Option<SubscriptionModel>) -> SubscriptionResponse {
+    match subscription {
+        Some(subscription) => SubscriptionResponse {
+            status: match subscription.status {
+                SubscriptionStatus::Active => SubscriptionStatusResponse::Active,
+                SubscriptionStatus::Suspended => SubscriptionStatusResponse::Suspended,
+            },
+            suspended_at: subscription.suspended_at,
+        },
+        None => SubscriptionResponse {
+            status: SubscriptionStatusResponse::Active,
+            suspended_at: None,
+        },
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(get_subscription_handler, list_invoices_handler, pay_invoice_handler))]
+pub(super) struct Api;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/billing")
+        .wrap(AuthMiddleware)
+        .service(get_subscription_handler)
+        .service(list_invoices_handler)
+        .service(pay_invoice_handler)
+    );
+}
+
+/// Whether the organisation can deploy or is suspended over a failed payment
+#[utoipa::path(
+    tag = "billing",
+    security(("user" = [])),
+    params(("id" = Uuid, Path, description = "Organisation id")),
+    responses(
+        (status = 200, body = SubscriptionResponse),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json"),
+    ),
+)]
+#[get("/{id}/subscription")]
+async fn get_subscription_handler(
+    state: web::Data<State>,
+    user_id: UserId,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let organisation_id = path.into_inner();
+    record_organisation_id(organisation_id);
+
+    cached_member(
+        &state.db,
+        state.cache.as_ref(),
+        state.config.cache.membership_ttl(),
+        organisation_id,
+        user_id.into_inner(),
+    )
+    .await?;
+    let subscription = get_subscription(&state.db, organisation_id).await?;
+
+    Ok(HttpResponse::Ok().json(subscription_response(subscription)))
+}
+
+/// The organisation's invoices with their lines, newest first
+#[utoipa::path(
+    tag = "billing",
+    security(("user" = [])),
+    params(("id" = Uuid, Path, description = "Organisation id"), PaginationQuery),
+    responses(
+        (status = 200, body = PaginatedResponse<InvoiceResponse>, headers(("Link" = String, description = "`next` and `prev` pages"))),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 422, body = ErrorResponse, content_type = "application/problem+json", description = "Invalid paging parameters"),
+    ),
+)]
+#[get("/{id}/invoices")]
+async fn list_invoices_handler(
+    req: HttpRequest,
+    state: web::Data<State>,
+    user_id: UserId,
+    path: web::Path<Uuid>,
+    query: web::Query<PaginationQuery>,
+) -> Result<HttpResponse, AppError> {
+    let organisation_id = path.into_inner();
+    record_organisation_id(organisation_id);
+
+    cached_member(
+        &state.db,
+        state.cache.as_ref(),
+        state.config.cache.membership_ttl(),
+        organisation_id,
+        user_id.into_inner(),
+    )
+    .await?;
+    let page = list_invoices(&state.db, organisation_id, query.pagination()?).await?;
+
+    Ok(paginated(&req, page.map(|invoice| invoice_response(organisation_id, invoice))))
+}
+
+/// Charges an unpaid invoice again, for owners. Paying the last unpaid invoice lifts a
+/// suspension.
+#[utoipa::path(
+    tag = "billing",
+    security(("user" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Organisation id"),
+        ("invoice_id" = Uuid, Path, description = "Invoice id"),
+    ),
+    responses(
+        (status = 200, body = InvoiceResponse),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 403, body = ErrorResponse, content_type = "application/problem+json", description = "The caller isn't an owner"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 409, body = ErrorResponse, content_type = "application/problem+json", description = "The invoice is already being charged"),
+        (status = 502, body = ErrorResponse, content_type = "application/problem+json", description = "The payment provider declined the charge"),
+    ),
+)]
+#[post("/{id}/invoices/{invoice_id}/pay")]
+async fn pay_invoice_handler(
+    state: web::Data<State>,
+    user_id: UserId,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (organisation_id, invoice_id) = path.into_inner();
+    record_organisation_id(organisation_id);
+
+    let member = cached_member(
+        &state.db,
+        state.cache.as_ref(),
+        state.config.cache.membership_ttl(),
+        organisation_id,
+        user_id.into_inner(),
+    )
+    .await?;
+    require_owner(&member)?;
+    get_organisation(&state.db, organisation_id).await?;
+    let invoice = get_invoice(&state.db, organisation_id, invoice_id).await?;
+    let invoice = charge_invoice(&state.db, state.clock.as_ref(), state.payments.as_ref(), invoice).await?;
+    let lines = invoice_lines(&state.db, invoice.id).await?;
+
+    Ok(HttpResponse::Ok().json(invoice_response(organisation_id, (invoice, lines))))
+}
@@ -1,22 +1,52 @@
 use actix_web::{HttpResponse, Result, get, web};
-use serde::{Serialize};
+use serde::Serialize;
+use utoipa::{OpenApi, ToSchema};
 
-use crate::errors::{AppError};
+use crate::errors::AppError;
+use crate::services::health::{Readiness, check_readiness};
+use crate::state::State;
+
+#[derive(OpenApi)]
+#[openapi(paths(get_liveness_handler, get_readiness_handler))]
+pub(super) struct Api;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(get_health_handler);
+    cfg.service(
+        web::scope("/health")
+            .service(get_liveness_handler)
+            .service(get_readiness_handler),
+    );
 }
 
-#[derive(Serialize)]
-struct GetHealthResponse {
+#[derive(Serialize, ToSchema)]
+struct GetLivenessResponse {
     status: String,
-    message: String,
 }
 
-#[get("/health")]
-async fn get_health_handler() -> Result<HttpResponse, AppError> {
-    Ok(HttpResponse::Ok().json(GetHealthResponse {
-        status: "Running".to_string(),
-        message: "Hello from Rust".to_string(),
+/// The process is up and serving requests, no dependencies are checked
+#[utoipa::path(tag = "health", responses((status = 200, body = GetLivenessResponse)))]
+#[get("/live")]
+async fn get_liveness_handler() -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(GetLivenessResponse {
+        status: "ok".to_string(),
     }))
 }
+
+/// Checks every dependency and returns 503 when a required one is failing
+#[utoipa::path(
+    tag = "health",
+    responses(
+        (status = 200, body = Readiness),
+        (status = 503, body = Readiness),
+    ),
+)]
+#[get("/ready")]
+async fn get_readiness_handler(state: web::Data<State>) -> Result<HttpResponse, AppError> {
+    let readiness = check_readiness(&state.db, state.kratos.as_ref()).await;
+
+    if readiness.ready {
+        Ok(HttpResponse::Ok().json(readiness))
+    } else {
+        Ok(HttpResponse::ServiceUnavailable().json(readiness))
+    }
+}
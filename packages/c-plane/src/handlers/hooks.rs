@@ -2,22 +2,29 @@ use std::str::FromStr;
 
 use actix_web::{HttpResponse, Result, post, web};
 use ory_client::models::Identity;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
+use utoipa::OpenApi;
 use uuid::Uuid;
 
 use crate::{
-    errors::AppError,
+    errors::{AppError, ErrorResponse},
     middleware::api::{ApiKey, ApiMiddleware},
-    services::organisations::{create_organisation, CreateOrganisationData},
-    state::get_app_state,
+    state::State,
 };
+#[cfg(feature = "cloud")]
+use crate::services::organisations::{create_organisation, CreateOrganisationData};
+#[cfg(not(feature = "cloud"))]
+use crate::{models::OrganisationRole, services::instance::join_organisation};
 
 #[derive(Deserialize, Debug)]
 struct IdentityTraits {
+    #[cfg(feature = "cloud")]
     name: Name,
+    #[cfg(not(feature = "cloud"))]
     email: String,
 }
 
+#[cfg(feature = "cloud")]
 #[derive(Deserialize, Debug)]
 struct Name {
     first: String,
@@ -26,10 +33,13 @@ struct Name {
 
 #[derive(Deserialize, Debug)]
 struct AfterRegistrationRequest {
-    flow_id: String,
     identity: Identity,
 }
 
+#[derive(OpenApi)]
+#[openapi(paths(after_registration_handler))]
+pub(super) struct Api;
+
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(web::scope("/hooks")
         .wrap(ApiMiddleware)
@@ -37,16 +47,31 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     );
 }
 
+/// Called by Kratos after a registration. The cloud build gives the new identity an
+/// organisation of its own, a self-hosted one adds it to the instance's organisation,
+/// as its Owner if it registered with the configured owner email.
+#[utoipa::path(
+    tag = "hooks",
+    security(("api_key" = [])),
+    request_body(content = Object, description = "Kratos webhook payload with the new identity"),
+    responses(
+        (status = 200, description = "The identity was set up"),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+    ),
+)]
 #[post("/after-registration")]
 async fn after_registration_handler(
+    state: web::Data<State>,
     payload: web::Json<AfterRegistrationRequest>,
     api_key: ApiKey,
 ) -> Result<HttpResponse, AppError> {
-    let state = get_app_state();
-    if api_key.into_inner() != state.config.kratos_api_key {
+    if !state.config.auth.kratos_api_key.matches(&api_key.into_inner()) {
         return Err(AppError::Unauthorized("Invalid API key".to_string()));
     }
 
+    let identity_id = Uuid::from_str(&payload.identity.id)
+        .map_err(|_| AppError::Internal("Invalid identity ID format".to_string()))?;
+
     let traits = payload
         .identity
         .traits
@@ -54,18 +79,37 @@ async fn after_registration_handler(
         .ok_or_else(|| AppError::Internal("Identity traits not found".to_string()))?;
     let identity_traits: IdentityTraits = serde_json::from_value(traits.clone())
         .map_err(|_| AppError::Internal("Invalid identity traits format".to_string()))?;
-    let identity_id = Uuid::from_str(&payload.identity.id)
-        .map_err(|_| AppError::Internal("Invalid identity ID format".to_string()))?;
-    let data = CreateOrganisationData {
-        identity_id,
-        name: format!(
-            "{} {}",
-            identity_traits.name.first, identity_traits.name.last
-        ),
-        description: None,
-        avatar_url: None,
-    };
 
-    let _organisation = create_organisation(state.db, data).await?;
+    #[cfg(feature = "cloud")]
+    {
+        let data = CreateOrganisationData {
+            identity_id,
+            name: format!(
+                "{} {}",
+                identity_traits.name.first, identity_traits.name.last
+            ),
+            description: None,
+            avatar_url: None,
+        };
+        create_organisation(&state.db, state.clock.as_ref(), data).await?;
+    }
+    #[cfg(not(feature = "cloud"))]
+    {
+        let instance = &state.config.instance;
+        let role = if identity_traits.email.trim().eq_ignore_ascii_case(instance.owner_email.trim()) {
+            OrganisationRole::Owner
+        } else {
+            OrganisationRole::Member
+        };
+        join_organisation(
+            &state.db,
+            state.clock.as_ref(),
+            instance.organisation_id,
+            identity_id,
+            role,
+        )
+        .await?;
+    }
+
     Ok(HttpResponse::Ok().finish())
 }
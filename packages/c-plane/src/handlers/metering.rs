@@ -0,0 +1,93 @@
+#![cfg(feature = "cloud")]
+
+use actix_web::{HttpResponse, Result, post, web};
+use c_plane_client::types::{UsageMetric as MetricResponse, UsageReportRequest, UsageReportResponse};
+use utoipa::OpenApi;
+
+use crate::errors::{AppError, ErrorResponse};
+use crate::middleware::api::{ApiKey, ApiMiddleware};
+use crate::models::entities::UsageMetric;
+use crate::services::billing::{UsageData, record_usage};
+use crate::state::State;
+use crate::utils::validation::{ValidatedJson, Validate, Validator};
+
+/// Records per usage report, agents split larger batches
+const MAX_REPORT_RECORDS: usize = 1000;
+
+impl From<MetricResponse> for UsageMetric {
+    fn from(metric: MetricResponse) -> Self {
+        match metric {
+            MetricResponse::RuntimeSeconds => UsageMetric::RuntimeSeconds,
+            MetricResponse::CpuSeconds => UsageMetric::CpuSeconds,
+            MetricResponse::StorageGbHours => UsageMetric::StorageGbHours,
+        }
+    }
+}
+
+impl Validate for UsageReportRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.check("records", !self.records.is_empty(), "blank", "must not be empty");
+        v.check(
+            "records",
+            self.records.len() <= MAX_REPORT_RECORDS,
+            "length",
+            format!("must hold at most {} records", MAX_REPORT_RECORDS),
+        );
+        for (index, record) in self.records.iter().enumerate() {
+            if record.quantity < 0 {
+                v.fail(format!("records[{}].quantity", index), "range", "must not be negative");
+            }
+        }
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(report_usage_handler))]
+pub(super) struct Api;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/metering")
+        .wrap(ApiMiddleware)
+        .service(report_usage_handler)
+    );
+}
+
+/// Records usage measured by an agent, billed with the month it happened in
+#[utoipa::path(
+    tag = "billing",
+    security(("metering_key" = [])),
+    request_body = UsageReportRequest,
+    responses(
+        (status = 202, body = UsageReportResponse),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json", description = "A record names an unknown project"),
+        (status = 422, body = ErrorResponse, content_type = "application/problem+json", description = "Invalid fields, listed in `errors`"),
+    ),
+)]
+#[post("/usage")]
+async fn report_usage_handler(
+    state: web::Data<State>,
+    request: ValidatedJson<UsageReportRequest>,
+    api_key: ApiKey,
+) -> Result<HttpResponse, AppError> {
+    if !state.config.billing.metering_api_key.matches(&api_key.into_inner()) {
+        return Err(AppError::Unauthorized("Invalid API key".to_string()));
+    }
+
+    let records = request
+        .into_inner()
+        .records
+        .into_iter()
+        .map(|record| UsageData {
+            id: record.id,
+            project_id: record.project_id,
+            deployment_id: record.deployment_id,
+            metric: UsageMetric::from(record.metric),
+            quantity: record.quantity,
+            recorded_at: record.recorded_at,
+        })
+        .collect();
+    let accepted = record_usage(&state.db, state.clock.as_ref(), records).await?;
+
+    Ok(HttpResponse::Accepted().json(UsageReportResponse { accepted }))
+}
@@ -0,0 +1,53 @@
+use actix_web::http::header::{AUTHORIZATION, ContentType};
+use actix_web::{HttpRequest, HttpResponse, Result, get, web};
+use utoipa::OpenApi;
+
+use crate::errors::{AppError, ErrorResponse};
+use crate::state::State;
+use crate::utils::metrics::metrics;
+
+#[derive(OpenApi)]
+#[openapi(paths(get_metrics_handler))]
+pub(super) struct Api;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_metrics_handler);
+}
+
+/// Prometheus scrape endpoint, guarded by `METRICS_TOKEN` when it is set
+#[utoipa::path(
+    tag = "metrics",
+    security((), ("metrics_token" = [])),
+    responses(
+        (status = 200, body = String, content_type = "text/plain"),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json", description = "The endpoint is disabled"),
+    ),
+)]
+#[get("/metrics")]
+async fn get_metrics_handler(
+    state: web::Data<State>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    if !state.config.features.metrics_endpoint {
+        return Err(AppError::NotFound("Metrics endpoint is disabled".to_string()));
+    }
+
+    if let Some(expected) = state.config.telemetry.metrics_token.as_ref() {
+        let token = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "));
+        if !token.is_some_and(|token| expected.matches(token)) {
+            return Err(AppError::Unauthorized("Invalid metrics token".to_string()));
+        }
+    }
+
+    let body = metrics().render(&state.db).await?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType(
+            "text/plain; version=0.0.4; charset=utf-8".parse().unwrap(),
+        ))
+        .body(body))
+}
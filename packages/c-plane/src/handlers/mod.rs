@@ -1,12 +1,26 @@
 use actix_web::web;
 
 mod organisations;
+mod billing;
 mod health;
 mod hooks;
+mod metering;
+mod metrics;
+mod openapi;
+mod projects;
+mod search;
+
+pub use openapi::openapi;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg
         .configure(organisations::config)
+        .configure(projects::config)
+        .configure(search::config)
         .configure(health::config)
-        .configure(hooks::config);
+        .configure(hooks::config)
+        .configure(metrics::config)
+        .configure(openapi::config);
+    #[cfg(feature = "cloud")]
+    cfg.configure(billing::config).configure(metering::config);
 }
@@ -0,0 +1,88 @@
+use actix_web::{HttpResponse, Result, get, web};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_scalar::{Scalar, Servable};
+
+use super::{health, hooks, metrics, organisations, projects, search};
+#[cfg(feature = "cloud")]
+use super::{billing, metering};
+
+/// The whole API, assembled from the document each handler module keeps for its own
+/// routes and nested under the scope it is mounted at
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "c-plane", description = "Control plane API"),
+    nest(
+        (path = "/organisations", api = organisations::Api),
+        (path = "/projects", api = projects::Api),
+        (path = "/search", api = search::Api),
+        (path = "/health", api = health::Api),
+        (path = "/hooks", api = hooks::Api),
+    ),
+    modifiers(&SecuritySchemes),
+)]
+struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "user",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "X-User",
+                "Identity id set by the gateway after authenticating the session",
+            ))),
+        );
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "X-API-KEY",
+                "Key shared with Kratos for its webhooks",
+            ))),
+        );
+        #[cfg(feature = "cloud")]
+        components.add_security_scheme(
+            "metering_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "X-API-KEY",
+                "Key agents send with usage reports",
+            ))),
+        );
+        components.add_security_scheme(
+            "metrics_token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// The OpenAPI document for every route the server exposes
+pub fn openapi() -> utoipa::openapi::OpenApi {
+    let mut openapi = ApiDoc::openapi();
+    // Mounted at the root, so there is no scope to nest it under
+    openapi.merge(metrics::Api::openapi());
+    openapi.merge(Api::openapi());
+    #[cfg(feature = "cloud")]
+    let openapi = openapi
+        .nest("/organisations", organisations::CloudApi::openapi())
+        .nest("/billing", billing::Api::openapi())
+        .nest("/metering", metering::Api::openapi());
+    openapi
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(get_openapi_handler))]
+struct Api;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_openapi_handler)
+        .service(Scalar::with_url("/docs", openapi()));
+}
+
+/// This document
+#[utoipa::path(tag = "docs", responses((status = 200, description = "OpenAPI 3.1 document")))]
+#[get("/openapi.json")]
+async fn get_openapi_handler() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(openapi()))
+}
@@ -1,30 +1,42 @@
-use actix_web::{HttpResponse, Result, get, post, web};
-use chrono::NaiveDateTime;
-use sea_orm::{DatabaseConnection, EntityTrait};
-use serde::{Deserialize, Serialize};
+use actix_web::{HttpRequest, HttpResponse, Result, delete, get, post, web};
+use c_plane_client::types::{
+    MemberResponse, OrganisationMemberResponse, OrganisationResponse,
+    OrganisationRole as RoleResponse,
+};
+#[cfg(feature = "cloud")]
+use c_plane_client::types::{
+    CreateOrganisationRequest, CreateOrganisationResponse, OrganisationUsageResponse, Plan as PlanResponse, PlanLimits as PlanLimitsResponse,
+    ResourceUsage,
+};
+use futures_util::future::join_all;
+use utoipa::OpenApi;
 use uuid::Uuid;
 
-use crate::errors::{AppError, OrganisationError};
-use crate::models::entities::OrganisationMemberModel;
+use crate::errors::{AppError, ErrorResponse};
+#[cfg(not(feature = "cloud"))]
+use crate::errors::OrganisationError;
+use crate::log_warn;
+use crate::models::entities::{OrganisationMember, OrganisationMemberModel};
 use crate::models::entities::OrganisationModel;
-use crate::models::{OrganisationRole, organisation_member};
+use crate::models::OrganisationRole;
+#[cfg(feature = "cloud")]
+use crate::models::entities::{Plan, PlanLimits};
+use crate::services::kratos::{IdentityDetails, identity_details};
 use crate::services::organisations::{
-    CreateOrganisationData, create_organisation, get_organisation,
+    cached_member, delete_organisation, get_organisation, list_members, list_organisations,
+    restore_organisation,
 };
+#[cfg(feature = "cloud")]
+use crate::services::organisations::{CreateOrganisationData, create_organisation};
+#[cfg(feature = "cloud")]
+use crate::services::plans::{Usage, usage};
 use crate::middleware::auth::{UserId, AuthMiddleware};
-use crate::state::get_app_state;
-
-#[derive(Serialize, Deserialize)]
-struct OrganisationResponse {
-    pub id: Uuid,
-    pub name: String,
-    pub description: Option<String>,
-    pub avatar_url: Option<String>,
-    pub is_active: bool,
-    pub created_at: NaiveDateTime,
-    pub updated_at: NaiveDateTime,
-    pub created_by: Uuid,
-}
+use crate::state::State;
+use crate::utils::listing::ListQuery;
+use crate::utils::logger::record_organisation_id;
+use crate::utils::pagination::{PaginatedResponse, PaginationQuery, paginated};
+#[cfg(feature = "cloud")]
+use crate::utils::validation::{ValidatedJson, Validate, Validator};
 
 impl From<OrganisationModel> for OrganisationResponse {
     fn from(organisation: OrganisationModel) -> Self {
@@ -41,14 +53,59 @@ impl From<OrganisationModel> for OrganisationResponse {
     }
 }
 
-#[derive(Serialize, Deserialize)]
-struct OrganisationMemberResponse {
-    pub id: Uuid,
-    pub organisation_id: Uuid,
-    pub role: OrganisationRole,
-    pub is_active: bool,
-    pub joined_at: NaiveDateTime,
-    pub invitation_accepted_at: NaiveDateTime,
+impl From<OrganisationRole> for RoleResponse {
+    fn from(role: OrganisationRole) -> Self {
+        match role {
+            OrganisationRole::Owner => RoleResponse::Owner,
+            OrganisationRole::Admin => RoleResponse::Admin,
+            OrganisationRole::Member => RoleResponse::Member,
+            OrganisationRole::Viewer => RoleResponse::Viewer,
+        }
+    }
+}
+
+#[cfg(feature = "cloud")]
+impl From<Plan> for PlanResponse {
+    fn from(plan: Plan) -> Self {
+        match plan {
+            Plan::Free => PlanResponse::Free,
+            Plan::Team => PlanResponse::Team,
+            Plan::Enterprise => PlanResponse::Enterprise,
+            Plan::Custom => PlanResponse::Custom,
+        }
+    }
+}
+
+#[cfg(feature = "cloud")]
+impl From<PlanLimits> for PlanLimitsResponse {
+    fn from(limits: PlanLimits) -> Self {
+        Self {
+            projects: limits.projects,
+            members: limits.members,
+            deployments: limits.deployments,
+            agents: limits.agents,
+            api_keys: limits.api_keys,
+            log_retention_days: limits.log_retention_days,
+        }
+    }
+}
+
+#[cfg(feature = "cloud")]
+impl From<Usage> for OrganisationUsageResponse {
+    fn from(usage: Usage) -> Self {
+        Self {
+            plan: PlanResponse::from(usage.plan),
+            projects: ResourceUsage {
+                used: usage.projects,
+                limit: usage.limits.projects,
+            },
+            members: ResourceUsage {
+                used: usage.members,
+                limit: usage.limits.members,
+            },
+            limits: PlanLimitsResponse::from(usage.limits),
+        }
+    }
 }
 
 impl From<OrganisationMemberModel> for OrganisationMemberResponse {
@@ -56,7 +113,7 @@ impl From<OrganisationMemberModel> for OrganisationMemberResponse {
         Self {
             id: organisation_member.id,
             organisation_id: organisation_member.organisation_id,
-            role: organisation_member.role,
+            role: RoleResponse::from(organisation_member.role),
             is_active: organisation_member.is_active,
             joined_at: organisation_member.joined_at,
             invitation_accepted_at: organisation_member.invitation_accepted_at,
@@ -64,38 +121,103 @@ impl From<OrganisationMemberModel> for OrganisationMemberResponse {
     }
 }
 
-#[derive(Serialize, Deserialize)]
-struct CreateOrganisationRequest {
-    pub name: String,
-    pub description: Option<String>,
-    pub avatar_url: Option<String>,
+fn member_response(member: OrganisationMemberModel, identity: Option<IdentityDetails>) -> MemberResponse {
+    let (name, email) = identity.map_or((None, None), |identity| (identity.name, identity.email));
+    MemberResponse {
+        identity_id: member.identity_id,
+        role: RoleResponse::from(member.role),
+        joined_at: member.joined_at,
+        name,
+        email,
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-struct CreateOrganisationResponse {
-    pub organisation: OrganisationResponse,
-    pub organisation_member: OrganisationMemberResponse,
+#[cfg(feature = "cloud")]
+impl Validate for CreateOrganisationRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.string("name", &self.name).not_blank().length(1, 100);
+        v.optional("description", self.description.as_deref()).max_length(2000);
+        v.optional("avatar_url", self.avatar_url.as_deref()).url().max_length(2048);
+    }
 }
 
+#[derive(OpenApi)]
+#[openapi(paths(
+    list_organisations_handler,
+    get_organisation_handler,
+    list_members_handler,
+    delete_organisation_handler,
+    restore_organisation_handler,
+))]
+pub(super) struct Api;
+
+/// Self-hosted installs have a single organisation, set up by the instance bootstrap,
+/// and no plans, so creating organisations and reading usage are `cloud` only
+#[cfg(feature = "cloud")]
+#[derive(OpenApi)]
+#[openapi(paths(create_organisation_handler, get_organisation_usage_handler))]
+pub(super) struct CloudApi;
+
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(
-        web::scope("/organisations")
-            .wrap(AuthMiddleware)
-            .service(create_organisation_handler)
-            .service(get_organisation_handler)
-    );
+    let scope = web::scope("/organisations")
+        .wrap(AuthMiddleware)
+        .service(list_organisations_handler)
+        .service(get_organisation_handler)
+        .service(list_members_handler)
+        .service(delete_organisation_handler)
+        .service(restore_organisation_handler);
+    #[cfg(feature = "cloud")]
+    let scope = scope
+        .service(create_organisation_handler)
+        .service(get_organisation_usage_handler);
+    cfg.service(scope);
 }
 
+/// Lists the organisations the caller is a member of, newest first
+#[utoipa::path(
+    tag = "organisations",
+    security(("user" = [])),
+    params(PaginationQuery),
+    responses(
+        (status = 200, body = PaginatedResponse<OrganisationResponse>, headers(("Link" = String, description = "`next` and `prev` pages"))),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 422, body = ErrorResponse, content_type = "application/problem+json", description = "Invalid paging parameters"),
+    ),
+)]
+#[get("")]
+async fn list_organisations_handler(
+    req: HttpRequest,
+    state: web::Data<State>,
+    user_id: UserId,
+    query: web::Query<PaginationQuery>,
+) -> Result<HttpResponse, AppError> {
+    let page = list_organisations(&state.db, user_id.into_inner(), query.pagination()?).await?;
+    Ok(paginated(&req, page.map(OrganisationResponse::from)))
+}
+
+/// Creates an organisation with the caller as its owner
+#[utoipa::path(
+    tag = "organisations",
+    security(("user" = [])),
+    responses(
+        (status = 200, body = CreateOrganisationResponse),
+        (status = 400, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 422, body = ErrorResponse, content_type = "application/problem+json", description = "Invalid fields, listed in `errors`"),
+    ),
+)]
+#[cfg(feature = "cloud")]
 #[post("/")]
 async fn create_organisation_handler(
-    request: web::Json<CreateOrganisationRequest>,
+    state: web::Data<State>,
+    request: ValidatedJson<CreateOrganisationRequest>,
     user_id: UserId,
 ) -> Result<HttpResponse, AppError> {
     let created_by = user_id.into_inner();
-    let state = get_app_state();
 
     let result = create_organisation(
-        state.db,
+        &state.db,
+        state.clock.as_ref(),
         CreateOrganisationData {
             identity_id: created_by,
             name: request.name.clone(),
@@ -107,6 +229,7 @@ async fn create_organisation_handler(
 
     match result {
         Ok((organisation, organisation_member)) => {
+            record_organisation_id(organisation.id);
             Ok(HttpResponse::Ok().json(CreateOrganisationResponse {
                 organisation: OrganisationResponse::from(organisation),
                 organisation_member: OrganisationMemberResponse::from(organisation_member),
@@ -116,17 +239,198 @@ async fn create_organisation_handler(
     }
 }
 
+/// Fetches an organisation the caller is a member of
+#[utoipa::path(
+    tag = "organisations",
+    security(("user" = [])),
+    params(("id" = Uuid, Path, description = "Organisation id")),
+    responses(
+        (status = 200, body = OrganisationResponse),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json"),
+    ),
+)]
 #[get("/{id}")]
 pub async fn get_organisation_handler(
-    db: web::Data<DatabaseConnection>,
+    state: web::Data<State>,
+    user_id: UserId,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let organisation_id = path.into_inner();
+    record_organisation_id(organisation_id);
+
+    cached_member(
+        &state.db,
+        state.cache.as_ref(),
+        state.config.cache.membership_ttl(),
+        organisation_id,
+        user_id.into_inner(),
+    )
+    .await?;
+    let organisation = get_organisation(&state.db, organisation_id).await?;
+
+    Ok(HttpResponse::Ok().json(OrganisationResponse::from(organisation)))
+}
+
+/// Lists the organisation's active members, most recently joined first, with their
+/// names and emails from Kratos
+///
+/// Filters: `role`, and `is_active`, where `false` lists former members instead.
+/// Sorts: `created_at`, when the member joined.
+#[utoipa::path(
+    tag = "organisations",
+    security(("user" = [])),
+    params(("id" = Uuid, Path, description = "Organisation id"), PaginationQuery, ListQuery),
+    responses(
+        (status = 200, body = PaginatedResponse<MemberResponse>, headers(("Link" = String, description = "`next` and `prev` pages"))),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 422, body = ErrorResponse, content_type = "application/problem+json", description = "Invalid filters, sorts or cursors"),
+    ),
+)]
+#[get("/{id}/members")]
+async fn list_members_handler(
+    req: HttpRequest,
+    state: web::Data<State>,
+    user_id: UserId,
+    path: web::Path<Uuid>,
+    query: web::Query<PaginationQuery>,
+    list: ListQuery,
+) -> Result<HttpResponse, AppError> {
+    let organisation_id = path.into_inner();
+    record_organisation_id(organisation_id);
+    let list = list.resolve::<OrganisationMember>(&query)?;
+
+    cached_member(
+        &state.db,
+        state.cache.as_ref(),
+        state.config.cache.membership_ttl(),
+        organisation_id,
+        user_id.into_inner(),
+    )
+    .await?;
+    let page = list_members(&state.db, organisation_id, list).await?;
+
+    // Members are still listed while Kratos is down, without what it knows
+    let identities = join_all(page.data.iter().map(|member| {
+        identity_details(
+            state.kratos.as_ref(),
+            state.cache.as_ref(),
+            state.config.cache.identity_ttl(),
+            member.identity_id,
+        )
+    }))
+    .await;
+    let mut identities = identities.into_iter().map(|identity| {
+        identity
+            .inspect_err(|err| log_warn!("Listing a member without identity details: {}", err))
+            .ok()
+    });
+    Ok(paginated(&req, page.map(|member| member_response(member, identities.next().flatten()))))
+}
+
+/// The organisation's plan, its limits and how much of them is used
+#[cfg(feature = "cloud")]
+#[utoipa::path(
+    tag = "organisations",
+    security(("user" = [])),
+    params(("id" = Uuid, Path, description = "Organisation id")),
+    responses(
+        (status = 200, body = OrganisationUsageResponse),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json"),
+    ),
+)]
+#[get("/{id}/usage")]
+async fn get_organisation_usage_handler(
+    state: web::Data<State>,
+    user_id: UserId,
     path: web::Path<Uuid>,
 ) -> Result<HttpResponse, AppError> {
-    // let organisation_id = path.into_inner();
+    let organisation_id = path.into_inner();
+    record_organisation_id(organisation_id);
 
-    // let organisation = Organisation::find_by_id(organisation_id)
-    //     .one(&**db)
-    //     .await?
-    //     .ok_or_else(|| AppError::Organisation(OrganisationError::OrganisationNotFound(organisation_id)))?;
+    cached_member(
+        &state.db,
+        state.cache.as_ref(),
+        state.config.cache.membership_ttl(),
+        organisation_id,
+        user_id.into_inner(),
+    )
+    .await?;
+    let organisation = get_organisation(&state.db, organisation_id).await?;
+    let usage = usage(&state.db, organisation).await?;
 
-    Ok(HttpResponse::Ok().finish())
+    Ok(HttpResponse::Ok().json(OrganisationUsageResponse::from(usage)))
+}
+
+/// Deletes an organisation, hiding its projects along with it. A self-hosted install's
+/// only organisation can't be deleted.
+#[utoipa::path(
+    tag = "organisations",
+    security(("user" = [])),
+    params(("id" = Uuid, Path, description = "Organisation id")),
+    responses(
+        (status = 204, description = "The organisation was deleted and can be restored until the retention period ends"),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 403, body = ErrorResponse, content_type = "application/problem+json", description = "The caller isn't an owner"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 409, body = ErrorResponse, content_type = "application/problem+json", description = "The organisation is the self-hosted instance's"),
+    ),
+)]
+#[delete("/{id}")]
+async fn delete_organisation_handler(
+    state: web::Data<State>,
+    user_id: UserId,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let organisation_id = path.into_inner();
+    record_organisation_id(organisation_id);
+    #[cfg(not(feature = "cloud"))]
+    if organisation_id == state.config.instance.organisation_id {
+        return Err(AppError::Organisation(OrganisationError::CannotDeleteInstanceOrganisation));
+    }
+
+    delete_organisation(
+        &state.db,
+        state.clock.as_ref(),
+        state.cache.as_ref(),
+        state.config.cache.membership_ttl(),
+        user_id.into_inner(),
+        organisation_id,
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Restores a deleted organisation together with its projects, for its owners
+#[utoipa::path(
+    tag = "organisations",
+    security(("user" = [])),
+    params(("id" = Uuid, Path, description = "Organisation id")),
+    responses(
+        (status = 200, body = OrganisationResponse),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 403, body = ErrorResponse, content_type = "application/problem+json", description = "The caller isn't an owner"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json", description = "The organisation doesn't exist or its retention period has ended"),
+    ),
+)]
+#[post("/{id}/restore")]
+async fn restore_organisation_handler(
+    state: web::Data<State>,
+    user_id: UserId,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let organisation_id = path.into_inner();
+    record_organisation_id(organisation_id);
+
+    let organisation = restore_organisation(
+        &state.db,
+        state.clock.as_ref(),
+        state.config.retention.deleted_days,
+        user_id.into_inner(),
+        organisation_id,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(OrganisationResponse::from(organisation)))
 }
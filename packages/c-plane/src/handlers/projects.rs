@@ -0,0 +1,313 @@
+use actix_web::{HttpRequest, HttpResponse, Result, delete, get, post, put, web};
+use c_plane_client::types::{
+    CreateProjectRequest, ProjectResponse, TransferProjectRequest, UpdateProjectRequest,
+};
+use utoipa::OpenApi;
+use uuid::Uuid;
+
+use crate::errors::{AppError, ErrorResponse};
+use crate::middleware::auth::{AuthMiddleware, UserId};
+use crate::models::entities::{Project, ProjectModel};
+use crate::services::projects::{
+    CreateProjectData, UpdateProjectData, create_project, delete_project, get_project,
+    list_projects, restore_project, transfer_project, update_project,
+};
+use crate::state::State;
+use crate::utils::listing::ListQuery;
+use crate::utils::pagination::{PaginatedResponse, PaginationQuery, paginated};
+use crate::utils::validation::{ValidatedJson, Validate, Validator, is_slug};
+
+impl Validate for CreateProjectRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.string("name", &self.name).not_blank().length(1, 100);
+        v.string("slug", &self.slug)
+            .length(1, 63)
+            .pattern(is_slug, "slug", "lowercase letters and digits separated by hyphens");
+        v.optional("description", self.description.as_deref()).max_length(2000);
+    }
+}
+
+impl Validate for UpdateProjectRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.check(
+            "body",
+            self.name.is_some() || self.description.is_some() || self.is_archived.is_some(),
+            "empty_update",
+            "at least one field must be given",
+        );
+        v.optional("name", self.name.as_deref()).not_blank().length(1, 100);
+        v.optional("description", self.description.as_ref().and_then(|d| d.as_deref()))
+            .max_length(2000);
+    }
+}
+
+impl Validate for TransferProjectRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.check("organisation_id", !self.organisation_id.is_nil(), "blank", "must not be blank");
+    }
+}
+
+impl From<ProjectModel> for ProjectResponse {
+    fn from(project: ProjectModel) -> Self {
+        Self {
+            id: project.id,
+            name: project.name,
+            description: project.description,
+            organisation_id: project.organisation_id,
+            owner_id: project.owner_id,
+            is_archived: project.is_archived,
+            is_public: project.is_public,
+            created_at: project.created_at,
+            updated_at: project.updated_at,
+        }
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    list_projects_handler,
+    create_project_handler,
+    get_project_handler,
+    update_project_handler,
+    delete_project_handler,
+    restore_project_handler,
+    transfer_project_handler,
+))]
+pub(super) struct Api;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/projects")
+            .wrap(AuthMiddleware)
+            .service(list_projects_handler)
+            .service(create_project_handler)
+            .service(get_project_handler)
+            .service(update_project_handler)
+            .service(delete_project_handler)
+            .service(restore_project_handler)
+            .service(transfer_project_handler),
+    );
+}
+
+/// Lists projects in the organisations the caller belongs to
+///
+/// Filters: `is_archived`, `is_public`, `organisation_id`, `owner_id`. Sorts:
+/// `created_at`, `updated_at`, `name`. `q` searches the name and description.
+#[utoipa::path(
+    tag = "projects",
+    security(("user" = [])),
+    params(PaginationQuery, ListQuery),
+    responses(
+        (status = 200, body = PaginatedResponse<ProjectResponse>, headers(("Link" = String, description = "`next` and `prev` pages"))),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 422, body = ErrorResponse, content_type = "application/problem+json", description = "Invalid filters, sorts or cursors"),
+    ),
+)]
+#[get("")]
+async fn list_projects_handler(
+    req: HttpRequest,
+    state: web::Data<State>,
+    user_id: UserId,
+    query: web::Query<PaginationQuery>,
+    list: ListQuery,
+) -> Result<HttpResponse, AppError> {
+    let list = list.resolve::<Project>(&query)?;
+    let page = list_projects(&state.db, user_id.into_inner(), list).await?;
+    Ok(paginated(&req, page.map(ProjectResponse::from)))
+}
+
+/// Creates a project in an organisation the caller belongs to
+#[utoipa::path(
+    tag = "projects",
+    security(("user" = [])),
+    responses(
+        (status = 201, body = ProjectResponse),
+        (status = 400, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 402, body = ErrorResponse, content_type = "application/problem+json", description = "In the cloud, the organisation's subscription is suspended until its unpaid invoice is paid"),
+        (status = 403, body = ErrorResponse, content_type = "application/problem+json", description = "The organisation's plan has no room for another project"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json", description = "The organisation doesn't exist or the caller isn't a member"),
+        (status = 422, body = ErrorResponse, content_type = "application/problem+json", description = "Invalid fields, listed in `errors`"),
+    ),
+)]
+#[post("")]
+async fn create_project_handler(
+    state: web::Data<State>,
+    user_id: UserId,
+    request: ValidatedJson<CreateProjectRequest>,
+) -> Result<HttpResponse, AppError> {
+    let request = request.into_inner();
+    let project = create_project(
+        &state.db,
+        state.clock.as_ref(),
+        user_id.into_inner(),
+        CreateProjectData {
+            name: request.name,
+            description: request.description,
+            organisation_id: request.organisation_id,
+            owner_id: request.owner_id,
+            is_public: request.is_public,
+        },
+    )
+    .await?;
+
+    Ok(HttpResponse::Created().json(ProjectResponse::from(project)))
+}
+
+#[utoipa::path(
+    tag = "projects",
+    security(("user" = [])),
+    params(("id" = Uuid, Path, description = "Project id")),
+    responses(
+        (status = 200, body = ProjectResponse),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json"),
+    ),
+)]
+#[get("/{id}")]
+async fn get_project_handler(
+    state: web::Data<State>,
+    user_id: UserId,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let project = get_project(&state.db, user_id.into_inner(), path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ProjectResponse::from(project)))
+}
+
+/// Updates the given fields, leaving the others as they are, for Admins and Owners
+///
+/// Archived projects are read-only: other fields only change together with
+/// `"is_archived": false`. Public projects can't be archived.
+#[utoipa::path(
+    tag = "projects",
+    security(("user" = [])),
+    params(("id" = Uuid, Path, description = "Project id")),
+    responses(
+        (status = 200, body = ProjectResponse),
+        (status = 400, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 402, body = ErrorResponse, content_type = "application/problem+json", description = "In the cloud, unarchiving waits for the organisation's suspended subscription to be paid up"),
+        (status = 403, body = ErrorResponse, content_type = "application/problem+json", description = "The caller isn't Admin or Owner"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 409, body = ErrorResponse, content_type = "application/problem+json", description = "The project is archived, or public and can't be archived"),
+        (status = 422, body = ErrorResponse, content_type = "application/problem+json", description = "Invalid fields, listed in `errors`"),
+    ),
+)]
+#[put("/{id}")]
+async fn update_project_handler(
+    state: web::Data<State>,
+    user_id: UserId,
+    path: web::Path<Uuid>,
+    request: ValidatedJson<UpdateProjectRequest>,
+) -> Result<HttpResponse, AppError> {
+    let request = request.into_inner();
+    let project = update_project(
+        &state.db,
+        state.clock.as_ref(),
+        user_id.into_inner(),
+        path.into_inner(),
+        UpdateProjectData {
+            name: request.name,
+            description: request.description,
+            is_archived: request.is_archived,
+        },
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(ProjectResponse::from(project)))
+}
+
+/// Deletes the project, for Admins and Owners of its organisation
+#[utoipa::path(
+    tag = "projects",
+    security(("user" = [])),
+    params(("id" = Uuid, Path, description = "Project id")),
+    responses(
+        (status = 204, description = "The project was deleted and can be restored until the retention period ends"),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 403, body = ErrorResponse, content_type = "application/problem+json", description = "The caller isn't Admin or Owner"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 409, body = ErrorResponse, content_type = "application/problem+json", description = "The project is archived"),
+    ),
+)]
+#[delete("/{id}")]
+async fn delete_project_handler(
+    state: web::Data<State>,
+    user_id: UserId,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    delete_project(
+        &state.db,
+        state.clock.as_ref(),
+        user_id.into_inner(),
+        path.into_inner(),
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Restores a deleted project, for owners of its organisation
+#[utoipa::path(
+    tag = "projects",
+    security(("user" = [])),
+    params(("id" = Uuid, Path, description = "Project id")),
+    responses(
+        (status = 200, body = ProjectResponse),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 402, body = ErrorResponse, content_type = "application/problem+json", description = "In the cloud, the organisation's subscription is suspended until its unpaid invoice is paid"),
+        (status = 403, body = ErrorResponse, content_type = "application/problem+json", description = "The caller isn't an owner of the organisation, or its plan has no room for the project"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json", description = "The project doesn't exist or its retention period has ended"),
+    ),
+)]
+#[post("/{id}/restore")]
+async fn restore_project_handler(
+    state: web::Data<State>,
+    user_id: UserId,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let project = restore_project(
+        &state.db,
+        state.clock.as_ref(),
+        state.config.retention.deleted_days,
+        user_id.into_inner(),
+        path.into_inner(),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(ProjectResponse::from(project)))
+}
+
+/// Moves a project to another organisation
+///
+/// The caller has to be Admin or Owner in both organisations. Archived projects
+/// can't be moved.
+#[utoipa::path(
+    tag = "projects",
+    security(("user" = [])),
+    params(("id" = Uuid, Path, description = "Project id")),
+    responses(
+        (status = 200, body = ProjectResponse),
+        (status = 400, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 403, body = ErrorResponse, content_type = "application/problem+json", description = "The caller isn't Admin or Owner in one of the organisations, or the destination's plan has no room for the project"),
+        (status = 404, body = ErrorResponse, content_type = "application/problem+json", description = "The project or the destination organisation doesn't exist, or the caller isn't a member"),
+        (status = 409, body = ErrorResponse, content_type = "application/problem+json", description = "The project is archived"),
+        (status = 422, body = ErrorResponse, content_type = "application/problem+json", description = "Invalid fields, listed in `errors`"),
+    ),
+)]
+#[post("/{id}/transfer")]
+async fn transfer_project_handler(
+    state: web::Data<State>,
+    user_id: UserId,
+    path: web::Path<Uuid>,
+    request: ValidatedJson<TransferProjectRequest>,
+) -> Result<HttpResponse, AppError> {
+    let project = transfer_project(
+        &state.db,
+        state.clock.as_ref(),
+        user_id.into_inner(),
+        path.into_inner(),
+        request.into_inner().organisation_id,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(ProjectResponse::from(project)))
+}
@@ -0,0 +1,74 @@
+use actix_web::{HttpResponse, Result, get, web};
+use c_plane_client::types::{SearchResponse, SearchResult, SearchResultType};
+use serde::Deserialize;
+use utoipa::{IntoParams, OpenApi};
+
+use crate::errors::{AppError, ErrorResponse};
+use crate::middleware::auth::{AuthMiddleware, UserId};
+use crate::services::search::{SearchHit, search, search_terms};
+use crate::state::State;
+use crate::utils::validation::Validator;
+
+#[derive(Deserialize, IntoParams)]
+pub struct SearchQuery {
+    /// Words to look for, each also matching as a prefix
+    pub q: String,
+    /// Defaults to 20, at most 50
+    pub limit: Option<u64>,
+}
+
+impl From<SearchHit> for SearchResult {
+    fn from(hit: SearchHit) -> Self {
+        Self {
+            result_type: match hit.kind.as_str() {
+                "organisation" => SearchResultType::Organisation,
+                _ => SearchResultType::Project,
+            },
+            id: hit.id,
+            name: hit.name,
+            description: hit.description,
+            organisation_id: hit.organisation_id,
+            rank: hit.rank,
+        }
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(search_handler))]
+pub(super) struct Api;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/search").wrap(AuthMiddleware).service(search_handler));
+}
+
+/// Searches the organisations the caller belongs to and their projects
+#[utoipa::path(
+    tag = "search",
+    security(("user" = [])),
+    params(SearchQuery),
+    responses(
+        (status = 200, body = SearchResponse),
+        (status = 401, body = ErrorResponse, content_type = "application/problem+json"),
+        (status = 422, body = ErrorResponse, content_type = "application/problem+json", description = "`q` has no words or is too long"),
+    ),
+)]
+#[get("")]
+async fn search_handler(
+    state: web::Data<State>,
+    user_id: UserId,
+    query: web::Query<SearchQuery>,
+) -> Result<HttpResponse, AppError> {
+    let terms = search_terms(&query.q);
+
+    let mut v = Validator::default();
+    v.string("q", &query.q).max_length(200);
+    v.check("q", !terms.is_empty(), "blank", "must contain a word to search for");
+    v.finish()?;
+
+    let limit = query.limit.unwrap_or(20).clamp(1, 50);
+    let hits = search(&state.db, user_id.into_inner(), &terms, limit).await?;
+
+    Ok(HttpResponse::Ok().json(SearchResponse {
+        results: hits.into_iter().map(SearchResult::from).collect(),
+    }))
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use sea_orm::{ColumnTrait, Condition, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use super::{Job, JobContext};
+use crate::config::Prices;
+use crate::errors::JobError;
+use crate::models::entities::{Invoice, InvoiceStatus, invoice};
+use crate::services::billing::{
+    PaymentProvider, abandoned_charge, charge_invoice, invoice_period, previous_month,
+};
+use crate::{log_info, log_warn};
+
+/// Monthly at 04:00 UTC on the first, once the previous month's usage is in
+pub const INVOICE_SCHEDULE: &str = "0 0 4 1 * *";
+
+/// Invoices the previous month's usage and charges every open invoice. Invoices left
+/// open by an interrupted run are charged by the retry, as are charges it abandoned.
+/// Failed charges wait for the organisation to pay them.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InvoiceUsage {
+    pub prices: Prices,
+}
+
+impl Job for InvoiceUsage {
+    const KIND: &'static str = "billing.invoice_usage";
+
+    async fn run(self, ctx: JobContext) -> Result<(), JobError> {
+        let payments = ctx.resources.get::<Arc<dyn PaymentProvider>>()?;
+        let (start, end) = previous_month(ctx.clock.now());
+        invoice_period(&ctx.db, ctx.clock.as_ref(), &self.prices, start, end).await?;
+
+        let open = Invoice::find()
+            .filter(
+                Condition::any()
+                    .add(invoice::Column::Status.eq(InvoiceStatus::Open))
+                    .add(abandoned_charge(ctx.clock.now())),
+            )
+            .all(&ctx.db)
+            .await?;
+        let mut failed = 0;
+        for invoice in open {
+            let invoice_id = invoice.id;
+            if let Err(err) = charge_invoice(&ctx.db, ctx.clock.as_ref(), payments.as_ref(), invoice).await {
+                log_warn!("Charging invoice {} failed: {}", invoice_id, err);
+                failed += 1;
+            }
+        }
+
+        log_info!("Charged the open invoices, {} payments failed", failed);
+        Ok(())
+    }
+}
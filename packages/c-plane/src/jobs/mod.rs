@@ -0,0 +1,261 @@
+#[cfg(feature = "cloud")]
+pub mod billing;
+pub mod prune;
+pub mod purge;
+pub mod schedule;
+pub mod worker;
+
+use std::any::{Any, TypeId, type_name};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set, TryInsertResult,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+
+use crate::errors::{AppError, JobError};
+use crate::models::entities::job::Column;
+use crate::models::entities::{Job as JobEntity, JobActiveModel, JobModel, JobStatus};
+use crate::state::State;
+use crate::utils::clock::{Clock, SystemClock};
+
+pub use schedule::RecurringJob;
+pub use worker::{WorkerConfig, WorkerPool};
+
+/// A typed unit of background work, persisted as JSON in the `jobs` table
+pub trait Job: Serialize + DeserializeOwned + Send + 'static {
+    /// Identifier stored in `jobs.kind`, must be unique across registered jobs
+    const KIND: &'static str;
+
+    /// Attempts before the job is marked as failed
+    const MAX_ATTEMPTS: i32 = 5;
+
+    fn run(self, ctx: JobContext) -> impl Future<Output = Result<(), JobError>> + Send;
+}
+
+/// Passed to every job run
+#[derive(Clone)]
+pub struct JobContext {
+    pub db: DatabaseConnection,
+    pub job_id: Uuid,
+    pub attempt: i32,
+    pub resources: Arc<Resources>,
+    /// Time as the server sees it, rather than `Utc::now()`, so tests can pin it
+    pub clock: Arc<dyn Clock>,
+}
+
+/// Services jobs look up by type, for what can't travel in a payload such as clients
+/// of external APIs. Provided to the registry when the server starts.
+#[derive(Clone, Default)]
+pub struct Resources(HashMap<TypeId, Arc<dyn Any + Send + Sync>>);
+
+impl Resources {
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) {
+        self.0.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self) -> Result<&T, JobError> {
+        self.0
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+            .ok_or_else(|| JobError::ExecutionFailed(format!("{} was not provided", type_name::<T>())))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EnqueueOptions {
+    /// Earliest time the job may run, defaults to now
+    pub run_at: Option<DateTime<Utc>>,
+    /// Skips the enqueue while another pending or running job holds the same key
+    pub unique_key: Option<String>,
+    /// Overrides `Job::MAX_ATTEMPTS`
+    pub max_attempts: Option<i32>,
+}
+
+type JobHandler =
+    Arc<dyn Fn(serde_json::Value, JobContext) -> BoxFuture<'static, Result<(), JobError>> + Send + Sync>;
+
+/// Maps job kinds to their handlers and holds the recurring schedules
+#[derive(Clone)]
+pub struct JobRegistry {
+    handlers: HashMap<&'static str, JobHandler>,
+    recurring: Vec<RecurringJob>,
+    resources: Arc<Resources>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for JobRegistry {
+    fn default() -> Self {
+        Self {
+            handlers: HashMap::new(),
+            recurring: Vec::new(),
+            resources: Arc::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<J: Job>(&mut self) -> &mut Self {
+        let handler: JobHandler = Arc::new(|payload, ctx| {
+            Box::pin(async move {
+                let job: J = serde_json::from_value(payload)
+                    .map_err(|e| JobError::InvalidPayload(e.to_string()))?;
+                job.run(ctx).await
+            })
+        });
+        self.handlers.insert(J::KIND, handler);
+        self
+    }
+
+    /// Registers `J` and enqueues `job` on every tick of the cron `expression`
+    /// (seconds resolution, e.g. `0 0 3 * * *` for 03:00 UTC daily)
+    pub fn recurring<J: Job>(&mut self, expression: &str, job: J) -> Result<&mut Self, JobError> {
+        let recurring = RecurringJob::new::<J>(expression, &job)?;
+        self.register::<J>();
+        self.recurring.push(recurring);
+        Ok(self)
+    }
+
+    /// Makes `value` available to jobs through `JobContext::resources`
+    pub fn provide<T: Any + Send + Sync>(&mut self, value: T) -> &mut Self {
+        Arc::make_mut(&mut self.resources).insert(value);
+        self
+    }
+
+    /// Replaces the system clock jobs read through `JobContext::clock`
+    pub fn use_clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        self.clock = clock;
+        self
+    }
+
+    fn handler(&self, kind: &str) -> Option<&JobHandler> {
+        self.handlers.get(kind)
+    }
+
+    fn recurring_jobs(&self) -> &[RecurringJob] {
+        &self.recurring
+    }
+
+    fn resources(&self) -> Arc<Resources> {
+        self.resources.clone()
+    }
+
+    fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+}
+
+/// All jobs known to the control plane, with the services from `state` they need
+pub fn registry(state: &State) -> Result<JobRegistry, JobError> {
+    let config = &state.config;
+    let mut registry = JobRegistry::new();
+    registry.use_clock(state.clock.clone());
+    registry.recurring(prune::PRUNE_SCHEDULE, prune::PruneFinishedJobs::default())?;
+    registry.recurring(
+        purge::PURGE_SCHEDULE,
+        purge::PurgeDeleted {
+            retention_days: config.retention.deleted_days,
+        },
+    )?;
+    #[cfg(feature = "cloud")]
+    registry.recurring(
+        billing::INVOICE_SCHEDULE,
+        billing::InvoiceUsage {
+            prices: config.billing.prices.clone(),
+        },
+    )?;
+    #[cfg(feature = "cloud")]
+    registry.provide(state.payments.clone());
+    Ok(registry)
+}
+
+/// Persists a job for the worker pool; returns `None` when `unique_key` is already taken
+pub async fn enqueue<J: Job, C: ConnectionTrait>(
+    db: &C,
+    job: &J,
+    options: EnqueueOptions,
+) -> Result<Option<JobModel>, AppError> {
+    let payload = serde_json::to_value(job)
+        .map_err(|e| AppError::Job(JobError::InvalidPayload(e.to_string())))?;
+
+    insert_job(db, J::KIND, payload, J::MAX_ATTEMPTS, options).await
+}
+
+async fn insert_job<C: ConnectionTrait>(
+    db: &C,
+    kind: &str,
+    payload: serde_json::Value,
+    max_attempts: i32,
+    options: EnqueueOptions,
+) -> Result<Option<JobModel>, AppError> {
+    let now = Utc::now();
+    let job = JobActiveModel {
+        id: Set(Uuid::new_v4()),
+        kind: Set(kind.to_string()),
+        payload: Set(payload),
+        status: Set(JobStatus::Pending),
+        attempts: Set(0),
+        max_attempts: Set(options.max_attempts.unwrap_or(max_attempts).max(1)),
+        unique_key: Set(options.unique_key),
+        last_error: Set(None),
+        run_at: Set(options.run_at.unwrap_or(now)),
+        locked_at: Set(None),
+        locked_by: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    // No conflict target: the partial unique index on `unique_key` is the only one that can fire
+    let result = JobEntity::insert(job)
+        .on_conflict(OnConflict::new().do_nothing().to_owned())
+        .do_nothing()
+        .exec_with_returning(db)
+        .await;
+
+    match result {
+        Ok(TryInsertResult::Inserted(job)) => Ok(Some(job)),
+        Ok(TryInsertResult::Conflicted | TryInsertResult::Empty) => Ok(None),
+        // A skipped insert returns no row, which sea-orm reports as not found
+        Err(DbErr::RecordNotFound(_)) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Age of the oldest job that is due but has not been picked up yet
+pub async fn queue_lag<C: ConnectionTrait>(db: &C) -> Result<Option<std::time::Duration>, DbErr> {
+    let now = Utc::now();
+    let oldest = JobEntity::find()
+        .filter(Column::Status.eq(JobStatus::Pending))
+        .filter(Column::RunAt.lte(now))
+        .order_by_asc(Column::RunAt)
+        .one(db)
+        .await?;
+
+    Ok(oldest.map(|job| (now - job.run_at).to_std().unwrap_or_default()))
+}
+
+/// Number of pending and running jobs, grouped by status
+pub async fn queue_depth<C: ConnectionTrait>(db: &C) -> Result<Vec<(JobStatus, i64)>, DbErr> {
+    JobEntity::find()
+        .select_only()
+        .column(Column::Status)
+        .column_as(Column::Id.count(), "count")
+        .filter(Column::Status.is_in([JobStatus::Pending, JobStatus::Running]))
+        .group_by(Column::Status)
+        .into_tuple()
+        .all(db)
+        .await
+}
@@ -0,0 +1,45 @@
+use chrono::TimeDelta;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use super::{Job, JobContext};
+use crate::errors::JobError;
+use crate::log_info;
+use crate::models::entities::job::Column;
+use crate::models::entities::{Job as JobEntity, JobStatus};
+
+/// Daily at 03:00 UTC
+pub const PRUNE_SCHEDULE: &str = "0 0 3 * * *";
+
+/// Deletes completed and failed jobs once they are older than the retention period
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PruneFinishedJobs {
+    pub retention_days: i64,
+}
+
+impl Default for PruneFinishedJobs {
+    fn default() -> Self {
+        Self { retention_days: 14 }
+    }
+}
+
+impl Job for PruneFinishedJobs {
+    const KIND: &'static str = "jobs.prune_finished";
+
+    async fn run(self, ctx: JobContext) -> Result<(), JobError> {
+        let cutoff = ctx.clock.now() - TimeDelta::days(self.retention_days);
+
+        let result = JobEntity::delete_many()
+            .filter(Column::Status.is_in([JobStatus::Completed, JobStatus::Failed]))
+            .filter(Column::UpdatedAt.lt(cutoff))
+            .exec(&ctx.db)
+            .await?;
+
+        log_info!(
+            "Pruned {} finished jobs older than {} days",
+            result.rows_affected,
+            self.retention_days
+        );
+        Ok(())
+    }
+}
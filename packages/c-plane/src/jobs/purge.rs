@@ -0,0 +1,59 @@
+use chrono::TimeDelta;
+#[cfg(feature = "cloud")]
+use sea_orm::sea_query::Query;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+use super::{Job, JobContext};
+use crate::errors::JobError;
+use crate::log_info;
+use crate::models::entities::{Organisation, Project, organisation, project};
+#[cfg(feature = "cloud")]
+use crate::models::entities::{Invoice, InvoiceStatus, invoice};
+
+/// Daily at 03:30 UTC, after the finished jobs are pruned
+pub const PURGE_SCHEDULE: &str = "0 30 3 * * *";
+
+/// Permanently deletes organisations and projects that were deleted longer ago than
+/// the retention period. Purging an organisation cascades to its projects and members;
+/// its invoices and usage are kept. Organisations with unpaid invoices wait until
+/// they are paid.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PurgeDeleted {
+    pub retention_days: u32,
+}
+
+impl Job for PurgeDeleted {
+    const KIND: &'static str = "retention.purge_deleted";
+
+    async fn run(self, ctx: JobContext) -> Result<(), JobError> {
+        let cutoff = ctx.clock.now() - TimeDelta::days(self.retention_days.into());
+
+        let projects = Project::delete_many()
+            .filter(project::Column::DeletedAt.lt(cutoff))
+            .exec(&ctx.db)
+            .await?;
+        let organisations = Organisation::delete_many()
+            .filter(organisation::Column::DeletedAt.lt(cutoff));
+        #[cfg(feature = "cloud")]
+        let organisations = organisations.filter(
+            organisation::Column::Id.not_in_subquery(
+                Query::select()
+                    .column(invoice::Column::OrganisationId)
+                    .from(Invoice)
+                    .and_where(invoice::Column::OrganisationId.is_not_null())
+                    .and_where(invoice::Column::Status.ne(InvoiceStatus::Paid))
+                    .to_owned(),
+            ),
+        );
+        let organisations = organisations.exec(&ctx.db).await?;
+
+        log_info!(
+            "Purged {} organisations and {} projects deleted more than {} days ago",
+            organisations.rows_affected,
+            projects.rows_affected,
+            self.retention_days
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use std::str::FromStr;
+
+use chrono::Utc;
+use sea_orm::DatabaseConnection;
+
+use super::{EnqueueOptions, Job, insert_job};
+use crate::errors::{AppError, JobError};
+
+/// A job that is enqueued on every tick of a cron schedule
+#[derive(Clone)]
+pub struct RecurringJob {
+    kind: &'static str,
+    schedule: cron::Schedule,
+    payload: serde_json::Value,
+    max_attempts: i32,
+}
+
+impl RecurringJob {
+    pub fn new<J: Job>(expression: &str, job: &J) -> Result<Self, JobError> {
+        let schedule = cron::Schedule::from_str(expression)
+            .map_err(|e| JobError::InvalidSchedule(format!("{}: {}", expression, e)))?;
+        let payload =
+            serde_json::to_value(job).map_err(|e| JobError::InvalidPayload(e.to_string()))?;
+
+        Ok(Self {
+            kind: J::KIND,
+            schedule,
+            payload,
+            max_attempts: J::MAX_ATTEMPTS,
+        })
+    }
+
+    /// Enqueues the next occurrence as a delayed job. The occurrence timestamp is part of
+    /// the unique key, so every replica running a scheduler converges on a single job.
+    pub async fn enqueue_next(&self, db: &DatabaseConnection) -> Result<(), AppError> {
+        let Some(next) = self.schedule.after(&Utc::now()).next() else {
+            return Ok(());
+        };
+
+        insert_job(
+            db,
+            self.kind,
+            self.payload.clone(),
+            self.max_attempts,
+            EnqueueOptions {
+                run_at: Some(next),
+                unique_key: Some(format!("recurring:{}:{}", self.kind, next.timestamp())),
+                max_attempts: None,
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+}
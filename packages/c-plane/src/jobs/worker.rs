@@ -0,0 +1,313 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{TimeDelta, Utc};
+use sea_orm::sea_query::{LockBehavior, LockType};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+use super::{JobContext, JobRegistry};
+use crate::errors::JobError;
+use crate::models::entities::job::Column;
+use crate::models::entities::{Job as JobEntity, JobActiveModel, JobModel, JobStatus};
+use crate::utils::metrics::metrics;
+use crate::{log_error, log_info, log_warn};
+
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    pub workers: usize,
+    /// How long an idle worker sleeps before polling again
+    pub poll_interval: Duration,
+    /// How often recurring jobs are checked for their next occurrence
+    pub schedule_interval: Duration,
+    /// Running jobs locked longer than this are assumed abandoned and picked up again
+    pub lock_timeout: Duration,
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        Self {
+            workers: 4,
+            poll_interval: Duration::from_secs(1),
+            schedule_interval: Duration::from_secs(30),
+            lock_timeout: Duration::from_secs(300),
+            base_backoff: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(3600),
+        }
+    }
+}
+
+/// Workers and the recurring job scheduler, running as tasks next to the HTTP server
+pub struct WorkerPool {
+    shutdown: watch::Sender<bool>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    pub fn start(db: DatabaseConnection, registry: JobRegistry, config: WorkerConfig) -> Self {
+        let registry = Arc::new(registry);
+        let (shutdown, receiver) = watch::channel(false);
+
+        let mut handles: Vec<JoinHandle<()>> = (0..config.workers)
+            .map(|index| {
+                let worker = Worker {
+                    id: format!("{}-{}", std::process::id(), index),
+                    db: db.clone(),
+                    registry: registry.clone(),
+                    config: config.clone(),
+                };
+                tokio::spawn(worker.run(receiver.clone()))
+            })
+            .collect();
+        handles.push(tokio::spawn(run_scheduler(
+            db,
+            registry,
+            config.schedule_interval,
+            receiver,
+        )));
+
+        log_info!("Started {} job workers", config.workers);
+        Self { shutdown, handles }
+    }
+
+    /// Stops polling and waits for in-flight jobs to finish
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+        for handle in self.handles {
+            if let Err(err) = handle.await {
+                log_error!("Job worker task ended abnormally: {}", err);
+            }
+        }
+        log_info!("Job workers stopped");
+    }
+}
+
+struct Worker {
+    id: String,
+    db: DatabaseConnection,
+    registry: Arc<JobRegistry>,
+    config: WorkerConfig,
+}
+
+impl Worker {
+    async fn run(self, mut shutdown: watch::Receiver<bool>) {
+        while !*shutdown.borrow() {
+            match self.claim().await {
+                Ok(Some(job)) => self.execute(job).await,
+                Ok(None) => idle(&mut shutdown, self.config.poll_interval).await,
+                Err(err) => {
+                    log_error!("Job worker {} failed to claim a job: {}", self.id, err);
+                    idle(&mut shutdown, self.config.poll_interval).await;
+                }
+            }
+        }
+    }
+
+    /// Locks the next due job with `FOR UPDATE SKIP LOCKED` so concurrent workers never
+    /// claim the same row, then marks it as running. SQLite has no row locks, so the
+    /// update only applies while the attempt count is still the one that was read.
+    #[tracing::instrument(name = "db.claim_job", skip_all, fields(otel.kind = "client"))]
+    async fn claim(&self) -> Result<Option<JobModel>, DbErr> {
+        let now = Utc::now();
+        let stale_before =
+            now - TimeDelta::from_std(self.config.lock_timeout).unwrap_or(TimeDelta::minutes(5));
+
+        let txn = self.db.begin().await?;
+        let job = JobEntity::find()
+            .filter(
+                Condition::any()
+                    .add(
+                        Column::Status
+                            .eq(JobStatus::Pending)
+                            .and(Column::RunAt.lte(now)),
+                    )
+                    .add(
+                        Column::Status
+                            .eq(JobStatus::Running)
+                            .and(Column::LockedAt.lt(stale_before)),
+                    ),
+            )
+            .order_by_asc(Column::RunAt)
+            .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+            .one(&txn)
+            .await?;
+
+        let Some(job) = job else {
+            txn.commit().await?;
+            return Ok(None);
+        };
+
+        let claimed = JobModel {
+            status: JobStatus::Running,
+            attempts: job.attempts + 1,
+            locked_at: Some(now),
+            locked_by: Some(self.id.clone()),
+            updated_at: now,
+            ..job.clone()
+        };
+        let result = JobEntity::update_many()
+            .set(JobActiveModel {
+                status: Set(claimed.status.clone()),
+                attempts: Set(claimed.attempts),
+                locked_at: Set(claimed.locked_at),
+                locked_by: Set(claimed.locked_by.clone()),
+                updated_at: Set(claimed.updated_at),
+                ..Default::default()
+            })
+            .filter(Column::Id.eq(job.id))
+            .filter(Column::Attempts.eq(job.attempts))
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+        // Another worker got there first
+        if result.rows_affected == 0 {
+            return Ok(None);
+        }
+        Ok(Some(claimed))
+    }
+
+    async fn execute(&self, job: JobModel) {
+        // A stale job that was re-claimed may already have used up its attempts
+        if job.attempts > job.max_attempts {
+            let err = JobError::ExecutionFailed(format!(
+                "abandoned by worker {} after {} attempts",
+                job.locked_by.clone().unwrap_or_default(),
+                job.max_attempts
+            ));
+            self.finish(job, Err(err)).await;
+            return;
+        }
+
+        let Some(handler) = self.registry.handler(&job.kind).cloned() else {
+            let err = JobError::UnknownKind(job.kind.clone());
+            self.finish(job, Err(err)).await;
+            return;
+        };
+
+        let ctx = JobContext {
+            db: self.db.clone(),
+            job_id: job.id,
+            attempt: job.attempts,
+            resources: self.registry.resources(),
+            clock: self.registry.clock(),
+        };
+        let start_time = Instant::now();
+
+        let span = tracing::info_span!(
+            "job",
+            otel.name = %job.kind,
+            job.id = %job.id,
+            job.attempt = job.attempts,
+        );
+
+        // Spawned so a panicking handler fails the job instead of the worker
+        let run = handler(job.payload.clone(), ctx).instrument(span);
+        let result = match tokio::spawn(run).await {
+            Ok(result) => result,
+            Err(err) => Err(JobError::Panicked(err.to_string())),
+        };
+
+        let duration = start_time.elapsed();
+        match &result {
+            Ok(()) => log_info!(
+                "Job {} ({}) completed in {}ms",
+                job.id,
+                job.kind,
+                duration.as_millis()
+            ),
+            Err(err) => log_warn!(
+                "Job {} ({}) attempt {}/{} failed: {}",
+                job.id,
+                job.kind,
+                job.attempts,
+                job.max_attempts,
+                err
+            ),
+        }
+
+        self.finish(job, result).await;
+    }
+
+    async fn finish(&self, job: JobModel, result: Result<(), JobError>) {
+        let now = Utc::now();
+        let job_id = job.id;
+        let retryable = !matches!(
+            result,
+            Err(JobError::UnknownKind(_)) | Err(JobError::InvalidPayload(_))
+        );
+        let attempts = job.attempts;
+        let max_attempts = job.max_attempts;
+        let kind = job.kind.clone();
+
+        let mut job: JobActiveModel = job.into();
+        job.locked_at = Set(None);
+        job.locked_by = Set(None);
+        job.updated_at = Set(now);
+
+        match result {
+            Ok(()) => {
+                metrics().observe_job(&kind, "completed");
+                job.status = Set(JobStatus::Completed);
+                job.last_error = Set(None);
+            }
+            Err(err) if retryable && attempts < max_attempts => {
+                metrics().observe_job(&kind, "retried");
+                job.status = Set(JobStatus::Pending);
+                job.run_at = Set(now + self.backoff(attempts));
+                job.last_error = Set(Some(err.to_string()));
+            }
+            Err(err) => {
+                log_error!("Job {} failed permanently: {}", job_id, err);
+                metrics().observe_job(&kind, "failed");
+                job.status = Set(JobStatus::Failed);
+                job.last_error = Set(Some(err.to_string()));
+            }
+        }
+
+        if let Err(err) = job.update(&self.db).await {
+            log_error!("Failed to record result of job {}: {}", job_id, err);
+        }
+    }
+
+    /// Exponential backoff: `base_backoff * 2^(attempt - 1)`, capped at `max_backoff`
+    fn backoff(&self, attempt: i32) -> TimeDelta {
+        let exponent = (attempt - 1).clamp(0, 16) as u32;
+        let delay = self
+            .config
+            .base_backoff
+            .saturating_mul(2u32.pow(exponent))
+            .min(self.config.max_backoff);
+        TimeDelta::from_std(delay).unwrap_or(TimeDelta::hours(1))
+    }
+}
+
+async fn run_scheduler(
+    db: DatabaseConnection,
+    registry: Arc<JobRegistry>,
+    interval: Duration,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    while !*shutdown.borrow() {
+        for recurring in registry.recurring_jobs() {
+            if let Err(err) = recurring.enqueue_next(&db).await {
+                log_error!("Failed to schedule recurring job: {}", err);
+            }
+        }
+        idle(&mut shutdown, interval).await;
+    }
+}
+
+async fn idle(shutdown: &mut watch::Receiver<bool>, duration: Duration) {
+    tokio::select! {
+        _ = tokio::time::sleep(duration) => {}
+        _ = shutdown.changed() => {}
+    }
+}
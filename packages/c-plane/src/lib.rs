@@ -0,0 +1,45 @@
+pub mod config;
+pub mod errors;
+pub mod handlers;
+pub mod jobs;
+pub mod middleware;
+pub mod models;
+pub mod services;
+pub mod state;
+pub mod utils;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::middleware::Condition;
+use actix_web::{App, Error, web};
+
+use crate::middleware::cors::cors;
+use crate::middleware::rate_limit::RateLimitMiddleware;
+use crate::state::State;
+use crate::utils::logger::CustomLogger;
+use crate::utils::validation::json_config;
+
+/// The full application with every route and middleware, used by the server and by
+/// integration tests
+pub fn create_app(
+    state: web::Data<State>,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    let origins = &state.config.server.cors_allowed_origins;
+    let cors = Condition::new(!origins.is_empty(), cors(origins));
+
+    App::new()
+        .app_data(state)
+        .app_data(json_config())
+        .wrap(RateLimitMiddleware)
+        .wrap(cors)
+        .wrap(CustomLogger)
+        .configure(handlers::config)
+}
@@ -1,37 +1,91 @@
-mod config;
-mod errors;
-mod handlers;
-mod middleware;
-mod models;
-mod services;
-mod state;
-mod utils;
-
-use crate::state::create_app_state;
-use crate::utils::logger::CustomLogger;
-use actix_web::{App, HttpServer, middleware::Logger};
+use std::time::Duration;
+
+use actix_web::{HttpServer, web};
+use c_plane::config::{self, Args};
+use c_plane::jobs::{self, WorkerConfig, WorkerPool};
+#[cfg(not(feature = "cloud"))]
+use c_plane::services::instance::bootstrap;
+use c_plane::state::create_app_state;
+use c_plane::utils::{self, telemetry::Telemetry};
+use c_plane::{create_app, log_info};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    if let Err(e) = create_app_state().await {
-        eprintln!("Failed to create app state: {}", e);
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("App state creation failed: {}", e),
-        ));
+    let args = Args::parse()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let config = match config::load_config(args.config_path.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Invalid configuration",
+            ));
+        }
+    };
+
+    if args.print_config {
+        let rendered = config.to_toml().map_err(std::io::Error::other)?;
+        print!("{}", rendered);
+        return Ok(());
     }
 
-    let config = config::load_config()
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let (telemetry, otel_layer) = match Telemetry::init(&config) {
+        Ok(Some((telemetry, layer))) => (Some(telemetry), Some(layer)),
+        Ok(None) => (None, None),
+        Err(e) => {
+            eprintln!("Failed to initialise OpenTelemetry: {}", e);
+            (None, None)
+        }
+    };
+    utils::logger::init(config.telemetry.log_format, otel_layer);
+
+    let state = match create_app_state(config.clone()).await {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("Failed to create app state: {}", e);
+            return Err(std::io::Error::other(format!(
+                "App state creation failed: {}",
+                e
+            )));
+        }
+    };
 
-    log_info!("Starting Actix at {}:{}", config.server_host, config.server_port);
-    HttpServer::new(move || {
-        App::new()
-            .wrap(Logger::default())
-            // .wrap(CustomLogger)
-            .configure(handlers::config)
-    })
-    .bind(format!("{}:{}", config.server_host, config.server_port))?
+    #[cfg(not(feature = "cloud"))]
+    if let Err(e) = bootstrap(&state.db, state.clock.as_ref(), &config.instance).await {
+        eprintln!("Failed to bootstrap the instance: {}", e);
+        return Err(std::io::Error::other(format!("Bootstrap failed: {}", e)));
+    }
+
+    let workers = if config.features.background_jobs {
+        let registry = jobs::registry(&state)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        Some(WorkerPool::start(
+            state.db.clone(),
+            registry,
+            WorkerConfig {
+                workers: config.jobs.workers,
+                ..WorkerConfig::default()
+            },
+        ))
+    } else {
+        None
+    };
+
+    log_info!("Starting Actix at {}:{}", config.server.host, config.server.port);
+    let state = web::Data::new(state);
+    HttpServer::new(move || create_app(state.clone()))
+    .client_request_timeout(Duration::from_secs(config.server.request_timeout_secs))
+    .shutdown_timeout(config.server.shutdown_timeout_secs)
+    .bind(format!("{}:{}", config.server.host, config.server.port))?
     .run()
-    .await
+    .await?;
+
+    if let Some(workers) = workers {
+        workers.shutdown().await;
+    }
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown();
+    }
+    Ok(())
 }
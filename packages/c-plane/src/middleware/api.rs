@@ -1,11 +1,13 @@
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error, HttpMessage, HttpResponse, FromRequest,
+    Error, HttpMessage, FromRequest, ResponseError,
     body::{EitherBody, BoxBody},
 };
 use futures_util::future::LocalBoxFuture;
 use std::future::{ready, Ready};
 
+use crate::errors::AppError;
+
 pub struct ApiMiddleware;
 
 impl<S, B> Transform<S, ServiceRequest> for ApiMiddleware
@@ -56,7 +58,7 @@ where
                 Ok(res.map_into_left_body())
             })
         } else {
-            let response = HttpResponse::Unauthorized().finish();
+            let response = AppError::Unauthorized("API key not provided".to_string()).error_response();
             let (req, _) = req.into_parts();
             Box::pin(async move { 
                 Ok(ServiceResponse::new(req, response).map_into_right_body()) 
@@ -75,7 +77,7 @@ impl ApiKey {
 }
 
 impl FromRequest for ApiKey {
-    type Error = crate::errors::AppError;
+    type Error = AppError;
     type Future = Ready<Result<Self, Self::Error>>;
 
     fn from_request(req: &actix_web::HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
@@ -84,7 +86,7 @@ impl FromRequest for ApiKey {
             .get::<String>()
             .cloned()
             .map(ApiKey)
-            .ok_or_else(|| crate::errors::AppError::Unauthorized("API key not provided".to_string()));
+            .ok_or_else(|| AppError::Unauthorized("API key not provided".to_string()));
         
         ready(result)
     }
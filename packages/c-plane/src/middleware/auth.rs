@@ -1,12 +1,15 @@
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error, HttpMessage, HttpResponse, FromRequest,
+    Error, HttpMessage, FromRequest, ResponseError,
     body::{EitherBody, BoxBody},
 };
 use futures_util::future::LocalBoxFuture;
 use std::future::{ready, Ready};
 use uuid::Uuid;
 
+use crate::errors::AppError;
+use crate::utils::logger::record_user_id;
+
 pub struct AuthMiddleware;
 
 impl<S, B> Transform<S, ServiceRequest> for AuthMiddleware
@@ -50,6 +53,7 @@ where
             .and_then(|s| Uuid::parse_str(s).ok());
 
         if let Some(id) = user_id {
+            record_user_id(id);
             req.extensions_mut().insert(id);  // Store Uuid directly
             let fut = self.service.call(req);
             Box::pin(async move { 
@@ -57,7 +61,7 @@ where
                 Ok(res.map_into_left_body())
             })
         } else {
-            let response = HttpResponse::Unauthorized().finish();
+            let response = AppError::Unauthorized("User not authenticated".to_string()).error_response();
             let (req, _) = req.into_parts();
             Box::pin(async move { 
                 Ok(ServiceResponse::new(req, response).map_into_right_body()) 
@@ -76,7 +80,7 @@ impl UserId {
 }
 
 impl FromRequest for UserId {
-    type Error = crate::errors::AppError;
+    type Error = AppError;
     type Future = Ready<Result<Self, Self::Error>>;
 
     fn from_request(req: &actix_web::HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
@@ -85,7 +89,7 @@ impl FromRequest for UserId {
             .get::<Uuid>()  // Get Uuid directly
             .copied()
             .map(UserId)
-            .ok_or_else(|| crate::errors::AppError::Unauthorized("User not authenticated".to_string()));
+            .ok_or_else(|| AppError::Unauthorized("User not authenticated".to_string()));
         
         ready(result)
     }
@@ -0,0 +1,26 @@
+use actix_cors::Cors;
+use actix_web::http::{Method, header};
+
+/// CORS policy for `server.cors_allowed_origins`; `*` allows any origin but then
+/// credentials (the Kratos session cookie) are not shared
+pub fn cors(allowed_origins: &[String]) -> Cors {
+    let cors = Cors::default()
+        .allowed_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .allowed_headers([header::AUTHORIZATION, header::CONTENT_TYPE, header::ACCEPT])
+        .expose_headers([header::HeaderName::from_static("x-request-id")])
+        .max_age(3600);
+
+    if allowed_origins.iter().any(|origin| origin == "*") {
+        return cors.allow_any_origin();
+    }
+
+    allowed_origins
+        .iter()
+        .fold(cors.supports_credentials(), |cors, origin| cors.allowed_origin(origin))
+}
@@ -1,2 +1,4 @@
 pub mod api;
-pub mod auth;
\ No newline at end of file
+pub mod auth;
+pub mod cors;
+pub mod rate_limit;
@@ -0,0 +1,185 @@
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderName, HeaderValue},
+    web, Error, ResponseError,
+    body::{EitherBody, BoxBody},
+};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::net::SocketAddr;
+use std::rc::Rc;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::errors::AppError;
+use crate::log_warn;
+use crate::services::rate_limit::{Budget, Decision};
+use crate::state::State;
+
+/// Probes and scrapes come from the infrastructure and are never limited
+const EXEMPT_PATHS: [&str; 2] = ["/health", "/metrics"];
+
+/// Applies the token buckets of `Config::rate_limit`, answering `429 rate_limited`
+/// once a bucket is empty. Responses on limited routes carry `RateLimit-Limit`,
+/// `RateLimit-Remaining` and `RateLimit-Reset` of the emptiest bucket drawn from.
+pub struct RateLimitMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimitMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B, BoxBody>>;
+    type Error = Error;
+    type Transform = RateLimitMiddlewareService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddlewareService { service: Rc::new(service) }))
+    }
+}
+
+pub struct RateLimitMiddlewareService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B, BoxBody>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let state = req
+            .app_data::<web::Data<State>>()
+            .filter(|state| state.config.features.rate_limiting)
+            .cloned();
+        let Some(state) = state.filter(|_| !under(req.path(), EXEMPT_PATHS)) else {
+            return Box::pin(async move { Ok(service.call(req).await?.map_into_left_body()) });
+        };
+
+        let config = &state.config.rate_limit;
+        let (class, budget) = if under(req.path(), &config.expensive_paths) {
+            ("expensive", Budget { per_minute: config.expensive_requests_per_minute })
+        } else {
+            ("default", Budget { per_minute: config.requests_per_minute })
+        };
+        let (ip, credential) = client_keys(&req, &state.config);
+
+        Box::pin(async move {
+            let decision = match draw(&state, class, budget, ip, credential).await {
+                Ok(decision) => decision,
+                Err(err) => {
+                    log_warn!("Rate limit check failed, letting the request through: {}", err);
+                    return Ok(service.call(req).await?.map_into_left_body());
+                }
+            };
+
+            if !decision.allowed {
+                let error = AppError::RateLimited { retry_after_secs: decision.retry_after_secs };
+                let mut res = req.into_response(error.error_response());
+                insert_headers(res.headers_mut(), &decision);
+                return Ok(res.map_into_right_body());
+            }
+            let mut res = service.call(req).await?;
+            insert_headers(res.headers_mut(), &decision);
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+/// Whether `path` is one of `prefixes` or below one of them
+fn under<P: AsRef<str>>(path: &str, prefixes: impl IntoIterator<Item = P>) -> bool {
+    prefixes.into_iter().any(|prefix| {
+        let prefix = prefix.as_ref().trim_end_matches('/');
+        path.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    })
+}
+
+/// Takes a token from the client IP's bucket and, unless that one is empty, from the
+/// credential's
+async fn draw(
+    state: &State,
+    class: &str,
+    budget: Budget,
+    ip: String,
+    credential: Option<String>,
+) -> Result<Decision, AppError> {
+    let now = state.clock.now();
+    let decision = state.rate_limits.take(&format!("{}:{}", class, ip), budget, now).await?;
+    match credential {
+        Some(credential) if decision.allowed => {
+            let taken = state.rate_limits.take(&format!("{}:{}", class, credential), budget, now).await?;
+            Ok(tighter(decision, taken))
+        }
+        _ => Ok(decision),
+    }
+}
+
+/// The buckets the request draws from: always the client IP's, so changing
+/// credentials never buys a fresh budget, and the user's or API key's on top. Users
+/// are who the gateway says in `X-User`, as for `AuthMiddleware`. An API key only
+/// gets a bucket once it matches a configured one, named after it so the key itself
+/// never reaches the store.
+fn client_keys(req: &ServiceRequest, config: &Config) -> (String, Option<String>) {
+    let header = |name| req.headers().get(name).and_then(|value| value.to_str().ok());
+    let ip = format!("ip:{}", client_ip(req, config));
+
+    if let Some(user_id) = header("X-User").and_then(|value| Uuid::parse_str(value).ok()) {
+        return (ip, Some(format!("user:{}", user_id)));
+    }
+    let configured = [
+        ("kratos", &config.auth.kratos_api_key),
+        #[cfg(feature = "cloud")]
+        ("metering", &config.billing.metering_api_key),
+    ];
+    let key = header("X-API-KEY").and_then(|api_key| {
+        configured
+            .into_iter()
+            .find(|(_, secret)| secret.matches(api_key))
+            .map(|(name, _)| format!("key:{}", name))
+    });
+    (ip, key)
+}
+
+fn client_ip(req: &ServiceRequest, config: &Config) -> String {
+    let ip = if config.rate_limit.trust_forwarded_for {
+        // Falls back to the peer address, which comes with a port
+        req.connection_info().realip_remote_addr().map(|addr| match addr.parse::<SocketAddr>() {
+            Ok(addr) => addr.ip().to_string(),
+            Err(_) => addr.to_string(),
+        })
+    } else {
+        req.peer_addr().map(|addr| addr.ip().to_string())
+    };
+    ip.unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Whichever leaves the request less room: a refusal, else the fewest tokens left
+fn tighter(first: Decision, second: Decision) -> Decision {
+    match (first.allowed, second.allowed) {
+        (false, _) => first,
+        (_, false) => second,
+        _ if second.remaining < first.remaining => second,
+        _ => first,
+    }
+}
+
+fn insert_headers(headers: &mut actix_web::http::header::HeaderMap, decision: &Decision) {
+    for (name, value) in [
+        ("ratelimit-limit", u64::from(decision.limit)),
+        ("ratelimit-remaining", u64::from(decision.remaining)),
+        ("ratelimit-reset", decision.reset_secs),
+    ] {
+        headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+    }
+}
@@ -0,0 +1,32 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "audit_log")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    pub organisation_id: Uuid,
+    pub actor_id: Uuid,
+    pub action: AuditAction,
+    pub project_id: Option<Uuid>,
+    #[sea_orm(column_type = "JsonBinary")]
+    pub details: Json,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(32))")]
+pub enum AuditAction {
+    /// Recorded in the organisation the project left
+    #[sea_orm(string_value = "project.transferred_out")]
+    ProjectTransferredOut,
+    /// Recorded in the organisation the project joined
+    #[sea_orm(string_value = "project.transferred_in")]
+    ProjectTransferredIn,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
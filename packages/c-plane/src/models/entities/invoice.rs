@@ -0,0 +1,47 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "invoice")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    pub organisation_id: Option<Uuid>, // Cleared when the organisation is purged
+    pub period_start: DateTimeUtc,
+    pub period_end: DateTimeUtc, // Exclusive
+    pub currency: String,
+    pub total_cents: i64,
+    pub status: InvoiceStatus,
+    pub payment_reference: Option<String>, // Set by the payment provider once paid
+    pub failure_reason: Option<String>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+pub enum InvoiceStatus {
+    #[sea_orm(string_value = "open")]
+    Open,
+    /// Claimed by a charge in progress
+    #[sea_orm(string_value = "processing")]
+    Processing,
+    #[sea_orm(string_value = "paid")]
+    Paid,
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::invoice_line::Entity")]
+    InvoiceLines,
+}
+
+impl Related<super::invoice_line::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::InvoiceLines.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
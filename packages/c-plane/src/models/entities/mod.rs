@@ -1,9 +1,38 @@
+pub mod audit_log;
+#[cfg(feature = "cloud")]
+pub mod invoice;
+#[cfg(feature = "cloud")]
+pub mod invoice_line;
+pub mod job;
 pub mod organisation;
 pub mod organisation_member;
 pub mod project;
+#[cfg(feature = "cloud")]
+pub mod subscription;
+#[cfg(feature = "cloud")]
+pub mod usage_record;
+
+pub use audit_log::{
+    ActiveModel as AuditLogActiveModel, AuditAction, Entity as AuditLog, Model as AuditLogModel,
+};
+
+#[cfg(feature = "cloud")]
+pub use invoice::{
+    ActiveModel as InvoiceActiveModel, Entity as Invoice, InvoiceStatus, Model as InvoiceModel,
+};
+
+#[cfg(feature = "cloud")]
+pub use invoice_line::{
+    ActiveModel as InvoiceLineActiveModel, Entity as InvoiceLine, Model as InvoiceLineModel,
+};
+
+pub use job::{
+    ActiveModel as JobActiveModel, Entity as Job, JobStatus, Model as JobModel,
+};
 
 pub use organisation::{
     ActiveModel as OrganisationActiveModel, Entity as Organisation, Model as OrganisationModel,
+    Plan, PlanLimits,
 };
 
 pub use organisation_member::{
@@ -14,3 +43,15 @@ pub use organisation_member::{
 pub use project::{
     ActiveModel as ProjectActiveModel, Entity as Project, Model as ProjectModel,
 };
+
+#[cfg(feature = "cloud")]
+pub use subscription::{
+    ActiveModel as SubscriptionActiveModel, Entity as Subscription, Model as SubscriptionModel,
+    SubscriptionStatus,
+};
+
+#[cfg(feature = "cloud")]
+pub use usage_record::{
+    ActiveModel as UsageRecordActiveModel, Entity as UsageRecord, Model as UsageRecordModel,
+    UsageMetric,
+};
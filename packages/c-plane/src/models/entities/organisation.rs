@@ -1,8 +1,9 @@
+use sea_orm::FromJsonQueryResult;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
-#[sea_orm(table_name = "organisations")]
+#[sea_orm(table_name = "organisation")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: Uuid,
@@ -10,9 +11,38 @@ pub struct Model {
     pub description: Option<String>,
     pub avatar_url: Option<String>,
     pub is_active: bool,
-    pub created_at: DateTime,
-    pub updated_at: DateTime,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
     pub created_by: Uuid,
+    pub plan: Plan,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub custom_limits: Option<PlanLimits>, // Only used on the custom plan
+    pub deleted_at: Option<DateTimeUtc>,
+    pub deleted_by: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+pub enum Plan {
+    #[sea_orm(string_value = "free")]
+    Free,
+    #[sea_orm(string_value = "team")]
+    Team,
+    #[sea_orm(string_value = "enterprise")]
+    Enterprise,
+    #[sea_orm(string_value = "custom")]
+    Custom,
+}
+
+/// What an organisation may hold at most, `None` meaning no limit
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+pub struct PlanLimits {
+    pub projects: Option<u32>,
+    pub members: Option<u32>,
+    pub deployments: Option<u32>,
+    pub agents: Option<u32>,
+    pub api_keys: Option<u32>,
+    pub log_retention_days: u32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
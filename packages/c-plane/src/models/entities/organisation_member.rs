@@ -2,7 +2,7 @@ use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
-#[sea_orm(table_name = "organisation_members")]
+#[sea_orm(table_name = "organisation_member")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: Uuid,
@@ -10,10 +10,10 @@ pub struct Model {
     pub identity_id: Uuid, // References Ory Kratos identity ID directly
     pub role: OrganisationRole,
     pub is_active: bool,
-    pub joined_at: DateTime,
+    pub joined_at: DateTimeUtc,
     pub invited_by: Uuid, // References Ory Kratos identity ID of inviter
-    pub invited_at: DateTime,
-    pub invitation_accepted_at: DateTime,
+    pub invited_at: DateTimeUtc,
+    pub invitation_accepted_at: DateTimeUtc,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
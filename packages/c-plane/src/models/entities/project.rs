@@ -2,7 +2,7 @@ use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
-#[sea_orm(table_name = "projects")]
+#[sea_orm(table_name = "project")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: Uuid,
@@ -11,8 +11,11 @@ pub struct Model {
     pub organisation_id: Uuid,
     pub owner_id: Uuid, // References Ory Kratos identity ID
     pub is_archived: bool,
-    pub created_at: DateTime,
-    pub updated_at: DateTime,
+    pub is_public: bool,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+    pub deleted_at: Option<DateTimeUtc>, // Soft deleted, purged after the retention period
+    pub deleted_by: Option<Uuid>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
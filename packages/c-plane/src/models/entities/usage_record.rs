@@ -0,0 +1,32 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "usage_record")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid, // Chosen by the agent, so a resent report isn't counted twice
+    pub organisation_id: Option<Uuid>, // Cleared when the organisation is purged
+    pub project_id: Uuid,
+    pub deployment_id: Uuid,
+    pub metric: UsageMetric,
+    pub quantity: i64,
+    pub recorded_at: DateTimeUtc,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(32))")]
+pub enum UsageMetric {
+    #[sea_orm(string_value = "runtime_seconds")]
+    RuntimeSeconds,
+    #[sea_orm(string_value = "cpu_seconds")]
+    CpuSeconds,
+    #[sea_orm(string_value = "storage_gb_hours")]
+    StorageGbHours,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
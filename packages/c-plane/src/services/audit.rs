@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use sea_orm::{ActiveModelTrait, ConnectionTrait, Set};
+use uuid::Uuid;
+
+use crate::errors::AppError;
+use crate::models::entities::{AuditAction, AuditLogActiveModel, AuditLogModel};
+
+pub struct AuditEntry {
+    pub organisation_id: Uuid,
+    pub actor_id: Uuid,
+    pub action: AuditAction,
+    pub project_id: Option<Uuid>,
+    pub details: serde_json::Value,
+}
+
+/// Writes an entry to the organisation's audit log. Pass the transaction making the
+/// change, so the entry is only kept if the change is.
+pub async fn record<C: ConnectionTrait>(
+    db: &C,
+    at: DateTime<Utc>,
+    entry: AuditEntry,
+) -> Result<AuditLogModel, AppError> {
+    let entry = AuditLogActiveModel {
+        id: Set(Uuid::new_v4()),
+        organisation_id: Set(entry.organisation_id),
+        actor_id: Set(entry.actor_id),
+        action: Set(entry.action),
+        project_id: Set(entry.project_id),
+        details: Set(entry.details),
+        created_at: Set(at),
+    };
+    Ok(entry.insert(db).await?)
+}
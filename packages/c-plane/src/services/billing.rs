@@ -0,0 +1,464 @@
+use std::collections::{BTreeMap, HashMap};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveTime, TimeDelta, Utc};
+use sea_orm::sea_query::{Alias, Expr, OnConflict};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait, TryInsertResult,
+};
+use uuid::Uuid;
+
+use crate::config::Prices;
+use crate::errors::{AppError, OrganisationError, ProjectError};
+use crate::log_info;
+use crate::models::entities::{
+    Invoice, InvoiceActiveModel, InvoiceLine, InvoiceLineActiveModel, InvoiceLineModel,
+    InvoiceModel, InvoiceStatus, Project, Subscription, SubscriptionActiveModel,
+    SubscriptionModel, SubscriptionStatus, UsageMetric, UsageRecord, UsageRecordActiveModel,
+    invoice, invoice_line, project, subscription, usage_record,
+};
+use crate::utils::clock::Clock;
+use crate::utils::pagination::{Cursor, Keyset, PaginatedResponse, Pagination, fetch_page};
+
+/// What the payment provider is asked to collect for an invoice
+#[derive(Debug, Clone)]
+pub struct Charge {
+    pub invoice_id: Uuid,
+    /// The invoice id again, for the provider's idempotency key, so a charge retried
+    /// after a crash or timeout collects the invoice at most once
+    pub idempotency_key: Uuid,
+    pub organisation_id: Uuid,
+    pub amount_cents: i64,
+    pub currency: String,
+}
+
+/// Collects invoice payments, behind a trait so tests can decide how charges turn out
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    /// Charges the organisation's payment method, returning the provider's reference
+    /// for the payment
+    async fn charge(&self, charge: Charge) -> Result<String, AppError>;
+}
+
+/// `PaymentProvider` used until a real provider is configured, only logs what would be
+/// charged and treats it as paid
+pub struct LogPaymentProvider;
+
+#[async_trait]
+impl PaymentProvider for LogPaymentProvider {
+    async fn charge(&self, charge: Charge) -> Result<String, AppError> {
+        log_info!(
+            "No payment provider configured, not charging {} {} for invoice {}",
+            charge.amount_cents,
+            charge.currency,
+            charge.invoice_id
+        );
+        Ok(format!("log-{}", charge.invoice_id))
+    }
+}
+
+/// Usage of one deployment, as reported by the agent running it
+#[derive(Debug, Clone)]
+pub struct UsageData {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub deployment_id: Uuid,
+    pub metric: UsageMetric,
+    pub quantity: i64,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Stores usage under the organisation owning each project, deleted projects included
+/// since their last usage is still billed. Records stored before are skipped, so agents
+/// can resend a report that timed out. Returns how many records were new.
+#[tracing::instrument(name = "db.record_usage", skip_all, fields(otel.kind = "client"))]
+pub async fn record_usage<C: ConnectionTrait>(
+    db: &C,
+    clock: &dyn Clock,
+    records: Vec<UsageData>,
+) -> Result<u64, AppError> {
+    let project_ids: Vec<Uuid> = records.iter().map(|record| record.project_id).collect();
+    let organisations: HashMap<Uuid, Uuid> = Project::find()
+        .select_only()
+        .column(project::Column::Id)
+        .column(project::Column::OrganisationId)
+        .filter(project::Column::Id.is_in(project_ids))
+        .into_tuple::<(Uuid, Uuid)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+
+    let now = clock.now();
+    let mut models = Vec::with_capacity(records.len());
+    for record in records {
+        let organisation_id = *organisations
+            .get(&record.project_id)
+            .ok_or(AppError::Project(ProjectError::ProjectNotFound(record.project_id)))?;
+        models.push(UsageRecordActiveModel {
+            id: Set(record.id),
+            organisation_id: Set(Some(organisation_id)),
+            project_id: Set(record.project_id),
+            deployment_id: Set(record.deployment_id),
+            metric: Set(record.metric),
+            quantity: Set(record.quantity),
+            recorded_at: Set(record.recorded_at),
+            created_at: Set(now),
+        });
+    }
+
+    let result = UsageRecord::insert_many(models)
+        .on_conflict(OnConflict::column(usage_record::Column::Id).do_nothing().to_owned())
+        .do_nothing()
+        .exec_without_returning(db)
+        .await?;
+
+    Ok(match result {
+        TryInsertResult::Inserted(count) => count,
+        TryInsertResult::Conflicted | TryInsertResult::Empty => 0,
+    })
+}
+
+/// The calendar month before the one `now` falls in, from its first day up to the
+/// first day of the next
+pub fn previous_month(now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let month_start = |date: NaiveDate| date - Days::new(date.day0().into());
+    let end = month_start(now.date_naive());
+    let start = month_start(end - Days::new(1));
+    (start.and_time(NaiveTime::MIN).and_utc(), end.and_time(NaiveTime::MIN).and_utc())
+}
+
+/// The price of one billed unit of the metric and how much of the metric makes one.
+/// Runtime and CPU time are billed by the hour, storage by the GB-month of 730 hours.
+fn unit_price(prices: &Prices, metric: UsageMetric) -> (i64, i64) {
+    match metric {
+        UsageMetric::RuntimeSeconds => (prices.runtime_hour_cents.into(), 3600),
+        UsageMetric::CpuSeconds => (prices.cpu_hour_cents.into(), 3600),
+        UsageMetric::StorageGbHours => (prices.storage_gb_month_cents.into(), 730),
+    }
+}
+
+/// Rounded up to the next cent
+fn line_amount(quantity: i64, unit_price_cents: i64, per_unit: i64) -> i64 {
+    let cents = (i128::from(quantity) * i128::from(unit_price_cents) + i128::from(per_unit) - 1)
+        / i128::from(per_unit);
+    i64::try_from(cents).unwrap_or(i64::MAX)
+}
+
+/// Turns the usage recorded between `start` and `end` into an open invoice per
+/// organisation, with a line for each metric. Organisations already invoiced for the
+/// period are skipped, so running it again doesn't bill twice. Returns the new invoices.
+#[tracing::instrument(name = "db.invoice_period", skip(db, clock, prices), fields(otel.kind = "client"))]
+pub async fn invoice_period(
+    db: &DatabaseConnection,
+    clock: &dyn Clock,
+    prices: &Prices,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<InvoiceModel>, AppError> {
+    // Postgres sums bigints as numeric. Usage of purged organisations has no one to bill.
+    let totals: Vec<(Uuid, UsageMetric, i64)> = UsageRecord::find()
+        .select_only()
+        .column(usage_record::Column::OrganisationId)
+        .column(usage_record::Column::Metric)
+        .column_as(
+            Expr::col(usage_record::Column::Quantity).sum().cast_as(Alias::new("bigint")),
+            "quantity",
+        )
+        .filter(usage_record::Column::OrganisationId.is_not_null())
+        .filter(usage_record::Column::RecordedAt.gte(start))
+        .filter(usage_record::Column::RecordedAt.lt(end))
+        .group_by(usage_record::Column::OrganisationId)
+        .group_by(usage_record::Column::Metric)
+        .order_by_asc(usage_record::Column::Metric)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let mut usage: BTreeMap<Uuid, Vec<(UsageMetric, i64)>> = BTreeMap::new();
+    for (organisation_id, metric, quantity) in totals {
+        usage.entry(organisation_id).or_default().push((metric, quantity));
+    }
+
+    let now = clock.now();
+    let mut invoices = Vec::new();
+    for (organisation_id, metrics) in usage {
+        let prices = prices.clone();
+        let invoice = db
+            .transaction::<_, Option<InvoiceModel>, AppError>(|transaction| {
+                Box::pin(async move {
+                    let invoiced = Invoice::find()
+                        .filter(invoice::Column::OrganisationId.eq(organisation_id))
+                        .filter(invoice::Column::PeriodStart.eq(start))
+                        .count(transaction)
+                        .await?;
+                    if invoiced > 0 {
+                        return Ok(None);
+                    }
+
+                    let invoice_id = Uuid::new_v4();
+                    let lines: Vec<InvoiceLineActiveModel> = metrics
+                        .into_iter()
+                        .map(|(metric, quantity)| {
+                            let (unit_price_cents, per_unit) = unit_price(&prices, metric);
+                            InvoiceLineActiveModel {
+                                id: Set(Uuid::new_v4()),
+                                invoice_id: Set(invoice_id),
+                                metric: Set(metric),
+                                quantity: Set(quantity),
+                                unit_price_cents: Set(unit_price_cents),
+                                amount_cents: Set(line_amount(quantity, unit_price_cents, per_unit)),
+                            }
+                        })
+                        .collect();
+                    let total_cents = lines
+                        .iter()
+                        .map(|line| *line.amount_cents.as_ref())
+                        .fold(0i64, i64::saturating_add);
+
+                    let invoice = InvoiceActiveModel {
+                        id: Set(invoice_id),
+                        organisation_id: Set(Some(organisation_id)),
+                        period_start: Set(start),
+                        period_end: Set(end),
+                        currency: Set(prices.currency),
+                        total_cents: Set(total_cents),
+                        status: Set(InvoiceStatus::Open),
+                        payment_reference: Set(None),
+                        failure_reason: Set(None),
+                        created_at: Set(now),
+                        updated_at: Set(now),
+                    }
+                    .insert(transaction)
+                    .await?;
+                    InvoiceLine::insert_many(lines).exec(transaction).await?;
+                    Ok(Some(invoice))
+                })
+            })
+            .await?;
+        invoices.extend(invoice);
+    }
+
+    log_info!(
+        "Created {} invoices for usage from {} to {}",
+        invoices.len(),
+        start,
+        end
+    );
+    Ok(invoices)
+}
+
+/// How long an invoice stays claimed by a charge that never finished, such as one cut
+/// off by a restart, before it can be charged again
+pub const CHARGE_CLAIM_TIMEOUT: TimeDelta = TimeDelta::minutes(15);
+
+/// Invoices whose charge was abandoned. Charging them again is safe thanks to the
+/// idempotency key.
+pub fn abandoned_charge(now: DateTime<Utc>) -> Condition {
+    Condition::all()
+        .add(invoice::Column::Status.eq(InvoiceStatus::Processing))
+        .add(invoice::Column::UpdatedAt.lt(now - CHARGE_CLAIM_TIMEOUT))
+}
+
+/// Collects an open or failed invoice. The invoice is first claimed by moving it to
+/// `processing` with a conditional update, so concurrent callers (the monthly job, an
+/// owner paying by hand) can't charge it twice; the loser gets a conflict, or the
+/// invoice if it is already paid. Once paid, a suspended subscription becomes active
+/// again unless other invoices are still unpaid. A failed charge marks the invoice
+/// failed and suspends the subscription, which stops deploys until it is paid.
+#[tracing::instrument(name = "billing.charge_invoice", skip_all, fields(invoice_id = %invoice.id))]
+pub async fn charge_invoice(
+    db: &DatabaseConnection,
+    clock: &dyn Clock,
+    payments: &dyn PaymentProvider,
+    invoice: InvoiceModel,
+) -> Result<InvoiceModel, AppError> {
+    // Purging skips organisations with unpaid invoices, so this is only ever paid
+    let organisation_id = invoice.organisation_id.ok_or_else(|| {
+        AppError::Conflict(format!("Invoice {} belongs to a purged organisation", invoice.id))
+    })?;
+    let claimed = Invoice::update_many()
+        .col_expr(invoice::Column::Status, Expr::value(InvoiceStatus::Processing))
+        .col_expr(invoice::Column::UpdatedAt, Expr::value(clock.now()))
+        .filter(invoice::Column::Id.eq(invoice.id))
+        .filter(
+            Condition::any()
+                .add(invoice::Column::Status.is_in([InvoiceStatus::Open, InvoiceStatus::Failed]))
+                .add(abandoned_charge(clock.now())),
+        )
+        .exec(db)
+        .await?;
+    if claimed.rows_affected == 0 {
+        let current = Invoice::find_by_id(invoice.id).one(db).await?.unwrap_or(invoice);
+        if current.status == InvoiceStatus::Paid {
+            return Ok(current);
+        }
+        return Err(AppError::Conflict(format!(
+            "Invoice {} is already being charged",
+            current.id
+        )));
+    }
+
+    let result = match invoice.total_cents {
+        0 => Ok(None),
+        amount_cents => payments
+            .charge(Charge {
+                invoice_id: invoice.id,
+                idempotency_key: invoice.id,
+                organisation_id,
+                amount_cents,
+                currency: invoice.currency.clone(),
+            })
+            .await
+            .map(Some),
+    };
+
+    let now = clock.now();
+    let mut invoice: InvoiceActiveModel = invoice.into();
+    invoice.updated_at = Set(now);
+    match result {
+        Ok(reference) => {
+            invoice.status = Set(InvoiceStatus::Paid);
+            invoice.payment_reference = Set(reference);
+            invoice.failure_reason = Set(None);
+            let invoice = invoice.update(db).await?;
+
+            let unpaid = Invoice::find()
+                .filter(invoice::Column::OrganisationId.eq(organisation_id))
+                .filter(invoice::Column::Status.eq(InvoiceStatus::Failed))
+                .count(db)
+                .await?;
+            if unpaid == 0 {
+                set_subscription_status(db, now, organisation_id, SubscriptionStatus::Active).await?;
+            }
+            Ok(invoice)
+        }
+        Err(err) => {
+            invoice.status = Set(InvoiceStatus::Failed);
+            invoice.failure_reason = Set(Some(err.to_string()));
+            invoice.update(db).await?;
+            set_subscription_status(db, now, organisation_id, SubscriptionStatus::Suspended).await?;
+
+            log_info!("Suspended organisation {} after a failed payment: {}", organisation_id, err);
+            Err(err)
+        }
+    }
+}
+
+/// Organisations without a subscription row have never failed a payment and are active
+pub async fn get_subscription<C: ConnectionTrait>(
+    db: &C,
+    organisation_id: Uuid,
+) -> Result<Option<SubscriptionModel>, AppError> {
+    Ok(Subscription::find_by_id(organisation_id).one(db).await?)
+}
+
+async fn set_subscription_status<C: ConnectionTrait>(
+    db: &C,
+    now: DateTime<Utc>,
+    organisation_id: Uuid,
+    status: SubscriptionStatus,
+) -> Result<(), AppError> {
+    let current = get_subscription(db, organisation_id).await?;
+    let current_status = current.map_or(SubscriptionStatus::Active, |subscription| subscription.status);
+    if current_status == status {
+        return Ok(());
+    }
+
+    let subscription = SubscriptionActiveModel {
+        organisation_id: Set(organisation_id),
+        status: Set(status),
+        suspended_at: Set((status == SubscriptionStatus::Suspended).then_some(now)),
+        updated_at: Set(now),
+    };
+    Subscription::insert(subscription)
+        .on_conflict(
+            OnConflict::column(subscription::Column::OrganisationId)
+                .update_columns([
+                    subscription::Column::Status,
+                    subscription::Column::SuspendedAt,
+                    subscription::Column::UpdatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+    Ok(())
+}
+
+/// Fails with `subscription_suspended` while a failed payment holds the organisation's
+/// subscription suspended. Creating, unarchiving and restoring projects check this, so
+/// a suspended organisation can't bring new projects into service until it pays.
+pub async fn require_deployable<C: ConnectionTrait>(db: &C, organisation_id: Uuid) -> Result<(), AppError> {
+    match get_subscription(db, organisation_id).await? {
+        Some(subscription) if subscription.status == SubscriptionStatus::Suspended => Err(
+            AppError::Organisation(OrganisationError::SubscriptionSuspended(organisation_id)),
+        ),
+        _ => Ok(()),
+    }
+}
+
+impl Keyset for Invoice {
+    fn keyset() -> (invoice::Column, invoice::Column) {
+        (invoice::Column::CreatedAt, invoice::Column::Id)
+    }
+
+    fn cursor(invoice: &InvoiceModel) -> Cursor {
+        Cursor {
+            created_at: invoice.created_at,
+            id: invoice.id,
+        }
+    }
+}
+
+/// The requested page of the organisation's invoices, newest first, each with its lines
+#[tracing::instrument(name = "db.list_invoices", skip(db), fields(otel.kind = "client"))]
+pub async fn list_invoices<C: ConnectionTrait>(
+    db: &C,
+    organisation_id: Uuid,
+    pagination: Pagination,
+) -> Result<PaginatedResponse<(InvoiceModel, Vec<InvoiceLineModel>)>, AppError> {
+    let select = Invoice::find().filter(invoice::Column::OrganisationId.eq(organisation_id));
+    let page = fetch_page(db, select, pagination, Vec::new()).await?;
+
+    let invoice_ids: Vec<Uuid> = page.data.iter().map(|invoice| invoice.id).collect();
+    let mut lines: HashMap<Uuid, Vec<InvoiceLineModel>> = HashMap::new();
+    for line in InvoiceLine::find()
+        .filter(invoice_line::Column::InvoiceId.is_in(invoice_ids))
+        .order_by_asc(invoice_line::Column::Metric)
+        .all(db)
+        .await?
+    {
+        lines.entry(line.invoice_id).or_default().push(line);
+    }
+
+    Ok(page.map(|invoice| {
+        let invoice_lines = lines.remove(&invoice.id).unwrap_or_default();
+        (invoice, invoice_lines)
+    }))
+}
+
+/// The organisation's invoice, reported as not found when it belongs to another one
+pub async fn get_invoice<C: ConnectionTrait>(
+    db: &C,
+    organisation_id: Uuid,
+    invoice_id: Uuid,
+) -> Result<InvoiceModel, AppError> {
+    Invoice::find_by_id(invoice_id)
+        .filter(invoice::Column::OrganisationId.eq(organisation_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Invoice not found: {}", invoice_id)))
+}
+
+pub async fn invoice_lines<C: ConnectionTrait>(
+    db: &C,
+    invoice_id: Uuid,
+) -> Result<Vec<InvoiceLineModel>, AppError> {
+    Ok(InvoiceLine::find()
+        .filter(invoice_line::Column::InvoiceId.eq(invoice_id))
+        .order_by_asc(invoice_line::Column::Metric)
+        .all(db)
+        .await?)
+}
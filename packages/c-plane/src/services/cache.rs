@@ -0,0 +1,171 @@
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use lru::LruCache;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::errors::AppError;
+use crate::log_warn;
+use crate::services::valkey::Valkey;
+use crate::utils::metrics::metrics;
+
+/// Entries kept in memory before the least recently used one is dropped to make room.
+/// Expired entries are dropped when they are read or pushed out.
+const MAX_MEMORY_ENTRIES: NonZeroUsize = NonZeroUsize::new(10_000).unwrap();
+
+/// Where lookups are cached, behind a trait so instances can share entries
+#[async_trait]
+pub trait Cache: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<String>, AppError>;
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), AppError>;
+
+    async fn delete(&self, keys: &[String]) -> Result<(), AppError>;
+}
+
+struct Entry {
+    value: String,
+    expires_at: Instant,
+}
+
+/// Entries of this instance only. Invalidations don't reach other instances, whose
+/// entries live out their TTL.
+pub struct MemoryCache {
+    entries: Mutex<LruCache<String, Entry>>,
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(MAX_MEMORY_ENTRIES)),
+        }
+    }
+}
+
+impl MemoryCache {
+    fn entries(&self) -> std::sync::MutexGuard<'_, LruCache<String, Entry>> {
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl Cache for MemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<String>, AppError> {
+        let mut entries = self.entries();
+        match entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => Ok(Some(entry.value.clone())),
+            Some(_) => {
+                entries.pop(key);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), AppError> {
+        let entry = Entry {
+            value: value.to_string(),
+            expires_at: Instant::now() + ttl,
+        };
+        self.entries().put(key.to_string(), entry);
+        Ok(())
+    }
+
+    async fn delete(&self, keys: &[String]) -> Result<(), AppError> {
+        let mut entries = self.entries();
+        for key in keys {
+            entries.pop(key);
+        }
+        Ok(())
+    }
+}
+
+/// Entries shared by every instance through Valkey, so an invalidation reaches all of them
+pub struct ValkeyCache {
+    valkey: Arc<Valkey>,
+}
+
+impl ValkeyCache {
+    pub fn new(valkey: Arc<Valkey>) -> Self {
+        Self { valkey }
+    }
+}
+
+fn valkey_key(key: &str) -> String {
+    format!("cache:{}", key)
+}
+
+#[async_trait]
+impl Cache for ValkeyCache {
+    async fn get(&self, key: &str) -> Result<Option<String>, AppError> {
+        self.valkey.query(redis::cmd("GET").arg(valkey_key(key))).await
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), AppError> {
+        let ttl_ms = ttl.as_millis().max(1) as u64;
+        self.valkey
+            .query(redis::cmd("SET").arg(valkey_key(key)).arg(value).arg("PX").arg(ttl_ms))
+            .await
+    }
+
+    async fn delete(&self, keys: &[String]) -> Result<(), AppError> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let keys: Vec<String> = keys.iter().map(|key| valkey_key(key)).collect();
+        self.valkey.query(redis::cmd("DEL").arg(keys)).await
+    }
+}
+
+/// The value under `key`, loaded and stored for `ttl` on a miss. `kind` labels the
+/// hit and miss counters. The cache never fails a lookup: its errors count as misses,
+/// and errors from `load` are passed on without being cached.
+pub async fn cached<T, F, Fut>(
+    cache: &dyn Cache,
+    kind: &'static str,
+    key: &str,
+    ttl: Duration,
+    load: F,
+) -> Result<T, AppError>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, AppError>>,
+{
+    if ttl.is_zero() {
+        return load().await;
+    }
+
+    match cache.get(key).await {
+        Ok(Some(value)) => match serde_json::from_str(&value) {
+            Ok(value) => {
+                metrics().observe_cache(kind, "hit");
+                return Ok(value);
+            }
+            Err(err) => {
+                log_warn!("Ignoring unreadable {} cache entry: {}", kind, err);
+                metrics().observe_cache(kind, "miss");
+            }
+        },
+        Ok(None) => metrics().observe_cache(kind, "miss"),
+        Err(err) => {
+            log_warn!("Bypassing the {} cache: {}", kind, err);
+            metrics().observe_cache(kind, "error");
+        }
+    }
+
+    let value = load().await?;
+    match serde_json::to_string(&value) {
+        Ok(encoded) => {
+            if let Err(err) = cache.set(key, &encoded, ttl).await {
+                log_warn!("Could not cache {}: {}", kind, err);
+            }
+        }
+        Err(err) => log_warn!("Could not encode {} for the cache: {}", kind, err),
+    }
+    Ok(value)
+}
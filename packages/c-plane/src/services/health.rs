@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use migration::{Migrator, MigratorTrait};
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::jobs;
+use crate::services::kratos::KratosClient;
+
+/// Upper bound for a single readiness check
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Oldest due job age before the queue is reported as lagging
+const MAX_JOB_QUEUE_LAG: Duration = Duration::from_secs(300);
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ComponentStatus {
+    Ok,
+    Failed,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct ComponentHealth {
+    pub status: ComponentStatus,
+    /// A failing required component makes the whole service unready
+    pub required: bool,
+    pub latency_ms: u64,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct Readiness {
+    pub ready: bool,
+    #[schema(value_type = BTreeMap<String, ComponentHealth>)]
+    pub components: BTreeMap<&'static str, ComponentHealth>,
+}
+
+#[tracing::instrument(name = "health.check_readiness", skip_all)]
+pub async fn check_readiness(db: &DatabaseConnection, kratos: &dyn KratosClient) -> Readiness {
+    let (database, migrations, kratos, job_queue) = futures_util::join!(
+        check_database(db),
+        check_migrations(db),
+        check_kratos(kratos),
+        check_job_queue(db),
+    );
+
+    let components = BTreeMap::from([
+        ("database", database),
+        ("migrations", migrations),
+        ("kratos", kratos),
+        ("job_queue", job_queue),
+    ]);
+    let ready = components
+        .values()
+        .all(|component| !component.required || component.status == ComponentStatus::Ok);
+
+    Readiness { ready, components }
+}
+
+async fn check_database(db: &DatabaseConnection) -> ComponentHealth {
+    run_check(true, async {
+        db.ping().await.map_err(|e| e.to_string())?;
+        Ok(None)
+    })
+    .await
+}
+
+async fn check_migrations(db: &DatabaseConnection) -> ComponentHealth {
+    run_check(true, async {
+        let pending = Migrator::get_pending_migrations(db)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if pending.is_empty() {
+            return Ok(None);
+        }
+        let names: Vec<&str> = pending.iter().map(|migration| migration.name()).collect();
+        Err(format!("Pending migrations: {}", names.join(", ")))
+    })
+    .await
+}
+
+async fn check_kratos(kratos: &dyn KratosClient) -> ComponentHealth {
+    run_check(true, async {
+        let version = kratos
+            .admin_version()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(Some(format!("Kratos {}", version)))
+    })
+    .await
+}
+
+async fn check_job_queue(db: &DatabaseConnection) -> ComponentHealth {
+    run_check(false, async {
+        let lag = jobs::queue_lag(db)
+            .await
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default();
+        let message = format!("Lag {}s", lag.as_secs());
+
+        if lag > MAX_JOB_QUEUE_LAG {
+            return Err(message);
+        }
+        Ok(Some(message))
+    })
+    .await
+}
+
+/// Runs `check` with `CHECK_TIMEOUT`, turning errors and timeouts into a failed component
+async fn run_check<F>(required: bool, check: F) -> ComponentHealth
+where
+    F: Future<Output = Result<Option<String>, String>>,
+{
+    let start_time = Instant::now();
+    let result = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("Timed out after {}ms", CHECK_TIMEOUT.as_millis())),
+    };
+    let latency_ms = start_time.elapsed().as_millis() as u64;
+
+    match result {
+        Ok(message) => ComponentHealth {
+            status: ComponentStatus::Ok,
+            required,
+            latency_ms,
+            message,
+        },
+        Err(message) => ComponentHealth {
+            status: ComponentStatus::Failed,
+            required,
+            latency_ms,
+            message: Some(message),
+        },
+    }
+}
@@ -0,0 +1,110 @@
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    Set, TransactionTrait,
+};
+use uuid::Uuid;
+
+use crate::config::InstanceConfig;
+use crate::errors::{AppError, OrganisationError};
+use crate::log_info;
+use crate::models::entities::{
+    Organisation, OrganisationActiveModel, OrganisationMember, OrganisationMemberActiveModel,
+    OrganisationMemberModel, OrganisationModel, OrganisationRole, Plan, organisation,
+    organisation_member,
+};
+use crate::services::plans::reserve_member;
+use crate::utils::clock::Clock;
+
+/// Creates the instance's organisation on first start, or returns the existing one.
+/// Nobody has registered yet, so it is created by the nil identity and gets its Owner
+/// when the identity with `owner_email` registers. Self-hosted installs aren't billed,
+/// so it is on the enterprise plan, including an existing organisation left on another
+/// one, and every registration depends on it, so a deleted one is brought back.
+#[tracing::instrument(name = "db.bootstrap_instance", skip_all, fields(otel.kind = "client"))]
+pub async fn bootstrap<C: ConnectionTrait>(
+    db: &C,
+    clock: &dyn Clock,
+    instance: &InstanceConfig,
+) -> Result<OrganisationModel, AppError> {
+    let now = clock.now();
+    let organisation = OrganisationActiveModel {
+        id: Set(instance.organisation_id),
+        name: Set(instance.organisation_name.clone()),
+        description: Set(None),
+        avatar_url: Set(None),
+        is_active: Set(true),
+        created_at: Set(now),
+        updated_at: Set(now),
+        created_by: Set(Uuid::nil()),
+        plan: Set(Plan::Enterprise),
+        custom_limits: Set(None),
+        deleted_at: Set(None),
+        deleted_by: Set(None),
+    };
+    // Replicas starting together race to create it, the losers keep the winner's row
+    Organisation::insert(organisation)
+        .on_conflict(
+            OnConflict::column(organisation::Column::Id)
+                .update_columns([
+                    organisation::Column::Plan,
+                    organisation::Column::DeletedAt,
+                    organisation::Column::DeletedBy,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+    let organisation = Organisation::find_by_id(instance.organisation_id)
+        .one(db)
+        .await?
+        .ok_or(AppError::Organisation(OrganisationError::OrganisationNotFound(
+            instance.organisation_id,
+        )))?;
+    log_info!("Running single-tenant with organisation {}", organisation.id);
+    Ok(organisation)
+}
+
+/// Adds the identity to the instance's organisation with `role`. Joining again returns
+/// the existing membership, so a retried webhook is harmless.
+#[tracing::instrument(name = "db.join_organisation", skip(db, clock), fields(otel.kind = "client"))]
+pub async fn join_organisation(
+    db: &DatabaseConnection,
+    clock: &dyn Clock,
+    organisation_id: Uuid,
+    identity_id: Uuid,
+    role: OrganisationRole,
+) -> Result<OrganisationMemberModel, AppError> {
+    let now = clock.now();
+    db.transaction::<_, OrganisationMemberModel, AppError>(|transaction| {
+        Box::pin(async move {
+            let existing = OrganisationMember::find()
+                .filter(organisation_member::Column::OrganisationId.eq(organisation_id))
+                .filter(organisation_member::Column::IdentityId.eq(identity_id))
+                .one(transaction)
+                .await?;
+            if let Some(member) = existing {
+                return Ok(member);
+            }
+
+            reserve_member(transaction, organisation_id).await?;
+            let member = OrganisationMemberActiveModel {
+                id: Set(Uuid::new_v4()),
+                organisation_id: Set(organisation_id),
+                identity_id: Set(identity_id),
+                role: Set(role.clone()),
+                is_active: Set(true),
+                joined_at: Set(now),
+                invited_by: Set(identity_id),
+                invited_at: Set(now),
+                invitation_accepted_at: Set(now),
+            };
+            let member = member.insert(transaction).await?;
+            log_info!("Identity {} joined organisation {} as {:?}", identity_id, organisation_id, role);
+            Ok(member)
+        })
+    })
+    .await
+    .map_err(AppError::from)
+}
@@ -0,0 +1,153 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use ory_client::apis::configuration::Configuration as OryConfiguration;
+use ory_client::apis::{identity_api, metadata_api};
+use ory_client::models::Identity;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::errors::{AppError, ExternalError};
+use crate::services::cache::{Cache, cached};
+use crate::utils::logger::Logger;
+use crate::utils::telemetry::trace_headers;
+
+/// Ory client configuration for a single call, carrying the current trace context so
+/// Kratos spans join the caller's trace
+fn configuration(base_path: &str) -> Result<OryConfiguration, AppError> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in trace_headers() {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            headers.insert(name, value);
+        }
+    }
+
+    let client = reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .map_err(|e| AppError::External(ExternalError::OryApiError(e.to_string())))?;
+
+    Ok(OryConfiguration {
+        base_path: base_path.trim_end_matches('/').to_string(),
+        client,
+        ..OryConfiguration::default()
+    })
+}
+
+/// What the control plane needs to know about an identity, taken from its traits.
+/// Traits the identity schema doesn't have are left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentityDetails {
+    pub id: Uuid,
+    pub email: Option<String>,
+    pub name: Option<String>,
+}
+
+impl From<Identity> for IdentityDetails {
+    fn from(identity: Identity) -> Self {
+        let traits = identity.traits.unwrap_or_default();
+        let text = |value: &serde_json::Value| value.as_str().map(str::to_string);
+        let name = match &traits["name"] {
+            serde_json::Value::Object(name) => {
+                let parts: Vec<&str> = ["first", "last"]
+                    .iter()
+                    .filter_map(|part| name.get(*part).and_then(|part| part.as_str()))
+                    .filter(|part| !part.is_empty())
+                    .collect();
+                Some(parts.join(" ")).filter(|name| !name.is_empty())
+            }
+            name => text(name),
+        };
+
+        Self {
+            id: Uuid::parse_str(&identity.id).unwrap_or_default(),
+            email: text(&traits["email"]),
+            name,
+        }
+    }
+}
+
+/// The Kratos calls the control plane makes, behind a trait so tests can swap in a fake
+#[async_trait]
+pub trait KratosClient: Send + Sync {
+    /// Version reported by the Kratos admin API, doubles as a reachability check
+    async fn admin_version(&self) -> Result<String, AppError>;
+
+    async fn identity(&self, identity_id: Uuid) -> Result<IdentityDetails, AppError>;
+}
+
+/// `KratosClient::identity` through the cache. Kratos doesn't report changes, so
+/// an updated name or email shows once the entry expires.
+pub async fn identity_details(
+    kratos: &dyn KratosClient,
+    cache: &dyn Cache,
+    ttl: Duration,
+    identity_id: Uuid,
+) -> Result<IdentityDetails, AppError> {
+    let key = format!("identity:{}", identity_id);
+    cached(cache, "identity", &key, ttl, || kratos.identity(identity_id)).await
+}
+
+/// `KratosClient` backed by the Ory SDK
+pub struct OryKratos {
+    admin_url: String,
+}
+
+impl OryKratos {
+    pub fn new(admin_url: &str) -> Self {
+        Self {
+            admin_url: admin_url.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl KratosClient for OryKratos {
+    async fn admin_version(&self) -> Result<String, AppError> {
+        admin_version(&self.admin_url).await
+    }
+
+    async fn identity(&self, identity_id: Uuid) -> Result<IdentityDetails, AppError> {
+        get_identity(&self.admin_url, identity_id).await
+    }
+}
+
+#[tracing::instrument(name = "kratos.get_version", skip_all, fields(otel.kind = "client"))]
+async fn admin_version(admin_url: &str) -> Result<String, AppError> {
+    let configuration = configuration(admin_url)?;
+    let start_time = Instant::now();
+
+    let result = metadata_api::get_version(&configuration).await;
+    Logger::external_service(
+        "kratos",
+        "get_version",
+        result.is_ok(),
+        start_time.elapsed().as_millis() as u64,
+    );
+
+    result
+        .map(|version| version.version)
+        .map_err(|e| AppError::External(ExternalError::OryApiError(e.to_string())))
+}
+
+#[tracing::instrument(name = "kratos.get_identity", skip(admin_url), fields(otel.kind = "client"))]
+async fn get_identity(admin_url: &str, identity_id: Uuid) -> Result<IdentityDetails, AppError> {
+    let configuration = configuration(admin_url)?;
+    let start_time = Instant::now();
+
+    let result = identity_api::get_identity(&configuration, &identity_id.to_string(), None).await;
+    Logger::external_service(
+        "kratos",
+        "get_identity",
+        result.is_ok(),
+        start_time.elapsed().as_millis() as u64,
+    );
+
+    result
+        .map(IdentityDetails::from)
+        .map_err(|e| AppError::External(ExternalError::OryApiError(e.to_string())))
+}
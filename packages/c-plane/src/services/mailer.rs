@@ -0,0 +1,99 @@
+use std::time::Instant;
+
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::config::SmtpConfig;
+use crate::errors::{AppError, ExternalError};
+use crate::log_info;
+use crate::utils::logger::Logger;
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Outgoing email, behind a trait so tests can capture messages instead of sending them
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), AppError>;
+}
+
+/// `Mailer` delivering through the SMTP server from `Config::smtp`
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &SmtpConfig) -> Result<Self, AppError> {
+        let from = config
+            .from_address
+            .parse()
+            .map_err(|e| email_error(format!("Invalid sender address: {}", e)))?;
+
+        let mut transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+            .map_err(|e| email_error(e.to_string()))?
+            .port(config.port);
+        if let Some(username) = &config.username {
+            let password = config
+                .password
+                .as_ref()
+                .map(|password| password.expose().to_string())
+                .unwrap_or_default();
+            transport = transport.credentials(Credentials::new(username.clone(), password));
+        }
+
+        Ok(Self {
+            transport: transport.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    #[tracing::instrument(name = "smtp.send", skip_all, fields(otel.kind = "client"))]
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let to = email
+            .to
+            .parse()
+            .map_err(|e| email_error(format!("Invalid recipient address: {}", e)))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(email.subject)
+            .body(email.body)
+            .map_err(|e| email_error(e.to_string()))?;
+
+        let start_time = Instant::now();
+        let result = self.transport.send(message).await;
+        Logger::external_service(
+            "smtp",
+            "send",
+            result.is_ok(),
+            start_time.elapsed().as_millis() as u64,
+        );
+
+        result.map(|_| ()).map_err(|e| email_error(e.to_string()))
+    }
+}
+
+/// `Mailer` used when no SMTP server is configured, only logs what would be sent
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        log_info!("SMTP not configured, dropping email to {}: {}", email.to, email.subject);
+        Ok(())
+    }
+}
+
+fn email_error(message: String) -> AppError {
+    AppError::External(ExternalError::EmailServiceError(message))
+}
@@ -1,2 +1,15 @@
+pub mod audit;
+#[cfg(feature = "cloud")]
+pub mod billing;
+pub mod cache;
+pub mod health;
+#[cfg(not(feature = "cloud"))]
+pub mod instance;
+pub mod kratos;
+pub mod mailer;
 pub mod organisations;
+pub mod plans;
 pub mod projects;
+pub mod rate_limit;
+pub mod search;
+pub mod valkey;
@@ -1,12 +1,26 @@
-use sea_orm::{ActiveModelTrait, DatabaseConnection, DbErr, EntityTrait, Set, TransactionTrait};
+use std::time::Duration;
+
+use chrono::TimeDelta;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QuerySelect, Set, TransactionTrait,
+};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::errors::{AppError, DatabaseError, OrganisationError};
-use crate::models::entities::{Organisation, OrganisationActiveModel, OrganisationModel};
+use crate::errors::{AppError, OrganisationError};
+use crate::models::entities::{Organisation, OrganisationActiveModel, OrganisationModel, Plan};
 use crate::models::entities::{
     OrganisationMember, OrganisationMemberActiveModel, OrganisationMemberModel, OrganisationRole,
 };
+use crate::models::entities::organisation::Column as OrganisationColumn;
+use crate::models::entities::organisation_member::Column as MemberColumn;
+use crate::log_warn;
+use crate::services::cache::{Cache, cached};
+use crate::utils::clock::Clock;
+use crate::utils::listing::{FieldType, ListParams, Listable};
+use crate::utils::metrics::metrics;
+use crate::utils::pagination::{Cursor, Keyset, PaginatedResponse, Pagination, fetch_page};
 
 #[derive(Serialize, Deserialize)]
 pub struct CreateOrganisationData {
@@ -16,11 +30,13 @@ pub struct CreateOrganisationData {
     pub identity_id: Uuid,
 }
 
+#[tracing::instrument(name = "db.create_organisation", skip_all, fields(otel.kind = "client"))]
 pub async fn create_organisation(
-    db: DatabaseConnection,
+    db: &DatabaseConnection,
+    clock: &dyn Clock,
     data: CreateOrganisationData,
 ) -> Result<(OrganisationModel, OrganisationMemberModel), AppError> {
-    let now = chrono::Utc::now().naive_utc();
+    let now = clock.now();
     let created_by = data.identity_id;
     let (organisation, organisation_member) = db
         .transaction::<_, (OrganisationModel, OrganisationMemberModel), DbErr>(|transaction| {
@@ -35,6 +51,10 @@ pub async fn create_organisation(
                     created_by: Set(created_by),
                     avatar_url: Set(data.avatar_url.clone()),
                     is_active: Set(true),
+                    plan: Set(Plan::Free),
+                    custom_limits: Set(None),
+                    deleted_at: Set(None),
+                    deleted_by: Set(None),
                 };
                 let organisation: OrganisationModel = organisation.insert(transaction).await?;
 
@@ -55,22 +75,287 @@ pub async fn create_organisation(
                 Ok((organisation, organisation_member))
             })
         })
-        .await
-        .map_err(|e| AppError::Database(DatabaseError::TransactionFailed(e.to_string())))?;
+        .await?;
 
     Ok((organisation, organisation_member))
 }
 
-pub async fn get_organisation(
-    db: DatabaseConnection,
+#[tracing::instrument(name = "db.get_organisation", skip(db), fields(otel.kind = "client"))]
+pub async fn get_organisation<C: ConnectionTrait>(
+    db: &C,
     organisation_id: Uuid,
 ) -> Result<OrganisationModel, AppError> {
     let organisation = Organisation::find_by_id(organisation_id)
-        .one(&db)
+        .filter(OrganisationColumn::DeletedAt.is_null())
+        .one(db)
         .await?
-        .ok_or_else(|| {
-            AppError::Organisation(OrganisationError::OrganisationNotFound(organisation_id))
-        })?;
+        .ok_or(AppError::Organisation(
+            OrganisationError::OrganisationNotFound(organisation_id),
+        ))?;
 
     Ok(organisation)
 }
+
+impl Keyset for Organisation {
+    fn keyset() -> (OrganisationColumn, OrganisationColumn) {
+        (OrganisationColumn::CreatedAt, OrganisationColumn::Id)
+    }
+
+    fn cursor(organisation: &OrganisationModel) -> Cursor {
+        Cursor {
+            created_at: organisation.created_at,
+            id: organisation.id,
+        }
+    }
+}
+
+/// The requested page of organisations the identity is an active member of, newest first
+#[tracing::instrument(name = "db.list_organisations", skip(db), fields(otel.kind = "client"))]
+pub async fn list_organisations<C: ConnectionTrait>(
+    db: &C,
+    identity_id: Uuid,
+    pagination: Pagination,
+) -> Result<PaginatedResponse<OrganisationModel>, AppError> {
+    let organisation_ids = member_organisation_ids(db, identity_id).await?;
+    let select = Organisation::find().filter(OrganisationColumn::Id.is_in(organisation_ids));
+    Ok(fetch_page(db, select, pagination, Vec::new()).await?)
+}
+
+/// The caller's active membership in `organisation_id`. Non-members get the same
+/// not-found error as a missing organisation, so IDs can't be probed. Deleted
+/// organisations have no members as far as this is concerned.
+#[tracing::instrument(name = "db.require_member", skip(db), fields(otel.kind = "client"))]
+pub async fn require_member<C: ConnectionTrait>(
+    db: &C,
+    organisation_id: Uuid,
+    identity_id: Uuid,
+) -> Result<OrganisationMemberModel, AppError> {
+    OrganisationMember::find()
+        .inner_join(Organisation)
+        .filter(OrganisationColumn::DeletedAt.is_null())
+        .filter(MemberColumn::OrganisationId.eq(organisation_id))
+        .filter(MemberColumn::IdentityId.eq(identity_id))
+        .filter(MemberColumn::IsActive.eq(true))
+        .one(db)
+        .await?
+        .ok_or(AppError::Organisation(
+            OrganisationError::OrganisationNotFound(organisation_id),
+        ))
+}
+
+fn membership_version_key(organisation_id: Uuid) -> String {
+    format!("membership-version:{}", organisation_id)
+}
+
+/// Membership entries are keyed by the organisation's version, which `forget_members`
+/// replaces. A lookup that read the database before a deletion and stores its entry
+/// after it stores it under the old version, where nothing reads it.
+fn membership_key(organisation_id: Uuid, version: &str, identity_id: Uuid) -> String {
+    format!("membership:{}:{}:{}", organisation_id, version, identity_id)
+}
+
+/// `require_member` through the cache, for checks outside a transaction. Only
+/// memberships are cached, so someone joining is let in right away.
+pub async fn cached_member(
+    db: &DatabaseConnection,
+    cache: &dyn Cache,
+    ttl: Duration,
+    organisation_id: Uuid,
+    identity_id: Uuid,
+) -> Result<OrganisationMemberModel, AppError> {
+    if ttl.is_zero() {
+        return require_member(db, organisation_id, identity_id).await;
+    }
+    // Read before the database, so a deletion in between changes it
+    let version = match cache.get(&membership_version_key(organisation_id)).await {
+        Ok(version) => version.unwrap_or_default(),
+        Err(err) => {
+            log_warn!("Bypassing the membership cache: {}", err);
+            metrics().observe_cache("membership", "error");
+            return require_member(db, organisation_id, identity_id).await;
+        }
+    };
+    let key = membership_key(organisation_id, &version, identity_id);
+    cached(cache, "membership", &key, ttl, || {
+        require_member(db, organisation_id, identity_id)
+    })
+    .await
+}
+
+/// Drops the cached memberships of everyone in the organisation by giving it a new
+/// version. Call it once the change is committed. The version outlives the entries
+/// stored under the one it replaces, which would be read again once it expired.
+pub async fn forget_members(cache: &dyn Cache, ttl: Duration, organisation_id: Uuid) {
+    if ttl.is_zero() {
+        return;
+    }
+    let version = Uuid::new_v4().to_string();
+    if let Err(err) = cache.set(&membership_version_key(organisation_id), &version, ttl * 2).await {
+        log_warn!("Could not invalidate the memberships of {}: {}", organisation_id, err);
+    }
+}
+
+impl Keyset for OrganisationMember {
+    fn keyset() -> (MemberColumn, MemberColumn) {
+        (MemberColumn::JoinedAt, MemberColumn::Id)
+    }
+
+    fn cursor(member: &OrganisationMemberModel) -> Cursor {
+        Cursor {
+            created_at: member.joined_at,
+            id: member.id,
+        }
+    }
+}
+
+impl Listable for OrganisationMember {
+    fn filter_field(name: &str) -> Option<(MemberColumn, FieldType)> {
+        match name {
+            "role" => Some((
+                MemberColumn::Role,
+                FieldType::Enum(&[
+                    ("Owner", "owner"),
+                    ("Admin", "admin"),
+                    ("Member", "member"),
+                    ("Viewer", "viewer"),
+                ]),
+            )),
+            "is_active" => Some((MemberColumn::IsActive, FieldType::Bool)),
+            _ => None,
+        }
+    }
+
+    fn sort_field(name: &str) -> Option<MemberColumn> {
+        match name {
+            // When the member joined, the listing's creation time
+            "created_at" => Some(MemberColumn::JoinedAt),
+            _ => None,
+        }
+    }
+
+    fn search_fields() -> Vec<MemberColumn> {
+        Vec::new()
+    }
+}
+
+/// The requested page of the organisation's members, most recently joined first.
+/// Only active members are listed unless `is_active` is filtered by.
+#[tracing::instrument(name = "db.list_members", skip(db, list), fields(otel.kind = "client"))]
+pub async fn list_members<C: ConnectionTrait>(
+    db: &C,
+    organisation_id: Uuid,
+    list: ListParams<OrganisationMember>,
+) -> Result<PaginatedResponse<OrganisationMemberModel>, AppError> {
+    let mut select = OrganisationMember::find().filter(MemberColumn::OrganisationId.eq(organisation_id));
+    if !list.filters_by("is_active") {
+        select = select.filter(MemberColumn::IsActive.eq(true));
+    }
+    Ok(list.fetch(db, select).await?)
+}
+
+/// IDs of every organisation the identity is an active member of, leaving out deleted ones
+pub async fn member_organisation_ids<C: ConnectionTrait>(
+    db: &C,
+    identity_id: Uuid,
+) -> Result<Vec<Uuid>, AppError> {
+    let ids = OrganisationMember::find()
+        .select_only()
+        .column(MemberColumn::OrganisationId)
+        .inner_join(Organisation)
+        .filter(OrganisationColumn::DeletedAt.is_null())
+        .filter(MemberColumn::IdentityId.eq(identity_id))
+        .filter(MemberColumn::IsActive.eq(true))
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(ids)
+}
+
+/// Fails with `insufficient_role` unless the membership is an Owner's
+pub fn require_owner(member: &OrganisationMemberModel) -> Result<(), AppError> {
+    if member.role == OrganisationRole::Owner {
+        return Ok(());
+    }
+    Err(AppError::Organisation(OrganisationError::InsufficientRole {
+        required: format!("{:?}", OrganisationRole::Owner),
+        current: format!("{:?}", member.role),
+    }))
+}
+
+/// Fails with `insufficient_role` unless the membership is an Admin's or an Owner's
+pub fn require_admin(member: &OrganisationMemberModel) -> Result<(), AppError> {
+    if matches!(member.role, OrganisationRole::Owner | OrganisationRole::Admin) {
+        return Ok(());
+    }
+    Err(AppError::Organisation(OrganisationError::InsufficientRole {
+        required: format!("{:?}", OrganisationRole::Admin),
+        current: format!("{:?}", member.role),
+    }))
+}
+
+/// Marks the organisation as deleted. `is_active` is left as it was, so restoring
+/// doesn't reactivate an organisation that was inactive before it was deleted. Its
+/// projects and members stay untouched, but are hidden along with it until it is
+/// restored or purged.
+#[tracing::instrument(name = "db.delete_organisation", skip(db, clock, cache), fields(otel.kind = "client"))]
+pub async fn delete_organisation(
+    db: &DatabaseConnection,
+    clock: &dyn Clock,
+    cache: &dyn Cache,
+    membership_ttl: Duration,
+    identity_id: Uuid,
+    organisation_id: Uuid,
+) -> Result<(), AppError> {
+    let member = require_member(db, organisation_id, identity_id).await?;
+    require_owner(&member)?;
+
+    let now = clock.now();
+    let mut organisation: OrganisationActiveModel = get_organisation(db, organisation_id).await?.into();
+    organisation.deleted_at = Set(Some(now));
+    organisation.deleted_by = Set(Some(identity_id));
+    organisation.updated_at = Set(now);
+    organisation.update(db).await?;
+    forget_members(cache, membership_ttl, organisation_id).await;
+    Ok(())
+}
+
+/// Undoes `delete_organisation` while the retention period lasts; afterwards the
+/// organisation is reported as not found. Restoring one that isn't deleted is a no-op.
+#[tracing::instrument(name = "db.restore_organisation", skip(db, clock), fields(otel.kind = "client"))]
+pub async fn restore_organisation<C: ConnectionTrait>(
+    db: &C,
+    clock: &dyn Clock,
+    retention_days: u32,
+    identity_id: Uuid,
+    organisation_id: Uuid,
+) -> Result<OrganisationModel, AppError> {
+    let not_found = || AppError::Organisation(OrganisationError::OrganisationNotFound(organisation_id));
+
+    // `require_member` skips deleted organisations, which are the ones to restore here
+    let member = OrganisationMember::find()
+        .filter(MemberColumn::OrganisationId.eq(organisation_id))
+        .filter(MemberColumn::IdentityId.eq(identity_id))
+        .filter(MemberColumn::IsActive.eq(true))
+        .one(db)
+        .await?
+        .ok_or_else(not_found)?;
+    require_owner(&member)?;
+
+    let organisation = Organisation::find_by_id(organisation_id)
+        .one(db)
+        .await?
+        .ok_or_else(not_found)?;
+    let Some(deleted_at) = organisation.deleted_at else {
+        return Ok(organisation);
+    };
+    let now = clock.now();
+    if deleted_at < now - TimeDelta::days(retention_days.into()) {
+        return Err(not_found());
+    }
+
+    let mut organisation: OrganisationActiveModel = organisation.into();
+    organisation.deleted_at = Set(None);
+    organisation.deleted_by = Set(None);
+    organisation.updated_at = Set(now);
+    Ok(organisation.update(db).await?)
+}
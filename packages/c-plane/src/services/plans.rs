@@ -0,0 +1,131 @@
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter, QuerySelect};
+use uuid::Uuid;
+
+use crate::errors::{AppError, OrganisationError, ProjectError};
+use crate::models::entities::{
+    Organisation, OrganisationMember, OrganisationModel, Plan, PlanLimits, Project, organisation,
+    organisation_member, project,
+};
+
+/// Limits of the built-in plans. The custom plan falls back to the free limits until
+/// the organisation has its own.
+pub fn plan_limits(plan: &Plan) -> PlanLimits {
+    match plan {
+        Plan::Free | Plan::Custom => PlanLimits {
+            projects: Some(3),
+            members: Some(5),
+            deployments: Some(10),
+            agents: Some(1),
+            api_keys: Some(2),
+            log_retention_days: 7,
+        },
+        Plan::Team => PlanLimits {
+            projects: Some(50),
+            members: Some(50),
+            deployments: Some(500),
+            agents: Some(10),
+            api_keys: Some(20),
+            log_retention_days: 30,
+        },
+        Plan::Enterprise => PlanLimits {
+            projects: None,
+            members: None,
+            deployments: None,
+            agents: None,
+            api_keys: None,
+            log_retention_days: 365,
+        },
+    }
+}
+
+/// The limits that apply to the organisation
+pub fn organisation_limits(organisation: &OrganisationModel) -> PlanLimits {
+    match (&organisation.plan, &organisation.custom_limits) {
+        (Plan::Custom, Some(limits)) => limits.clone(),
+        (plan, _) => plan_limits(plan),
+    }
+}
+
+/// Projects counting towards the quota, deleted ones are left out
+pub async fn count_projects<C: ConnectionTrait>(db: &C, organisation_id: Uuid) -> Result<u64, AppError> {
+    Ok(Project::find()
+        .filter(project::Column::OrganisationId.eq(organisation_id))
+        .filter(project::Column::DeletedAt.is_null())
+        .count(db)
+        .await?)
+}
+
+pub async fn count_members<C: ConnectionTrait>(db: &C, organisation_id: Uuid) -> Result<u64, AppError> {
+    Ok(OrganisationMember::find()
+        .filter(organisation_member::Column::OrganisationId.eq(organisation_id))
+        .filter(organisation_member::Column::IsActive.eq(true))
+        .count(db)
+        .await?)
+}
+
+/// An organisation's plan, its limits and what counts against them
+pub struct Usage {
+    pub plan: Plan,
+    pub limits: PlanLimits,
+    pub projects: u64,
+    pub members: u64,
+}
+
+#[tracing::instrument(name = "db.organisation_usage", skip(db), fields(otel.kind = "client"))]
+pub async fn usage<C: ConnectionTrait>(db: &C, organisation: OrganisationModel) -> Result<Usage, AppError> {
+    Ok(Usage {
+        limits: organisation_limits(&organisation),
+        projects: count_projects(db, organisation.id).await?,
+        members: count_members(db, organisation.id).await?,
+        plan: organisation.plan,
+    })
+}
+
+/// Locks the organisation row for the rest of the transaction, so quota checks on the
+/// same organisation queue up instead of counting concurrently. SQLite has no row
+/// locks, but only ever lets one transaction write.
+async fn lock_organisation<C: ConnectionTrait>(
+    db: &C,
+    organisation_id: Uuid,
+) -> Result<OrganisationModel, AppError> {
+    Organisation::find_by_id(organisation_id)
+        .filter(organisation::Column::DeletedAt.is_null())
+        .lock_exclusive()
+        .one(db)
+        .await?
+        .ok_or(AppError::Organisation(OrganisationError::OrganisationNotFound(organisation_id)))
+}
+
+/// Fails with `project_limit_exceeded` unless the organisation has room for another
+/// project. Call it in the transaction that adds the project.
+pub async fn reserve_project<C: ConnectionTrait>(db: &C, organisation_id: Uuid) -> Result<(), AppError> {
+    let organisation = lock_organisation(db, organisation_id).await?;
+    let Some(limit) = organisation_limits(&organisation).projects else {
+        return Ok(());
+    };
+    let current = count_projects(db, organisation_id).await?;
+    if current >= u64::from(limit) {
+        return Err(AppError::Project(ProjectError::ProjectLimitExceeded {
+            current: current as u32,
+            limit,
+        }));
+    }
+    Ok(())
+}
+
+/// Fails with `member_limit_exceeded` unless the organisation has room for another
+/// member. Call it in the transaction that adds the member.
+pub async fn reserve_member<C: ConnectionTrait>(db: &C, organisation_id: Uuid) -> Result<(), AppError> {
+    let organisation = lock_organisation(db, organisation_id).await?;
+    let Some(limit) = organisation_limits(&organisation).members else {
+        return Ok(());
+    };
+    let current = count_members(db, organisation_id).await?;
+    if current >= u64::from(limit) {
+        return Err(AppError::Organisation(OrganisationError::MemberLimitExceeded {
+            current: current as u32,
+            limit,
+        }));
+    }
+    Ok(())
+}
@@ -1,196 +1,334 @@
-use actix_web::{HttpResponse, Result, delete, get, post, put, web};
-use sea_orm::{DatabaseConnection, EntityTrait, PaginatorTrait, QueryOrder};
-use serde::{Deserialize, Serialize};
+use chrono::TimeDelta;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    Set, TransactionTrait,
+};
 use uuid::Uuid;
 
 use crate::errors::{AppError, ProjectError};
-use crate::models::entities::project::{Entity as Project, Model as ProjectModel};
+use crate::models::entities::AuditAction;
+use crate::models::entities::project::Column;
+use crate::models::entities::{Project, ProjectActiveModel, ProjectModel};
+use crate::services::audit::{AuditEntry, record};
+#[cfg(feature = "cloud")]
+use crate::services::billing::require_deployable;
+use crate::services::organisations::{
+    member_organisation_ids, require_admin, require_member, require_owner,
+};
+use crate::services::plans::reserve_project;
+use crate::utils::clock::Clock;
+use crate::log_info;
 use crate::utils::logger::Logger;
-use crate::utils::pagination::{PaginatedResponse, PaginationQuery};
+use crate::utils::listing::{FieldType, ListParams, Listable};
+use crate::utils::pagination::{Cursor, Keyset, PaginatedResponse};
 
-#[derive(Serialize, Deserialize)]
-pub struct CreateProjectRequest {
+pub struct CreateProjectData {
     pub name: String,
     pub description: Option<String>,
-    pub slug: String,
     pub organisation_id: Uuid,
     pub owner_id: Uuid,
     pub is_public: bool,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct ProjectResponse {
-    pub id: Uuid,
-    pub name: String,
-    pub description: Option<String>,
-    pub organisation_id: Uuid,
-    pub owner_id: Uuid,
-    pub is_archived: bool,
-    pub created_at: chrono::NaiveDateTime,
-    pub updated_at: chrono::NaiveDateTime,
+pub struct UpdateProjectData {
+    pub name: Option<String>,
+    pub description: Option<Option<String>>,
+    pub is_archived: Option<bool>,
 }
 
-impl From<ProjectModel> for ProjectResponse {
-    fn from(project: ProjectModel) -> Self {
-        Self {
-            id: project.id,
-            name: project.name,
-            description: project.description,
-            organisation_id: project.organisation_id,
-            owner_id: project.owner_id,
-            is_archived: project.is_archived,
+impl Keyset for Project {
+    fn keyset() -> (Column, Column) {
+        (Column::CreatedAt, Column::Id)
+    }
+
+    fn cursor(project: &ProjectModel) -> Cursor {
+        Cursor {
             created_at: project.created_at,
-            updated_at: project.updated_at,
+            id: project.id,
         }
     }
 }
 
-#[derive(Serialize)]
-pub struct ProjectsListResponse {
-    pub projects: Vec<ProjectResponse>,
-    pub total: u64,
-    pub page: u64,
-    pub per_page: u64,
-}
-
-#[get("/projects")]
-pub async fn list_projects(
-    db: web::Data<DatabaseConnection>,
-    query: web::Query<PaginationQuery>,
-) -> Result<HttpResponse, AppError> {
-    let page = query.page();
-    let per_page = query.per_page();
-
-    Logger::debug(&format!(
-        "Fetching projects: page={}, per_page={}",
-        page, per_page
-    ));
-
-    let start_time = std::time::Instant::now();
-    let paginator = Project::find()
-        .order_by_desc(crate::models::entities::project::Column::CreatedAt)
-        .paginate(&**db, per_page);
-
-    let total = paginator.num_items().await?;
-    let projects = paginator.fetch_page(page - 1).await?;
-    let duration = start_time.elapsed();
-
-    Logger::database_operation("SELECT", "projects", duration.as_millis() as u64);
-
-    let project_responses: Vec<ProjectResponse> =
-        projects.into_iter().map(ProjectResponse::from).collect();
+impl Listable for Project {
+    fn filter_field(name: &str) -> Option<(Column, FieldType)> {
+        match name {
+            "is_archived" => Some((Column::IsArchived, FieldType::Bool)),
+            "is_public" => Some((Column::IsPublic, FieldType::Bool)),
+            "organisation_id" => Some((Column::OrganisationId, FieldType::Uuid)),
+            "owner_id" => Some((Column::OwnerId, FieldType::Uuid)),
+            _ => None,
+        }
+    }
 
-    Logger::info(&format!(
-        "Listed {} projects (total: {})",
-        project_responses.len(),
-        total
-    ));
+    fn sort_field(name: &str) -> Option<Column> {
+        match name {
+            "created_at" => Some(Column::CreatedAt),
+            "updated_at" => Some(Column::UpdatedAt),
+            "name" => Some(Column::Name),
+            _ => None,
+        }
+    }
 
-    let response = PaginatedResponse::new(project_responses, total, page, per_page);
+    fn search_fields() -> Vec<Column> {
+        vec![Column::Name, Column::Description]
+    }
+}
 
-    Ok(HttpResponse::Ok().json(response))
+/// The requested page of projects in organisations the identity belongs to
+#[tracing::instrument(name = "db.list_projects", skip(db, list), fields(otel.kind = "client"))]
+pub async fn list_projects<C: ConnectionTrait>(
+    db: &C,
+    identity_id: Uuid,
+    list: ListParams<Project>,
+) -> Result<PaginatedResponse<ProjectModel>, AppError> {
+    let organisation_ids = member_organisation_ids(db, identity_id).await?;
+
+    let select = Project::find()
+        .filter(Column::OrganisationId.is_in(organisation_ids))
+        .filter(Column::DeletedAt.is_null());
+    Ok(list.fetch(db, select).await?)
 }
 
-#[get("/projects/{id}")]
-pub async fn get_project(
-    db: web::Data<DatabaseConnection>,
-    path: web::Path<Uuid>,
-) -> Result<HttpResponse, AppError> {
-    let project_id = path.into_inner();
+/// The project, provided it isn't deleted and the identity is a member of its
+/// organisation. Otherwise it is reported as not found.
+#[tracing::instrument(name = "db.get_project", skip(db), fields(otel.kind = "client"))]
+pub async fn get_project<C: ConnectionTrait>(
+    db: &C,
+    identity_id: Uuid,
+    project_id: Uuid,
+) -> Result<ProjectModel, AppError> {
+    let not_found = || AppError::Project(ProjectError::ProjectNotFound(project_id));
 
     let project = Project::find_by_id(project_id)
-        .one(&**db)
+        .filter(Column::DeletedAt.is_null())
+        .one(db)
         .await?
-        .ok_or_else(|| AppError::Project(ProjectError::ProjectNotFound(project_id)))?;
+        .ok_or_else(not_found)?;
+    require_member(db, project.organisation_id, identity_id)
+        .await
+        .map_err(|_| not_found())?;
 
-    Ok(HttpResponse::Ok().json(ProjectResponse::from(project)))
+    Ok(project)
 }
 
-#[post("/projects")]
+/// Creates the project, provided the organisation has room for it on its plan and, in
+/// the cloud, its subscription isn't suspended
+#[tracing::instrument(name = "db.create_project", skip_all, fields(otel.kind = "client"))]
 pub async fn create_project(
-    db: web::Data<DatabaseConnection>,
-    request: web::Json<CreateProjectRequest>,
-) -> Result<HttpResponse, AppError> {
-    use crate::models::entities::project::ActiveModel;
-    use sea_orm::{ActiveModelTrait, Set};
-
-    Logger::info(&format!(
-        "Creating new project: name='{}', slug='{}'",
-        request.name, request.slug
-    ));
-
-    let now = chrono::Utc::now().naive_utc();
-
-    let new_project = ActiveModel {
-        id: Set(Uuid::new_v4()),
-        name: Set(request.name.clone()),
-        description: Set(request.description.clone()),
-        organisation_id: Set(request.organisation_id),
-        owner_id: Set(request.owner_id),
-        is_archived: Set(false),
-        created_at: Set(now),
-        updated_at: Set(now),
-    };
-
-    let project = new_project.insert(&**db).await?;
-    Ok(HttpResponse::Created().json(ProjectResponse::from(project)))
+    db: &DatabaseConnection,
+    clock: &dyn Clock,
+    identity_id: Uuid,
+    data: CreateProjectData,
+) -> Result<ProjectModel, AppError> {
+    let now = clock.now();
+    let project = db
+        .transaction::<_, ProjectModel, AppError>(|transaction| {
+            Box::pin(async move {
+                require_member(transaction, data.organisation_id, identity_id).await?;
+                #[cfg(feature = "cloud")]
+                require_deployable(transaction, data.organisation_id).await?;
+                reserve_project(transaction, data.organisation_id).await?;
+
+                Logger::info(&format!(
+                    "Creating new project: name='{}', organisation={}",
+                    data.name, data.organisation_id
+                ));
+
+                let project = ProjectActiveModel {
+                    id: Set(Uuid::new_v4()),
+                    name: Set(data.name),
+                    description: Set(data.description),
+                    organisation_id: Set(data.organisation_id),
+                    owner_id: Set(data.owner_id),
+                    is_archived: Set(false),
+                    is_public: Set(data.is_public),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    deleted_at: Set(None),
+                    deleted_by: Set(None),
+                };
+                Ok(project.insert(transaction).await?)
+            })
+        })
+        .await?;
+
+    Ok(project)
 }
 
-#[put("/projects/{id}")]
-pub async fn update_project(
-    db: web::Data<DatabaseConnection>,
-    path: web::Path<Uuid>,
-    request: web::Json<UpdateProjectRequest>,
-) -> Result<HttpResponse, AppError> {
-    use crate::models::entities::project::ActiveModel;
-    use sea_orm::{ActiveModelTrait, Set};
-
-    let project_id = path.into_inner();
+/// Fails with `project_archived` while the project is archived. Anything that changes a
+/// project or what belongs to it checks this first; unarchiving is the only way out.
+pub fn require_writable(project: &ProjectModel) -> Result<(), AppError> {
+    if project.is_archived {
+        return Err(AppError::Project(ProjectError::ProjectArchived(project.id)));
+    }
+    Ok(())
+}
 
-    let existing_project = Project::find_by_id(project_id)
-        .one(&**db)
-        .await?
-        .ok_or_else(|| AppError::Project(ProjectError::ProjectNotFound(project_id)))?;
+/// Changes the project, for Admins and Owners of its organisation. Archiving makes
+/// the project read-only and isn't possible for public projects. Other fields of an
+/// archived project can only change along with unarchiving it, which in the cloud
+/// waits for a suspended subscription to be paid up.
+#[tracing::instrument(name = "db.update_project", skip(db, clock, data), fields(otel.kind = "client"))]
+pub async fn update_project<C: ConnectionTrait>(
+    db: &C,
+    clock: &dyn Clock,
+    identity_id: Uuid,
+    project_id: Uuid,
+    data: UpdateProjectData,
+) -> Result<ProjectModel, AppError> {
+    let existing_project = get_project(db, identity_id, project_id).await?;
+    require_admin(&require_member(db, existing_project.organisation_id, identity_id).await?)?;
+    if data.is_archived != Some(false) {
+        require_writable(&existing_project)?;
+    }
+    if data.is_archived == Some(true) && existing_project.is_public {
+        return Err(AppError::Project(ProjectError::CannotArchivePublicProject));
+    }
+    #[cfg(feature = "cloud")]
+    if data.is_archived == Some(false) && existing_project.is_archived {
+        require_deployable(db, existing_project.organisation_id).await?;
+    }
 
-    let mut project: ActiveModel = existing_project.into();
-    if let Some(name) = &request.name {
-        project.name = Set(name.clone());
+    let mut project: ProjectActiveModel = existing_project.into();
+    if let Some(name) = data.name {
+        project.name = Set(name);
     }
-    if let Some(description) = &request.description {
-        project.description = Set(description.clone());
+    if let Some(description) = data.description {
+        project.description = Set(description);
     }
-    if let Some(is_archived) = request.is_archived {
+    if let Some(is_archived) = data.is_archived {
         project.is_archived = Set(is_archived);
     }
+    project.updated_at = Set(clock.now());
 
-    project.updated_at = Set(chrono::Utc::now().naive_utc());
-
-    let updated_project = project.update(&**db).await?;
-
-    Ok(HttpResponse::Ok().json(ProjectResponse::from(updated_project)))
+    Ok(project.update(db).await?)
 }
 
-#[delete("/projects/{id}")]
-pub async fn delete_project(
-    db: web::Data<DatabaseConnection>,
-    path: web::Path<Uuid>,
-) -> Result<HttpResponse, AppError> {
-    use sea_orm::EntityTrait;
-
-    let project_id = path.into_inner();
-
-    let result = Project::delete_by_id(project_id).exec(&**db).await?;
-
-    if result.rows_affected == 0 {
-        return Err(AppError::Project(ProjectError::ProjectNotFound(project_id)));
-    }
+/// Moves the project to `organisation_id`, for callers who are Admin or Owner in both
+/// organisations and as long as the destination's plan has room for it. Archived
+/// projects stay where they are. Both organisations get an audit log entry, written in
+/// the same transaction. Moving a project to its own organisation changes nothing.
+#[tracing::instrument(name = "db.transfer_project", skip(db, clock), fields(otel.kind = "client"))]
+pub async fn transfer_project(
+    db: &DatabaseConnection,
+    clock: &dyn Clock,
+    identity_id: Uuid,
+    project_id: Uuid,
+    organisation_id: Uuid,
+) -> Result<ProjectModel, AppError> {
+    let now = clock.now();
+    db.transaction::<_, ProjectModel, AppError>(|transaction| {
+        Box::pin(async move {
+            let project = get_project(transaction, identity_id, project_id).await?;
+            if project.organisation_id == organisation_id {
+                return Ok(project);
+            }
+            require_writable(&project)?;
+            require_admin(&require_member(transaction, project.organisation_id, identity_id).await?)?;
+            require_admin(&require_member(transaction, organisation_id, identity_id).await?)?;
+            reserve_project(transaction, organisation_id).await?;
+
+            let source = project.organisation_id;
+            let mut project: ProjectActiveModel = project.into();
+            project.organisation_id = Set(organisation_id);
+            project.updated_at = Set(now);
+            let project = project.update(transaction).await?;
+
+            let details = serde_json::json!({ "from": source, "to": organisation_id });
+            for (organisation_id, action) in [
+                (source, AuditAction::ProjectTransferredOut),
+                (organisation_id, AuditAction::ProjectTransferredIn),
+            ] {
+                let entry = AuditEntry {
+                    organisation_id,
+                    actor_id: identity_id,
+                    action,
+                    project_id: Some(project_id),
+                    details: details.clone(),
+                };
+                record(transaction, now, entry).await?;
+            }
+
+            log_info!(
+                "Transferred project {} from organisation {} to {} by {}",
+                project_id,
+                source,
+                organisation_id,
+                identity_id
+            );
+            Ok(project)
+        })
+    })
+    .await
+    .map_err(AppError::from)
+}
 
-    Ok(HttpResponse::NoContent().finish())
+/// Marks the project as deleted, for Admins and Owners of its organisation; it can be
+/// restored until the purge job removes it. Archived projects are read-only, so they
+/// have to be unarchived first.
+#[tracing::instrument(name = "db.delete_project", skip(db, clock), fields(otel.kind = "client"))]
+pub async fn delete_project<C: ConnectionTrait>(
+    db: &C,
+    clock: &dyn Clock,
+    identity_id: Uuid,
+    project_id: Uuid,
+) -> Result<(), AppError> {
+    let project = get_project(db, identity_id, project_id).await?;
+    require_admin(&require_member(db, project.organisation_id, identity_id).await?)?;
+    require_writable(&project)?;
+
+    let now = clock.now();
+    let mut project: ProjectActiveModel = project.into();
+    project.deleted_at = Set(Some(now));
+    project.deleted_by = Set(Some(identity_id));
+    project.updated_at = Set(now);
+    project.update(db).await?;
+    Ok(())
 }
 
-#[derive(Deserialize)]
-pub struct UpdateProjectRequest {
-    pub name: Option<String>,
-    pub description: Option<Option<String>>,
-    pub is_archived: Option<bool>,
+/// Undoes `delete_project` for owners of its organisation while the retention period
+/// lasts and the plan has room for it; afterwards the project is reported as not
+/// found. In the cloud a suspended subscription has to be paid up first. Restoring
+/// one that isn't deleted is a no-op.
+#[tracing::instrument(name = "db.restore_project", skip(db, clock), fields(otel.kind = "client"))]
+pub async fn restore_project(
+    db: &DatabaseConnection,
+    clock: &dyn Clock,
+    retention_days: u32,
+    identity_id: Uuid,
+    project_id: Uuid,
+) -> Result<ProjectModel, AppError> {
+    let not_found = move || AppError::Project(ProjectError::ProjectNotFound(project_id));
+    let now = clock.now();
+
+    db.transaction::<_, ProjectModel, AppError>(|transaction| {
+        Box::pin(async move {
+            let project = Project::find_by_id(project_id)
+                .one(transaction)
+                .await?
+                .ok_or_else(not_found)?;
+            let member = require_member(transaction, project.organisation_id, identity_id)
+                .await
+                .map_err(|_| not_found())?;
+            require_owner(&member)?;
+
+            let Some(deleted_at) = project.deleted_at else {
+                return Ok(project);
+            };
+            if deleted_at < now - TimeDelta::days(retention_days.into()) {
+                return Err(not_found());
+            }
+            #[cfg(feature = "cloud")]
+            require_deployable(transaction, project.organisation_id).await?;
+            reserve_project(transaction, project.organisation_id).await?;
+
+            let mut project: ProjectActiveModel = project.into();
+            project.deleted_at = Set(None);
+            project.deleted_by = Set(None);
+            project.updated_at = Set(now);
+            Ok(project.update(transaction).await?)
+        })
+    })
+    .await
+    .map_err(AppError::from)
 }
@@ -0,0 +1,184 @@
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use lru::LruCache;
+use redis::Script;
+
+use crate::errors::AppError;
+use crate::log_warn;
+use crate::services::valkey::Valkey;
+
+/// Buckets kept in memory before the least recently used one is dropped to make room,
+/// which is full again unless ten thousand clients came by within the minute
+const MAX_MEMORY_BUCKETS: NonZeroUsize = NonZeroUsize::new(10_000).unwrap();
+
+/// Takes a token from the bucket in `KEYS[1]` if one is left, after refilling it for
+/// the time passed since the last request. Empty buckets are deleted once they would
+/// be full again.
+const TAKE_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local per_ms = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'at')
+local tokens = tonumber(bucket[1]) or capacity
+local at = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - at) * per_ms)
+local allowed = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'at', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity / per_ms))
+return {allowed, tostring(tokens)}
+"#;
+
+/// How many requests a bucket allows, refilled evenly over a minute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    pub per_minute: u32,
+}
+
+impl Budget {
+    fn capacity(self) -> f64 {
+        f64::from(self.per_minute)
+    }
+
+    fn tokens_per_ms(self) -> f64 {
+        self.capacity() / 60_000.0
+    }
+
+    /// Seconds until `tokens` grows to `target`, rounded up
+    fn secs_until(self, tokens: f64, target: f64) -> u64 {
+        ((target - tokens).max(0.0) / self.tokens_per_ms() / 1000.0).ceil() as u64
+    }
+
+    /// The outcome for a bucket left with `tokens` after the request
+    fn decision(self, allowed: bool, tokens: f64) -> Decision {
+        Decision {
+            allowed,
+            limit: self.per_minute,
+            remaining: tokens.floor() as u32,
+            reset_secs: self.secs_until(tokens, self.capacity()),
+            retry_after_secs: if allowed { 0 } else { self.secs_until(tokens, 1.0).max(1) },
+        }
+    }
+}
+
+/// Whether a request may go ahead, with what is left of its budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the bucket is full again
+    pub reset_secs: u64,
+    /// Seconds until the next request is allowed, 0 when this one was
+    pub retry_after_secs: u64,
+}
+
+/// Where token buckets are kept, behind a trait so instances can share them
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Takes a token from the bucket under `key`, if one is left
+    async fn take(&self, key: &str, budget: Budget, now: DateTime<Utc>) -> Result<Decision, AppError>;
+}
+
+struct Bucket {
+    tokens: f64,
+    at_ms: i64,
+}
+
+/// Buckets of this instance only, so each instance hands out the full budget
+pub struct MemoryStore {
+    buckets: Mutex<LruCache<String, Bucket>>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self {
+            buckets: Mutex::new(LruCache::new(MAX_MEMORY_BUCKETS)),
+        }
+    }
+}
+
+impl MemoryStore {
+    fn take_now(&self, key: &str, budget: Budget, now: DateTime<Utc>) -> Decision {
+        let now_ms = now.timestamp_millis();
+        let refilled = |bucket: &Bucket| {
+            let elapsed = (now_ms - bucket.at_ms).max(0) as f64;
+            (bucket.tokens + elapsed * budget.tokens_per_ms()).min(budget.capacity())
+        };
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let bucket = buckets.get_or_insert_mut(key.to_string(), || Bucket {
+            tokens: budget.capacity(),
+            at_ms: now_ms,
+        });
+        let mut tokens = refilled(bucket);
+        let allowed = tokens >= 1.0;
+        if allowed {
+            tokens -= 1.0;
+        }
+        *bucket = Bucket { tokens, at_ms: now_ms };
+        budget.decision(allowed, tokens)
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn take(&self, key: &str, budget: Budget, now: DateTime<Utc>) -> Result<Decision, AppError> {
+        Ok(self.take_now(key, budget, now))
+    }
+}
+
+/// Buckets shared by every instance through Valkey. While Valkey can't be reached
+/// each instance falls back to its own buckets rather than turning requests away.
+pub struct ValkeyStore {
+    valkey: Arc<Valkey>,
+    /// `TAKE_SCRIPT`, sent by its hash once Valkey has loaded it
+    take: Script,
+    fallback: MemoryStore,
+}
+
+impl ValkeyStore {
+    pub fn new(valkey: Arc<Valkey>) -> Self {
+        Self {
+            valkey,
+            take: Script::new(TAKE_SCRIPT),
+            fallback: MemoryStore::default(),
+        }
+    }
+
+    async fn take_shared(&self, key: &str, budget: Budget, now: DateTime<Utc>) -> Result<Decision, AppError> {
+        let key = format!("ratelimit:{}", key);
+        let (allowed, tokens): (i64, String) = self
+            .valkey
+            .invoke(
+                self.take
+                    .key(key)
+                    .arg(budget.per_minute)
+                    .arg(budget.tokens_per_ms())
+                    .arg(now.timestamp_millis()),
+            )
+            .await?;
+        let allowed = allowed == 1;
+        let tokens = tokens.parse::<f64>().unwrap_or(0.0);
+        Ok(budget.decision(allowed, tokens))
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for ValkeyStore {
+    async fn take(&self, key: &str, budget: Budget, now: DateTime<Utc>) -> Result<Decision, AppError> {
+        match self.take_shared(key, budget, now).await {
+            Ok(decision) => Ok(decision),
+            Err(err) => {
+                log_warn!("Rate limiting in memory, Valkey failed: {}", err);
+                Ok(self.fallback.take_now(key, budget, now))
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DatabaseBackend, EntityTrait, FromQueryResult,
+    QueryFilter, Statement,
+};
+use uuid::Uuid;
+
+use crate::errors::AppError;
+use crate::models::entities::{Organisation, Project, organisation, project};
+use crate::services::organisations::member_organisation_ids;
+use crate::utils::listing::contains;
+
+/// Matches from every table `/search` covers; `kind` is the table's result type
+#[derive(Debug, FromQueryResult)]
+pub struct SearchHit {
+    pub kind: String,
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub organisation_id: Uuid,
+    pub rank: f32,
+}
+
+/// Lowercase words of `q`, which is what both backends match on
+pub fn search_terms(q: &str) -> Vec<String> {
+    q.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The best `limit` organisations and projects matching every term, restricted to
+/// organisations the identity is a member of and leaving out deleted ones. The last
+/// term may be incomplete, so terms also match as prefixes.
+#[tracing::instrument(name = "db.search", skip(db), fields(otel.kind = "client"))]
+pub async fn search<C: ConnectionTrait>(
+    db: &C,
+    identity_id: Uuid,
+    terms: &[String],
+    limit: u64,
+) -> Result<Vec<SearchHit>, AppError> {
+    match db.get_database_backend() {
+        DatabaseBackend::Postgres => search_postgres(db, identity_id, terms, limit).await,
+        _ => search_like(db, identity_id, terms, limit).await,
+    }
+}
+
+/// Ranked with `ts_rank` over the generated `search_vector` columns
+async fn search_postgres<C: ConnectionTrait>(
+    db: &C,
+    identity_id: Uuid,
+    terms: &[String],
+    limit: u64,
+) -> Result<Vec<SearchHit>, AppError> {
+    // Terms only hold letters and digits, so they can't break the tsquery syntax
+    let query = terms
+        .iter()
+        .map(|term| format!("{}:*", term))
+        .collect::<Vec<_>>()
+        .join(" & ");
+
+    let statement = Statement::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        r#"
+        WITH query AS (SELECT to_tsquery('simple', $1) AS q),
+        member_of AS (
+            SELECT m.organisation_id FROM organisation_member m
+            JOIN organisation o ON o.id = m.organisation_id
+            WHERE m.identity_id = $2 AND m.is_active AND o.deleted_at IS NULL
+        )
+        SELECT 'organisation' AS kind, o.id, o.name, o.description, o.id AS organisation_id,
+               ts_rank(o.search_vector, query.q) AS rank
+        FROM organisation o, query
+        WHERE o.search_vector @@ query.q AND o.id IN (SELECT organisation_id FROM member_of)
+        UNION ALL
+        SELECT 'project', p.id, p.name, p.description, p.organisation_id,
+               ts_rank(p.search_vector, query.q)
+        FROM project p, query
+        WHERE p.search_vector @@ query.q AND p.deleted_at IS NULL
+          AND p.organisation_id IN (SELECT organisation_id FROM member_of)
+        ORDER BY rank DESC, name
+        LIMIT $3
+        "#,
+        [query.into(), identity_id.into(), (limit as i64).into()],
+    );
+
+    Ok(SearchHit::find_by_statement(statement).all(db).await?)
+}
+
+/// For backends without full-text search: every term has to appear in the name or
+/// description, and matches on the name rank first
+async fn search_like<C: ConnectionTrait>(
+    db: &C,
+    identity_id: Uuid,
+    terms: &[String],
+    limit: u64,
+) -> Result<Vec<SearchHit>, AppError> {
+    let organisation_ids = member_organisation_ids(db, identity_id).await?;
+    let rank = |name: &str| {
+        let name = name.to_lowercase();
+        if terms.iter().all(|term| name.contains(term.as_str())) { 1.0 } else { 0.5 }
+    };
+
+    let organisations = Organisation::find()
+        .filter(organisation::Column::Id.is_in(organisation_ids.clone()))
+        .filter(matches_all(terms, organisation::Column::Name, organisation::Column::Description))
+        .all(db)
+        .await?;
+    let projects = Project::find()
+        .filter(project::Column::OrganisationId.is_in(organisation_ids))
+        .filter(project::Column::DeletedAt.is_null())
+        .filter(matches_all(terms, project::Column::Name, project::Column::Description))
+        .all(db)
+        .await?;
+
+    let mut hits: Vec<SearchHit> = organisations
+        .into_iter()
+        .map(|o| SearchHit {
+            kind: "organisation".to_string(),
+            rank: rank(&o.name),
+            id: o.id,
+            name: o.name,
+            description: o.description,
+            organisation_id: o.id,
+        })
+        .chain(projects.into_iter().map(|p| SearchHit {
+            kind: "project".to_string(),
+            rank: rank(&p.name),
+            id: p.id,
+            name: p.name,
+            description: p.description,
+            organisation_id: p.organisation_id,
+        }))
+        .collect();
+    hits.sort_by(|a, b| b.rank.total_cmp(&a.rank).then_with(|| a.name.cmp(&b.name)));
+    hits.truncate(limit as usize);
+    Ok(hits)
+}
+
+fn matches_all<C: ColumnTrait>(terms: &[String], name: C, description: C) -> Condition {
+    terms.iter().fold(Condition::all(), |all, term| {
+        all.add(contains(vec![name, description], term))
+    })
+}
@@ -0,0 +1,100 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use redis::{Cmd, ErrorKind, FromRedisValue, RedisError, RedisResult, ScriptInvocation};
+
+use crate::config::ValkeyConfig;
+use crate::errors::{AppError, ExternalError};
+
+/// How long commands fail straight away after one couldn't reach Valkey, so callers
+/// fall back at once instead of each waiting out the timeout
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(5);
+
+/// Valkey, or Redis, over one multiplexed connection that is opened on first use and
+/// reopened after it breaks. Commands that time out or can't reach the server trip a
+/// breaker, so the ones following fail straight away until `BREAKER_COOLDOWN` is over.
+pub struct Valkey {
+    connection: ConnectionManager,
+    timeout: Duration,
+    /// Until when commands fail without trying, set by the last failed one
+    broken_until: std::sync::Mutex<Option<Instant>>,
+}
+
+impl Valkey {
+    pub fn new(config: &ValkeyConfig) -> Result<Self, AppError> {
+        let client = redis::Client::open(config.url.as_str())
+            .map_err(|e| valkey_error(format!("Invalid URL: {}", e)))?;
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let manager = ConnectionManagerConfig::new()
+            .set_connection_timeout(Some(timeout))
+            .set_response_timeout(Some(timeout))
+            .set_number_of_retries(1);
+        let connection = ConnectionManager::new_lazy_with_config(client, manager)
+            .map_err(|e| valkey_error(e.to_string()))?;
+
+        Ok(Self {
+            connection,
+            timeout,
+            broken_until: std::sync::Mutex::new(None),
+        })
+    }
+
+    /// Runs one command, e.g. `redis::cmd("GET").arg(key)`. Error replies come back as
+    /// errors.
+    #[tracing::instrument(name = "valkey.query", skip_all, fields(otel.kind = "client"))]
+    pub async fn query<T: FromRedisValue>(&self, command: &Cmd) -> Result<T, AppError> {
+        let mut connection = self.connection.clone();
+        self.run(command.query_async(&mut connection)).await
+    }
+
+    /// Runs a script by its hash, loading it first when Valkey doesn't know it yet
+    #[tracing::instrument(name = "valkey.invoke", skip_all, fields(otel.kind = "client"))]
+    pub async fn invoke<T: FromRedisValue>(&self, script: &ScriptInvocation<'_>) -> Result<T, AppError> {
+        let mut connection = self.connection.clone();
+        self.run(script.invoke_async(&mut connection)).await
+    }
+
+    async fn run<T>(&self, command: impl Future<Output = RedisResult<T>>) -> Result<T, AppError> {
+        self.check_breaker()?;
+        match tokio::time::timeout(self.timeout, command).await {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(err)) => {
+                // An error reply means the server is up and answering
+                if !is_reply(&err) {
+                    self.trip_breaker();
+                }
+                Err(valkey_error(err.to_string()))
+            }
+            Err(_) => {
+                self.trip_breaker();
+                Err(AppError::External(ExternalError::NetworkTimeout))
+            }
+        }
+    }
+
+    fn breaker(&self) -> std::sync::MutexGuard<'_, Option<Instant>> {
+        self.broken_until.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn check_breaker(&self) -> Result<(), AppError> {
+        match *self.breaker() {
+            Some(until) if Instant::now() < until => Err(AppError::External(
+                ExternalError::ServiceUnavailable("Valkey: failing fast after an error".to_string()),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn trip_breaker(&self) {
+        *self.breaker() = Some(Instant::now() + BREAKER_COOLDOWN);
+    }
+}
+
+fn is_reply(err: &RedisError) -> bool {
+    matches!(err.kind(), ErrorKind::Server(_) | ErrorKind::UnexpectedReturnType)
+}
+
+fn valkey_error(message: impl Into<String>) -> AppError {
+    AppError::External(ExternalError::ValkeyError(message.into()))
+}
@@ -1,40 +1,111 @@
-use crate::config::{Config, load_config};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::{Config, DatabaseConfig};
 use crate::errors::{AppError, DatabaseError};
-use sea_orm::{ConnectOptions, Database, DatabaseConnection};
-use std::process;
-use std::sync::OnceLock;
+#[cfg(feature = "cloud")]
+use crate::services::billing::{LogPaymentProvider, PaymentProvider};
+use crate::services::cache::{Cache, MemoryCache, ValkeyCache};
+use crate::services::kratos::{KratosClient, OryKratos};
+use crate::services::mailer::{LogMailer, Mailer, SmtpMailer};
+use crate::services::rate_limit::{MemoryStore, RateLimitStore, ValkeyStore};
+use crate::services::valkey::Valkey;
+use crate::utils::clock::{Clock, SystemClock};
+use crate::utils::logger::Logger;
+use crate::utils::metrics::{metrics, statement_target};
+use crate::utils::telemetry::record_db_statement;
+use sea_orm::{ConnectOptions, ConnectionTrait, Database, DatabaseBackend, DatabaseConnection};
 
+/// Everything handlers need, registered as `web::Data<State>`. External services sit
+/// behind traits so tests can replace them with fakes.
 #[derive(Clone)]
 pub struct State {
     pub db: DatabaseConnection,
     pub config: Config,
+    pub kratos: Arc<dyn KratosClient>,
+    pub mailer: Arc<dyn Mailer>,
+    pub clock: Arc<dyn Clock>,
+    pub rate_limits: Arc<dyn RateLimitStore>,
+    pub cache: Arc<dyn Cache>,
+    #[cfg(feature = "cloud")]
+    pub payments: Arc<dyn PaymentProvider>,
+}
+
+impl State {
+    /// State with the production implementations of every external service
+    pub fn new(db: DatabaseConnection, config: Config) -> Result<Self, AppError> {
+        let mailer: Arc<dyn Mailer> = match &config.smtp {
+            Some(smtp) => Arc::new(SmtpMailer::new(smtp)?),
+            None => Arc::new(LogMailer),
+        };
+        let valkey = config.valkey.as_ref().map(Valkey::new).transpose()?.map(Arc::new);
+        let (rate_limits, cache): (Arc<dyn RateLimitStore>, Arc<dyn Cache>) = match valkey {
+            Some(valkey) => (
+                Arc::new(ValkeyStore::new(valkey.clone())),
+                Arc::new(ValkeyCache::new(valkey)),
+            ),
+            None => (Arc::new(MemoryStore::default()), Arc::new(MemoryCache::default())),
+        };
+
+        Ok(Self {
+            db,
+            kratos: Arc::new(OryKratos::new(&config.auth.kratos_admin_url)),
+            mailer,
+            clock: Arc::new(SystemClock),
+            rate_limits,
+            cache,
+            #[cfg(feature = "cloud")]
+            payments: Arc::new(LogPaymentProvider),
+            config,
+        })
+    }
 }
 
-static STATE: OnceLock<State> = OnceLock::new();
+pub async fn create_app_state(config: Config) -> Result<State, AppError> {
+    let db = connect(&config.database).await?;
+    State::new(db, config)
+}
 
-pub async fn create_app_state() -> Result<State, AppError> {
-    let config = load_config()?;
-    let mut options = ConnectOptions::new(&config.database_url);
-    options.sqlx_logging(true);
+/// Opens the connection pool and hooks statement timings into logs, metrics and traces
+pub async fn connect(database: &DatabaseConfig) -> Result<DatabaseConnection, AppError> {
+    let mut options = ConnectOptions::new(connection_url(&database.url, database.statement_timeout_ms));
+    options
+        .max_connections(database.max_connections)
+        .min_connections(database.min_connections)
+        .connect_timeout(Duration::from_secs(database.connect_timeout_secs))
+        .acquire_timeout(Duration::from_secs(database.acquire_timeout_secs))
+        .idle_timeout(Duration::from_secs(database.idle_timeout_secs))
+        .sqlx_logging(database.log_statements);
 
-    let db = Database::connect(options)
+    let mut db = Database::connect(options)
         .await
         .map_err(|err| AppError::Database(DatabaseError::ConnectionFailed(err.to_string())))?;
+    let slow_threshold = Duration::from_millis(database.slow_statement_threshold_ms);
+    let system = match db.get_database_backend() {
+        DatabaseBackend::Sqlite => "sqlite",
+        _ => "postgresql",
+    };
+    db.set_metric_callback(move |info| {
+        let (operation, table) = statement_target(&info.statement.sql);
+        record_db_statement(system, operation, table, &info.statement.sql, info.elapsed, info.failed);
+        let duration_ms = info.elapsed.as_millis() as u64;
+        Logger::database_operation(operation, table, duration_ms);
+        if !slow_threshold.is_zero() && info.elapsed >= slow_threshold {
+            Logger::slow_query(operation, table, duration_ms, &info.statement.sql);
+        }
+        metrics().observe_db_query(operation, table, info.failed, info.elapsed);
+    });
 
-    let state = State { db, config };
-    STATE.set(state)
-        .map_err(|_| AppError::Internal(format!("Couldnt set STATE")))?;
-    Ok(get_app_state())
+    Ok(db)
 }
 
-pub fn get_app_state() -> State {
-    match STATE.get() {
-        Some(v) => v.clone(),
-        None => {
-            eprintln!("ERROR: get_app_state() called before initialization");
-            let backtrace = std::backtrace::Backtrace::capture();
-            eprintln!("{backtrace}");
-            process::exit(0)
-        }
+/// Adds the Postgres `statement_timeout` as a startup parameter, so it applies to
+/// every connection the pool opens. SQLite has no equivalent and is left as is.
+fn connection_url(url: &str, statement_timeout_ms: u64) -> String {
+    let postgres = url.starts_with("postgres://") || url.starts_with("postgresql://");
+    if !postgres || statement_timeout_ms == 0 {
+        return url.to_string();
     }
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}options[statement_timeout]={}", url, separator, statement_timeout_ms)
 }
@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+
+/// Source of the current time, so tests can pin timestamps written by services
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
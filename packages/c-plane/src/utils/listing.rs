@@ -0,0 +1,239 @@
+use std::future::{Ready, ready};
+
+use actix_web::{FromRequest, HttpRequest, dev::Payload};
+use sea_orm::sea_query::{Expr, Func, LikeExpr};
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DbErr, FromQueryResult, Order, QueryFilter, Select,
+    Value,
+};
+use url::form_urlencoded;
+use utoipa::IntoParams;
+use utoipa::openapi::path::{Parameter, ParameterBuilder, ParameterIn, ParameterStyle};
+use utoipa::openapi::{Object, ObjectBuilder, Type};
+use uuid::Uuid;
+
+use crate::errors::{AppError, ValidationError};
+use crate::utils::pagination::{Keyset, PaginatedResponse, Pagination, PaginationQuery, fetch_page};
+use crate::utils::validation::Validator;
+
+/// How a filter value is parsed before it is compared with its column
+#[derive(Debug, Clone, Copy)]
+pub enum FieldType {
+    Bool,
+    Uuid,
+    Text,
+    /// An enum, given as it appears in responses and paired with how it is stored
+    Enum(&'static [(&'static str, &'static str)]),
+}
+
+/// What clients may filter, sort and search a listing by. Anything else is rejected,
+/// which keeps hidden and unindexed columns out of reach.
+pub trait Listable: Keyset {
+    /// The column behind `filter[name]`
+    fn filter_field(name: &str) -> Option<(Self::Column, FieldType)>;
+
+    /// The column behind `name` in `sort`, which should be indexed
+    fn sort_field(name: &str) -> Option<Self::Column>;
+
+    /// Columns `q` is matched against, none to reject `q`
+    fn search_fields() -> Vec<Self::Column>;
+}
+
+/// The query grammar shared by list endpoints, next to the pagination parameters:
+///
+/// - `filter[field]=value` keeps items whose field equals the value, a comma
+///   separated list matches any of them
+/// - `sort=-updated_at,name` orders by the given fields, descending with a `-`
+/// - `q=text` keeps items containing the text, ignoring case
+///
+/// Sorting other than the default newest first only works with page numbers, since
+/// cursors point into the default order.
+#[derive(Debug, Default)]
+pub struct ListQuery {
+    pub filters: Vec<(String, String)>,
+    pub sort: Vec<String>,
+    pub q: Option<String>,
+}
+
+/// A `ListQuery` checked against the fields of `E`
+pub struct ListParams<E: Listable> {
+    pub condition: Condition,
+    /// Names of the fields filtered by
+    pub filtered: Vec<String>,
+    pub sort: Vec<(E::Column, Order)>,
+    pub pagination: Pagination,
+}
+
+impl ListQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut list = Self::default();
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            if key == "sort" {
+                list.sort.extend(
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(str::to_string),
+                );
+            } else if key == "q" {
+                list.q = Some(value.trim().to_string()).filter(|q| !q.is_empty());
+            } else if let Some(name) = key.strip_prefix("filter") {
+                // A malformed key keeps its brackets and fails the whitelist below
+                let name = name
+                    .strip_prefix('[')
+                    .and_then(|name| name.strip_suffix(']'))
+                    .unwrap_or(name);
+                list.filters.push((name.to_string(), value.into_owned()));
+            }
+        }
+        list
+    }
+
+    pub fn resolve<E: Listable>(&self, page: &PaginationQuery) -> Result<ListParams<E>, ValidationError> {
+        let pagination = page.pagination()?;
+        let mut v = Validator::default();
+        let mut condition = Condition::all();
+        let mut filtered = Vec::new();
+
+        for (name, value) in &self.filters {
+            let field = format!("filter[{}]", name);
+            let Some((column, field_type)) = E::filter_field(name) else {
+                v.fail(field, "unknown_field", "is not a field this listing can be filtered by");
+                continue;
+            };
+            let values: Option<Vec<Value>> = value
+                .split(',')
+                .map(|value| parse_value(field_type, value.trim()))
+                .collect();
+            match values {
+                Some(values) => {
+                    condition = condition.add(column.is_in(values));
+                    filtered.push(name.clone());
+                }
+                None => v.fail(field, "invalid_value", format!("'{}' is not a valid value", value)),
+            }
+        }
+
+        let mut sort = Vec::new();
+        for item in &self.sort {
+            let (name, order) = match item.strip_prefix('-') {
+                Some(name) => (name, Order::Desc),
+                None => (item.as_str(), Order::Asc),
+            };
+            match E::sort_field(name) {
+                Some(column) => sort.push((column, order)),
+                None => v.fail("sort", "unknown_field", format!("can't sort by '{}'", name)),
+            }
+        }
+        v.check(
+            "sort",
+            sort.is_empty() || matches!(pagination, Pagination::Page { .. }),
+            "exclusive",
+            "can't be combined with a cursor",
+        );
+
+        if let Some(q) = &self.q {
+            let fields = E::search_fields();
+            v.check("q", !fields.is_empty(), "unknown_field", "this listing can't be searched");
+            v.check("q", q.chars().count() <= 200, "length", "must be at most 200 characters");
+            condition = condition.add(contains(fields, q));
+        }
+
+        v.finish()?;
+        Ok(ListParams {
+            condition,
+            filtered,
+            sort,
+            pagination,
+        })
+    }
+}
+
+impl<E: Listable> ListParams<E> {
+    /// Whether `filter[name]` was given
+    pub fn filters_by(&self, name: &str) -> bool {
+        self.filtered.iter().any(|field| field == name)
+    }
+
+    /// The requested page of `select`, narrowed down by the filters and search
+    pub async fn fetch<C>(self, db: &C, select: Select<E>) -> Result<PaginatedResponse<E::Model>, DbErr>
+    where
+        E::Model: FromQueryResult + Send + Sync,
+        C: ConnectionTrait,
+    {
+        fetch_page(db, select.filter(self.condition), self.pagination, self.sort).await
+    }
+}
+
+fn parse_value(field_type: FieldType, value: &str) -> Option<Value> {
+    match field_type {
+        FieldType::Bool => value.parse::<bool>().ok().map(Value::from),
+        FieldType::Uuid => value.parse::<Uuid>().ok().map(Value::from),
+        FieldType::Text => Some(Value::from(value.to_string())),
+        FieldType::Enum(values) => values
+            .iter()
+            .find(|(name, _)| *name == value)
+            .map(|(_, stored)| Value::from(*stored)),
+    }
+}
+
+/// Any of `columns` contains `text`, ignoring case and treating `%` and `_` literally
+pub fn contains<C: ColumnTrait>(columns: Vec<C>, text: &str) -> Condition {
+    let escaped = text
+        .to_lowercase()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    let pattern = format!("%{}%", escaped);
+
+    columns.into_iter().fold(Condition::any(), |any, column| {
+        any.add(Expr::expr(Func::lower(Expr::col(column))).like(LikeExpr::new(&pattern).escape('\\')))
+    })
+}
+
+impl FromRequest for ListQuery {
+    type Error = AppError;
+    type Future = Ready<Result<Self, AppError>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(Self::parse(req.query_string())))
+    }
+}
+
+impl IntoParams for ListQuery {
+    fn into_params(parameter_in_provider: impl Fn() -> Option<ParameterIn>) -> Vec<Parameter> {
+        let parameter_in = parameter_in_provider().unwrap_or(ParameterIn::Query);
+        let string = || Object::with_type(Type::String);
+        vec![
+            ParameterBuilder::new()
+                .name("filter")
+                .parameter_in(parameter_in.clone())
+                .style(Some(ParameterStyle::DeepObject))
+                .explode(Some(true))
+                .description(Some(
+                    "`filter[field]=value`, a comma separated list matches any of the values",
+                ))
+                .schema(Some(
+                    ObjectBuilder::new()
+                        .schema_type(Type::Object)
+                        .additional_properties(Some(ObjectBuilder::new().schema_type(Type::String))),
+                ))
+                .build(),
+            ParameterBuilder::new()
+                .name("sort")
+                .parameter_in(parameter_in.clone())
+                .description(Some(
+                    "Comma separated fields, `-` for descending. Only with page numbers.",
+                ))
+                .schema(Some(string()))
+                .build(),
+            ParameterBuilder::new()
+                .name("q")
+                .parameter_in(parameter_in)
+                .description(Some("Text the items contain, ignoring case"))
+                .schema(Some(string()))
+                .build(),
+        ]
+    }
+}
@@ -1,4 +1,13 @@
 use std::fmt;
+use std::str::FromStr;
+
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::utils::metrics::metrics;
+use crate::utils::telemetry::{self, OtelLayer};
 
 /// Custom logger utility for consistent logging across the application
 pub struct Logger;
@@ -24,6 +33,47 @@ impl fmt::Display for LogLevel {
     }
 }
 
+/// Output format of the global subscriber
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Json,
+    Pretty,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => Ok(LogFormat::Json),
+            "pretty" => Ok(LogFormat::Pretty),
+            _ => Err("expected 'json' or 'pretty'".to_string()),
+        }
+    }
+}
+
+/// Installs the global `tracing` subscriber. Filtering follows `RUST_LOG`
+/// (e.g. `info,sqlx=warn`) and defaults to `info`. Spans are also exported through
+/// `otel_layer` when OpenTelemetry is enabled.
+pub fn init(format: LogFormat, otel_layer: Option<OtelLayer>) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let fmt_layer = match format {
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+        LogFormat::Pretty => tracing_subscriber::fmt::layer().pretty().boxed(),
+    };
+
+    tracing_subscriber::registry()
+        .with(otel_layer)
+        .with(filter)
+        .with(fmt_layer)
+        .init();
+}
+
 impl Logger {
     /// Log an error message
     pub fn error(message: &str) {
@@ -57,40 +107,74 @@ impl Logger {
 
     /// Log HTTP request/response information
     pub fn http_request(method: &str, path: &str, status: u16, duration_ms: u64) {
-        let message = format!("HTTP {} {} - {} - {}ms", method, path, status, duration_ms);
-        Self::log(LogLevel::Info, &message, Some("HTTP"));
+        tracing::info!(
+            context = "HTTP",
+            method,
+            path,
+            status,
+            duration_ms,
+            "HTTP {} {} - {} - {}ms",
+            method,
+            path,
+            status,
+            duration_ms
+        );
     }
 
     /// Log database operations
     pub fn database_operation(operation: &str, table: &str, duration_ms: u64) {
-        let message = format!("DB {} on {} - {}ms", operation, table, duration_ms);
-        Self::log(LogLevel::Debug, &message, Some("DB"));
+        tracing::debug!(
+            context = "DB",
+            operation,
+            table,
+            duration_ms,
+            "DB {} on {} - {}ms",
+            operation,
+            table,
+            duration_ms
+        );
+    }
+
+    /// Log statements that exceeded `database.slow_statement_threshold_ms`
+    pub fn slow_query(operation: &str, table: &str, duration_ms: u64, sql: &str) {
+        tracing::warn!(
+            context = "DB",
+            operation,
+            table,
+            duration_ms,
+            sql,
+            "Slow DB {} on {} - {}ms",
+            operation,
+            table,
+            duration_ms
+        );
     }
 
     /// Log external service calls
     pub fn external_service(service: &str, operation: &str, success: bool, duration_ms: u64) {
         let status = if success { "SUCCESS" } else { "FAILED" };
-        let message = format!(
+        tracing::info!(
+            context = "EXT",
+            service,
+            operation,
+            success,
+            duration_ms,
             "External {} {} - {} - {}ms",
-            service, operation, status, duration_ms
+            service,
+            operation,
+            status,
+            duration_ms
         );
-        Self::log(LogLevel::Info, &message, Some("EXT"));
     }
 
-    /// Core logging function with custom formatting
+    /// Core logging function, forwards to the `tracing` subscriber
     fn log(level: LogLevel, message: &str, context: Option<&str>) {
-        let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S%.3f UTC");
-
-        let formatted_message = match context {
-            Some(ctx) => format!("[{}] {} [{}] {}", timestamp, level, ctx, message),
-            None => format!("[{}] {} {}", timestamp, level, message),
-        };
-
-        // For now, just print to stdout/stderr
-        // Later you can extend this to write to files, send to logging services, etc.
         match level {
-            LogLevel::Error => eprintln!("{}", formatted_message),
-            _ => println!("{}", formatted_message),
+            LogLevel::Error => tracing::error!(context, "{}", message),
+            LogLevel::Warn => tracing::warn!(context, "{}", message),
+            LogLevel::Info => tracing::info!(context, "{}", message),
+            LogLevel::Debug => tracing::debug!(context, "{}", message),
+            LogLevel::Trace => tracing::trace!(context, "{}", message),
         }
     }
 }
@@ -99,48 +183,74 @@ impl Logger {
 #[macro_export]
 macro_rules! log_error {
     ($($arg:tt)*) => {
-        $crate::utils::logger::Logger::error(&format!($($arg)*))
+        ::tracing::error!($($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! log_warn {
     ($($arg:tt)*) => {
-        $crate::utils::logger::Logger::warn(&format!($($arg)*))
+        ::tracing::warn!($($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! log_info {
     ($($arg:tt)*) => {
-        $crate::utils::logger::Logger::info(&format!($($arg)*))
+        ::tracing::info!($($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! log_debug {
     ($($arg:tt)*) => {
-        $crate::utils::logger::Logger::debug(&format!($($arg)*))
+        ::tracing::debug!($($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! log_trace {
     ($($arg:tt)*) => {
-        $crate::utils::logger::Logger::trace(&format!($($arg)*))
+        ::tracing::trace!($($arg)*)
     };
 }
 
-/// Custom Actix-web middleware for logging HTTP requests
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Request ID of the request currently being handled, if any
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Attaches the authenticated user to the current request span
+pub fn record_user_id(user_id: uuid::Uuid) {
+    tracing::Span::current().record("user_id", tracing::field::display(user_id));
+}
+
+/// Attaches the organisation a request operates on to the current request span
+pub fn record_organisation_id(organisation_id: uuid::Uuid) {
+    tracing::Span::current().record("organisation_id", tracing::field::display(organisation_id));
+}
+
+/// Custom Actix-web middleware for logging HTTP requests.
+///
+/// Every request runs inside an `http_request` span carrying its request ID, taken from an
+/// incoming `X-Request-Id` header or generated, and echoed back on the response.
 use actix_web::{
     Error,
     dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready},
+    http::header::{HeaderName, HeaderValue},
 };
 use futures_util::future::LocalBoxFuture;
 use std::{
     future::{Ready, ready},
     time::Instant,
 };
+use tracing::Instrument;
 
 pub struct CustomLogger;
 
@@ -182,16 +292,68 @@ where
         let method = req.method().to_string();
         let path = req.path().to_string();
 
-        let fut = self.service.call(req);
-
-        Box::pin(async move {
-            let res = fut.await?;
-            let duration = start_time.elapsed();
-            let status = res.status().as_u16();
-
-            Logger::http_request(&method, &path, status, duration.as_millis() as u64);
-
-            Ok(res)
-        })
+        // Only propagate IDs that are safe to echo back and to put in logs
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|h| h.to_str().ok())
+            .filter(|id| !id.is_empty() && id.len() <= 128 && id.is_ascii())
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+        let span = tracing::info_span!(
+            "http_request",
+            otel.name = %format!("{} {}", method, path),
+            otel.kind = "server",
+            request_id = %request_id,
+            method = %method,
+            path = %path,
+            http.route = tracing::field::Empty,
+            http.response.status_code = tracing::field::Empty,
+            user_id = tracing::field::Empty,
+            organisation_id = tracing::field::Empty,
+        );
+        telemetry::set_remote_parent(&span, req.headers());
+
+        // Downstream middleware (e.g. auth) records into the span while it is entered
+        let fut = span.in_scope(|| {
+            REQUEST_ID.sync_scope(request_id.clone(), || self.service.call(req))
+        });
+
+        Box::pin(
+            REQUEST_ID
+                .scope(request_id.clone(), async move {
+                    let mut res = fut.await?;
+                    let duration = start_time.elapsed();
+                    let status = res.status().as_u16();
+
+                    // The matched route pattern keeps span names and metrics low-cardinality
+                    let route = res
+                        .request()
+                        .match_pattern()
+                        .unwrap_or_else(|| "unmatched".to_string());
+                    let span = tracing::Span::current();
+                    span.record("otel.name", format!("{} {}", method, route));
+                    span.record("http.route", route.as_str());
+                    span.record("http.response.status_code", status);
+                    telemetry::record_http_request(
+                        &method,
+                        &route,
+                        status,
+                        duration.as_secs_f64(),
+                    );
+                    metrics().observe_http_request(&method, &route, status, duration);
+
+                    if let Ok(value) = HeaderValue::from_str(&request_id) {
+                        res.headers_mut()
+                            .insert(HeaderName::from_static("x-request-id"), value);
+                    }
+
+                    Logger::http_request(&method, &path, status, duration.as_millis() as u64);
+
+                    Ok(res)
+                })
+                .instrument(span),
+        )
     }
 }
@@ -0,0 +1,207 @@
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use sea_orm::{ConnectionTrait, DatabaseBackend, DatabaseConnection};
+
+use crate::errors::AppError;
+use crate::jobs;
+use crate::models::entities::JobStatus;
+
+/// Prometheus collectors for the control plane, exposed on `/metrics`
+pub struct Metrics {
+    registry: Registry,
+    http_requests_total: IntCounterVec,
+    http_request_duration_seconds: HistogramVec,
+    db_query_duration_seconds: HistogramVec,
+    db_pool_connections: IntGaugeVec,
+    job_queue_depth: IntGaugeVec,
+    jobs_total: IntCounterVec,
+    cache_requests_total: IntCounterVec,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("cplane".to_string()), None)
+            .expect("metric registry prefix is valid");
+
+        let http_requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status"),
+            &["method", "route", "status"],
+        )
+        .expect("metric definition is valid");
+        let http_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency by route and status",
+            ),
+            &["method", "route", "status"],
+        )
+        .expect("metric definition is valid");
+        let db_query_duration_seconds = HistogramVec::new(
+            HistogramOpts::new("db_query_duration_seconds", "Database statement latency")
+                .buckets(vec![0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0]),
+            &["operation", "table", "outcome"],
+        )
+        .expect("metric definition is valid");
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Database pool connections by state"),
+            &["state"],
+        )
+        .expect("metric definition is valid");
+        let job_queue_depth = IntGaugeVec::new(
+            Opts::new("job_queue_depth", "Jobs waiting or running by status"),
+            &["status"],
+        )
+        .expect("metric definition is valid");
+        let jobs_total = IntCounterVec::new(
+            Opts::new("jobs_total", "Finished job attempts by kind and outcome"),
+            &["kind", "outcome"],
+        )
+        .expect("metric definition is valid");
+        let cache_requests_total = IntCounterVec::new(
+            Opts::new("cache_requests_total", "Cache lookups by cache and outcome"),
+            &["cache", "outcome"],
+        )
+        .expect("metric definition is valid");
+
+        for collector in [
+            Box::new(http_requests_total.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_request_duration_seconds.clone()),
+            Box::new(db_query_duration_seconds.clone()),
+            Box::new(db_pool_connections.clone()),
+            Box::new(job_queue_depth.clone()),
+            Box::new(jobs_total.clone()),
+            Box::new(cache_requests_total.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+
+        Self {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            db_query_duration_seconds,
+            db_pool_connections,
+            job_queue_depth,
+            jobs_total,
+            cache_requests_total,
+        }
+    }
+
+    pub fn observe_http_request(&self, method: &str, route: &str, status: u16, duration: Duration) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+        self.http_requests_total.with_label_values(&labels).inc();
+        self.http_request_duration_seconds
+            .with_label_values(&labels)
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn observe_db_query(&self, operation: &str, table: &str, failed: bool, duration: Duration) {
+        let outcome = if failed { "error" } else { "ok" };
+        self.db_query_duration_seconds
+            .with_label_values(&[operation, table, outcome])
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn observe_job(&self, kind: &str, outcome: &str) {
+        self.jobs_total.with_label_values(&[kind, outcome]).inc();
+    }
+
+    /// `outcome` is `hit`, `miss` or `error`, the hit rate being hits over all three
+    pub fn observe_cache(&self, cache: &str, outcome: &str) {
+        self.cache_requests_total.with_label_values(&[cache, outcome]).inc();
+    }
+
+    /// Refreshes the gauges that are sampled rather than tracked, then encodes
+    /// everything in the Prometheus text format
+    pub async fn render(&self, db: &DatabaseConnection) -> Result<String, AppError> {
+        let (size, idle) = match db.get_database_backend() {
+            DatabaseBackend::Sqlite => {
+                let pool = db.get_sqlite_connection_pool();
+                (pool.size(), pool.num_idle())
+            }
+            _ => {
+                let pool = db.get_postgres_connection_pool();
+                (pool.size(), pool.num_idle())
+            }
+        };
+        let idle = idle as i64;
+        self.db_pool_connections
+            .with_label_values(&["idle"])
+            .set(idle);
+        self.db_pool_connections
+            .with_label_values(&["in_use"])
+            .set(size as i64 - idle);
+
+        for status in [JobStatus::Pending, JobStatus::Running] {
+            self.job_queue_depth
+                .with_label_values(&[job_status_label(&status)])
+                .set(0);
+        }
+        for (status, count) in jobs::queue_depth(db).await? {
+            self.job_queue_depth
+                .with_label_values(&[job_status_label(&status)])
+                .set(count);
+        }
+
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        String::from_utf8(buffer).map_err(|e| AppError::Internal(e.to_string()))
+    }
+}
+
+fn job_status_label(status: &JobStatus) -> &'static str {
+    match status {
+        JobStatus::Pending => "pending",
+        JobStatus::Running => "running",
+        JobStatus::Completed => "completed",
+        JobStatus::Failed => "failed",
+    }
+}
+
+/// Statement kind and the table it targets, e.g. `("SELECT", "jobs")`, taken from the SQL
+/// sea-orm generates. Unknown shapes fall back to `"other"`.
+pub fn statement_target(sql: &str) -> (&'static str, &str) {
+    let mut words = sql.split_whitespace();
+    let operation = match words.next().map(|word| word.to_ascii_uppercase()).as_deref() {
+        Some("SELECT") => "SELECT",
+        Some("INSERT") => "INSERT",
+        Some("UPDATE") => "UPDATE",
+        Some("DELETE") => "DELETE",
+        _ => return ("OTHER", "other"),
+    };
+
+    let keyword = match operation {
+        "INSERT" => "INTO",
+        "UPDATE" => "UPDATE",
+        _ => "FROM",
+    };
+    let table = if operation == "UPDATE" {
+        words.next()
+    } else {
+        words
+            .by_ref()
+            .skip_while(|word| !word.eq_ignore_ascii_case(keyword))
+            .nth(1)
+    };
+
+    let table = table
+        .map(|table| table.trim_matches(|c: char| c == '"' || c == '`' || c == '(' || c == ','))
+        .filter(|table| !table.is_empty())
+        .unwrap_or("other");
+    (operation, table)
+}
@@ -1,2 +1,7 @@
+pub mod clock;
+pub mod listing;
 pub mod logger;
+pub mod metrics;
 pub mod pagination;
+pub mod telemetry;
+pub mod validation;
@@ -1,49 +1,42 @@
+use actix_web::http::header::LINK;
+use actix_web::{HttpRequest, HttpResponse};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, SecondsFormat, Utc};
+use sea_orm::{
+    ConnectionTrait, DbErr, EntityTrait, FromQueryResult, Order, PaginatorTrait, QueryOrder,
+    Select,
+};
 use serde::{Deserialize, Serialize};
+use url::form_urlencoded;
+use utoipa::IntoParams;
+use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct PaginatedResponse<T> {
-    pub data: Vec<T>,
-    pub pagination: PaginationMeta,
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct PaginationMeta {
-    pub total: u64,
-    pub page: u64,
-    pub per_page: u64,
-    pub total_pages: u64,
-    pub has_next: bool,
-    pub has_prev: bool,
-}
-
-impl<T> PaginatedResponse<T> {
-    pub fn new(data: Vec<T>, total: u64, page: u64, per_page: u64) -> Self {
-        let total_pages = if total == 0 {
-            1
-        } else {
-            (total + per_page - 1) / per_page
-        };
-        let has_next = page < total_pages;
-        let has_prev = page > 1;
-
-        Self {
-            data,
-            pagination: PaginationMeta {
-                total,
-                page,
-                per_page,
-                total_pages,
-                has_next,
-                has_prev,
-            },
-        }
-    }
-}
+use crate::errors::ValidationError;
+use crate::utils::validation::Validator;
+
+pub use c_plane_client::types::{PaginatedResponse, PaginationMeta};
 
-#[derive(Deserialize)]
+/// Pages either by number or, when `after` or `before` is given, by cursor. Cursors
+/// come from a previous page's `next_cursor` and `prev_cursor`; they skip counting
+/// and don't shift when items are added or removed in between.
+#[derive(Deserialize, IntoParams)]
 pub struct PaginationQuery {
+    /// 1-based page number, defaults to 1
     pub page: Option<u64>,
+    /// Defaults to 10, at most 100
     pub per_page: Option<u64>,
+    /// Items after this cursor
+    pub after: Option<String>,
+    /// Items before this cursor
+    pub before: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pagination {
+    Page { page: u64, per_page: u64 },
+    After { cursor: Cursor, per_page: u64 },
+    Before { cursor: Cursor, per_page: u64 },
 }
 
 impl PaginationQuery {
@@ -52,6 +45,206 @@ impl PaginationQuery {
     }
 
     pub fn per_page(&self) -> u64 {
-        self.per_page.unwrap_or(10).min(100).max(1) // Default 10, max 100, min 1
+        self.per_page.unwrap_or(10).clamp(1, 100)
+    }
+
+    pub fn pagination(&self) -> Result<Pagination, ValidationError> {
+        let mut v = Validator::default();
+        v.check(
+            "before",
+            self.after.is_none() || self.before.is_none(),
+            "exclusive",
+            "can't be combined with after",
+        );
+        v.check(
+            "page",
+            self.page.is_none() || (self.after.is_none() && self.before.is_none()),
+            "exclusive",
+            "can't be combined with a cursor",
+        );
+        let after = decode(&mut v, "after", self.after.as_deref());
+        let before = decode(&mut v, "before", self.before.as_deref());
+        v.finish()?;
+
+        let per_page = self.per_page();
+        Ok(match (after, before) {
+            (Some(cursor), _) => Pagination::After { cursor, per_page },
+            (_, Some(cursor)) => Pagination::Before { cursor, per_page },
+            _ => Pagination::Page {
+                page: self.page(),
+                per_page,
+            },
+        })
+    }
+}
+
+fn decode(v: &mut Validator, field: &'static str, value: Option<&str>) -> Option<Cursor> {
+    let cursor = value.map(Cursor::decode)?;
+    v.check(field, cursor.is_some(), "cursor", "is not a cursor from this listing");
+    cursor
+}
+
+/// Position of an item in a listing ordered newest first, by `created_at` and then by
+/// `id` among items created at the same time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    /// Opaque to clients, so the format can change as long as old cursors still decode
+    pub fn encode(&self) -> String {
+        let created_at = self.created_at.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+        URL_SAFE_NO_PAD.encode(format!("{}|{}", created_at, self.id))
+    }
+
+    pub fn decode(value: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(value).ok()?;
+        let (created_at, id) = std::str::from_utf8(&bytes).ok()?.split_once('|')?;
+        Some(Self {
+            created_at: DateTime::parse_from_rfc3339(created_at).ok()?.to_utc(),
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+/// Entities listed newest first, which is what cursors point into
+pub trait Keyset: EntityTrait {
+    /// The `created_at` and `id` columns
+    fn keyset() -> (Self::Column, Self::Column);
+
+    fn cursor(model: &Self::Model) -> Cursor;
+}
+
+/// The page of `select` that `pagination` asks for, ordered by `sort` and then newest
+/// first. Cursors only point into the default order, so they are left out when sorting
+/// by anything else.
+pub async fn fetch_page<E, C>(
+    db: &C,
+    select: Select<E>,
+    pagination: Pagination,
+    sort: Vec<(E::Column, Order)>,
+) -> Result<PaginatedResponse<E::Model>, DbErr>
+where
+    E: Keyset,
+    E::Model: FromQueryResult + Send + Sync,
+    C: ConnectionTrait,
+{
+    let (created_at, id) = E::keyset();
+
+    let mut page = match pagination {
+        Pagination::Page { page, per_page } => {
+            let select = sort
+                .iter()
+                .fold(select, |select, (column, order)| select.order_by(*column, order.clone()));
+            let paginator = select
+                .order_by_desc(created_at)
+                .order_by_desc(id)
+                .paginate(db, per_page);
+            let total = paginator.num_items().await?;
+            let data = paginator.fetch_page(page - 1).await?;
+            PaginatedResponse::new(data, total, page, per_page)
+        }
+        // One extra item tells whether there is another page beyond this one
+        Pagination::After { cursor, per_page } => {
+            let mut data = select
+                .cursor_by((created_at, id))
+                .desc()
+                .after((cursor.created_at, cursor.id))
+                .first(per_page + 1)
+                .all(db)
+                .await?;
+            let has_next = data.len() as u64 > per_page;
+            data.truncate(per_page as usize);
+            keyset_page(data, per_page, has_next, true)
+        }
+        Pagination::Before { cursor, per_page } => {
+            let mut data = select
+                .cursor_by((created_at, id))
+                .desc()
+                .before((cursor.created_at, cursor.id))
+                .last(per_page + 1)
+                .all(db)
+                .await?;
+            let has_prev = data.len() as u64 > per_page;
+            if has_prev {
+                data.remove(0);
+            }
+            keyset_page(data, per_page, true, has_prev)
+        }
+    };
+
+    let meta = &mut page.pagination;
+    if !sort.is_empty() {
+        return Ok(page);
+    }
+    if meta.has_next {
+        meta.next_cursor = page.data.last().map(|model| E::cursor(model).encode());
+    }
+    if meta.has_prev {
+        meta.prev_cursor = page.data.first().map(|model| E::cursor(model).encode());
+    }
+    Ok(page)
+}
+
+fn keyset_page<T>(data: Vec<T>, per_page: u64, has_next: bool, has_prev: bool) -> PaginatedResponse<T> {
+    PaginatedResponse {
+        data,
+        pagination: PaginationMeta {
+            total: None,
+            page: None,
+            per_page,
+            total_pages: None,
+            has_next,
+            has_prev,
+            next_cursor: None,
+            prev_cursor: None,
+        },
+    }
+}
+
+/// A 200 with `page` as the body and a `Link` header pointing at its neighbours
+pub fn paginated<T: Serialize>(req: &HttpRequest, page: PaginatedResponse<T>) -> HttpResponse {
+    let mut response = HttpResponse::Ok();
+    if let Some(links) = links(req, &page.pagination) {
+        response.insert_header((LINK, links));
+    }
+    response.json(page)
+}
+
+/// `next` and `prev` links in the mode the page was requested in, keeping every other
+/// query parameter
+fn links(req: &HttpRequest, meta: &PaginationMeta) -> Option<String> {
+    let link = |rel: &str, key: &str, value: &str| {
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        for (k, v) in form_urlencoded::parse(req.query_string().as_bytes()) {
+            if !matches!(k.as_ref(), "page" | "after" | "before") {
+                query.append_pair(&k, &v);
+            }
+        }
+        query.append_pair(key, value);
+        format!("<{}?{}>; rel=\"{}\"", req.path(), query.finish(), rel)
+    };
+
+    let mut links = Vec::new();
+    match meta.page {
+        Some(page) => {
+            if meta.has_next {
+                links.push(link("next", "page", &(page + 1).to_string()));
+            }
+            if meta.has_prev {
+                links.push(link("prev", "page", &(page - 1).to_string()));
+            }
+        }
+        None => {
+            if let Some(cursor) = &meta.next_cursor {
+                links.push(link("next", "after", cursor));
+            }
+            if let Some(cursor) = &meta.prev_cursor {
+                links.push(link("prev", "before", cursor));
+            }
+        }
     }
+    (!links.is_empty()).then(|| links.join(", "))
 }
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+use actix_web::http::header::HeaderMap;
+use opentelemetry::metrics::Histogram;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::{
+    Span as _, SpanKind, Status, TraceContextExt as _, Tracer as _, TracerProvider as _,
+};
+use opentelemetry::{Context, KeyValue, global};
+use opentelemetry_otlp::{MetricExporter, Protocol, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{SdkTracerProvider, Tracer};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::Registry;
+
+use crate::config::Config;
+use crate::errors::{AppError, ExternalError};
+
+pub type OtelLayer = OpenTelemetryLayer<Registry, Tracer>;
+
+/// Keeps the OTLP exporters alive; `shutdown` flushes whatever is still buffered
+pub struct Telemetry {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+impl Telemetry {
+    /// Sets up OTLP/HTTP export of traces and metrics to `Config::otlp_endpoint`.
+    /// Returns `None`, leaving OpenTelemetry fully disabled, when no endpoint is configured.
+    pub fn init(config: &Config) -> Result<Option<(Self, OtelLayer)>, AppError> {
+        let Some(endpoint) = config.telemetry.otlp_endpoint.as_deref() else {
+            return Ok(None);
+        };
+        let endpoint = endpoint.trim_end_matches('/');
+
+        let resource = Resource::builder()
+            .with_service_name(config.telemetry.service_name.clone())
+            .build();
+
+        let span_exporter = SpanExporter::builder()
+            .with_http()
+            .with_protocol(Protocol::HttpBinary)
+            .with_endpoint(format!("{}/v1/traces", endpoint))
+            .build()
+            .map_err(|e| AppError::External(ExternalError::ServiceUnavailable(e.to_string())))?;
+        let tracer_provider = SdkTracerProvider::builder()
+            .with_batch_exporter(span_exporter)
+            .with_resource(resource.clone())
+            .build();
+
+        let metric_exporter = MetricExporter::builder()
+            .with_http()
+            .with_protocol(Protocol::HttpBinary)
+            .with_endpoint(format!("{}/v1/metrics", endpoint))
+            .build()
+            .map_err(|e| AppError::External(ExternalError::ServiceUnavailable(e.to_string())))?;
+        let meter_provider = SdkMeterProvider::builder()
+            .with_periodic_exporter(metric_exporter)
+            .with_resource(resource)
+            .build();
+
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        global::set_tracer_provider(tracer_provider.clone());
+        global::set_meter_provider(meter_provider.clone());
+
+        let tracer = tracer_provider.tracer("c-plane");
+        let layer = tracing_opentelemetry::layer().with_tracer(tracer);
+
+        Ok(Some((
+            Self {
+                tracer_provider,
+                meter_provider,
+            },
+            layer,
+        )))
+    }
+
+    pub fn shutdown(self) {
+        if let Err(err) = self.tracer_provider.shutdown() {
+            eprintln!("Failed to flush traces: {}", err);
+        }
+        if let Err(err) = self.meter_provider.shutdown() {
+            eprintln!("Failed to flush metrics: {}", err);
+        }
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+/// Continues the trace of an incoming W3C `traceparent` header on `span`
+pub fn set_remote_parent(span: &tracing::Span, headers: &HeaderMap) {
+    let parent: Context =
+        global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+    let _ = span.set_parent(parent);
+}
+
+/// W3C trace context of the current span, to be sent along with outgoing requests and
+/// messages. Empty when OpenTelemetry is disabled.
+pub fn trace_headers() -> HashMap<String, String> {
+    let context = tracing::Span::current().context();
+    let mut headers = HashMap::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut headers)
+    });
+    headers
+}
+
+/// Records `http.server.request.duration` for a finished request
+pub fn record_http_request(method: &str, route: &str, status: u16, duration_secs: f64) {
+    static HISTOGRAM: OnceLock<Histogram<f64>> = OnceLock::new();
+
+    let histogram = HISTOGRAM.get_or_init(|| {
+        global::meter("c-plane")
+            .f64_histogram("http.server.request.duration")
+            .with_unit("s")
+            .build()
+    });
+    histogram.record(
+        duration_secs,
+        &[
+            KeyValue::new("http.request.method", method.to_string()),
+            KeyValue::new("http.route", route.to_string()),
+            KeyValue::new("http.response.status_code", status as i64),
+        ],
+    );
+}
+
+/// Records a finished statement as a `db` client span under the current span. The
+/// hook sea-orm offers only runs once a statement is done, so the span is backdated
+/// by its duration instead of being opened around it.
+pub fn record_db_statement(
+    system: &'static str,
+    operation: &'static str,
+    table: &str,
+    sql: &str,
+    elapsed: Duration,
+    failed: bool,
+) {
+    let parent = tracing::Span::current().context();
+    if !parent.span().span_context().is_valid() {
+        // Outside of any trace, e.g. migrations or with OpenTelemetry disabled
+        return;
+    }
+
+    let end = SystemTime::now();
+    let tracer = global::tracer("c-plane");
+    let mut span = tracer
+        .span_builder(format!("{} {}", operation, table))
+        .with_kind(SpanKind::Client)
+        .with_start_time(end.checked_sub(elapsed).unwrap_or(end))
+        .with_attributes([
+            KeyValue::new("db.system.name", system),
+            KeyValue::new("db.operation.name", operation),
+            KeyValue::new("db.collection.name", table.to_string()),
+            KeyValue::new("db.query.text", sql.to_string()),
+        ])
+        .start_with_context(&tracer, &parent);
+    if failed {
+        span.set_status(Status::error("statement failed"));
+    }
+    span.end_with_timestamp(end);
+}
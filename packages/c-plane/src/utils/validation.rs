@@ -0,0 +1,196 @@
+use std::future::Future;
+use std::ops::Deref;
+use std::pin::Pin;
+
+use actix_web::error::JsonPayloadError;
+use actix_web::{FromRequest, HttpRequest, dev::Payload, web};
+use serde::de::DeserializeOwned;
+
+use crate::errors::{AppError, FieldError, ValidationError};
+
+/// Rules a request body has to satisfy before a handler sees it
+pub trait Validate {
+    fn validate(&self, v: &mut Validator);
+}
+
+/// Collects every failing rule so the client can fix all fields in one go
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn string<'a>(&'a mut self, field: &'static str, value: &'a str) -> StringRules<'a> {
+        StringRules {
+            validator: self,
+            field,
+            value: Some(value),
+        }
+    }
+
+    /// Rules only apply when the value is present
+    pub fn optional<'a>(&'a mut self, field: &'static str, value: Option<&'a str>) -> StringRules<'a> {
+        StringRules {
+            validator: self,
+            field,
+            value,
+        }
+    }
+
+    /// A custom or cross-field rule, failing with `code` unless `ok`
+    pub fn check(
+        &mut self,
+        field: impl Into<String>,
+        ok: bool,
+        code: &'static str,
+        message: impl Into<String>,
+    ) {
+        if !ok {
+            self.fail(field, code, message);
+        }
+    }
+
+    pub fn fail(&mut self, field: impl Into<String>, code: &'static str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.into(),
+            code,
+            message: message.into(),
+        });
+    }
+
+    pub fn finish(self) -> Result<(), ValidationError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError::InvalidFields(self.errors))
+        }
+    }
+}
+
+/// Chained rules for one string field; after the first failure the rest are skipped
+/// so each field reports a single error
+pub struct StringRules<'a> {
+    validator: &'a mut Validator,
+    field: &'static str,
+    value: Option<&'a str>,
+}
+
+impl StringRules<'_> {
+    fn rule(mut self, ok: impl FnOnce(&str) -> bool, code: &'static str, message: impl FnOnce() -> String) -> Self {
+        if let Some(value) = self.value
+            && !ok(value)
+        {
+            self.validator.fail(self.field, code, message());
+            self.value = None;
+        }
+        self
+    }
+
+    pub fn not_blank(self) -> Self {
+        self.rule(|v| !v.trim().is_empty(), "blank", || "must not be blank".to_string())
+    }
+
+    /// Length in characters, inclusive on both ends
+    pub fn length(self, min: usize, max: usize) -> Self {
+        self.rule(
+            |v| (min..=max).contains(&v.chars().count()),
+            "length",
+            || format!("must be between {} and {} characters", min, max),
+        )
+    }
+
+    pub fn max_length(self, max: usize) -> Self {
+        self.length(0, max)
+    }
+
+    /// Fails with `code` when `matches` rejects the value, `expected` describes the format
+    pub fn pattern(self, matches: fn(&str) -> bool, code: &'static str, expected: &'static str) -> Self {
+        self.rule(matches, code, || format!("must be {}", expected))
+    }
+
+    /// An absolute http or https URL
+    pub fn url(self) -> Self {
+        self.rule(
+            |v| url::Url::parse(v).is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host()),
+            "url",
+            || "must be an http or https URL".to_string(),
+        )
+    }
+
+    pub fn one_of(self, allowed: &'static [&'static str]) -> Self {
+        self.rule(
+            |v| allowed.contains(&v),
+            "one_of",
+            || format!("must be one of {}", allowed.join(", ")),
+        )
+    }
+}
+
+/// Lowercase letters and digits, separated by single hyphens
+pub fn is_slug(value: &str) -> bool {
+    !value.is_empty()
+        && !value.starts_with('-')
+        && !value.ends_with('-')
+        && !value.contains("--")
+        && value.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// A JSON body that passed its `Validate` rules. Parse and validation failures are
+/// both answered with an `ErrorResponse`.
+pub struct ValidatedJson<T>(pub T);
+
+impl<T> ValidatedJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ValidatedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + Validate + 'static> FromRequest for ValidatedJson<T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = web::Json::<T>::from_request(req, payload);
+        Box::pin(async move {
+            // Errors already converted by the `json_config` handler pass through as they are
+            let body = json
+                .await
+                .map_err(|err| match err.as_error::<JsonPayloadError>() {
+                    Some(err) => AppError::from(json_error(err)).into(),
+                    None => err,
+                })?
+                .into_inner();
+
+            let mut validator = Validator::default();
+            body.validate(&mut validator);
+            validator.finish().map_err(AppError::from)?;
+            Ok(ValidatedJson(body))
+        })
+    }
+}
+
+fn json_error(err: &JsonPayloadError) -> ValidationError {
+    match err {
+        JsonPayloadError::ContentType => ValidationError::UnsupportedContentType,
+        JsonPayloadError::Overflow { limit } | JsonPayloadError::OverflowKnownLength { limit, .. } => {
+            ValidationError::BodyTooLarge { limit: *limit }
+        }
+        JsonPayloadError::Deserialize(err) if err.is_data() => {
+            ValidationError::InvalidBody(err.to_string())
+        }
+        other => ValidationError::MalformedBody(other.to_string()),
+    }
+}
+
+/// Makes plain `web::Json` extractors answer with the same `ErrorResponse` shape
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|err, _req| AppError::from(json_error(&err)).into())
+}
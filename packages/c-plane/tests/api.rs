@@ -0,0 +1,756 @@
+mod common;
+
+use std::sync::Arc;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, web};
+use c_plane::create_app;
+use c_plane::models::entities::{
+    AuditAction, AuditLog, OrganisationMemberActiveModel, OrganisationRole, Plan, audit_log,
+};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use common::{FakeKratos, create_organisation, fixed_time, set_plan, test_state};
+
+#[actix_web::test]
+async fn liveness_does_not_touch_dependencies() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+
+    let req = test::TestRequest::get().uri("/health/live").to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn readiness_follows_kratos() {
+    let state = test_state().await;
+
+    let healthy = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let req = test::TestRequest::get().uri("/health/ready").to_request();
+    let body: Value = test::call_and_read_body_json(&healthy, req).await;
+    assert_eq!(body["ready"], true);
+    assert_eq!(body["components"]["kratos"]["message"], "Kratos v-test");
+
+    let mut down = state;
+    down.kratos = Arc::new(FakeKratos { healthy: false });
+    let unhealthy = test::init_service(create_app(web::Data::new(down))).await;
+    let req = test::TestRequest::get().uri("/health/ready").to_request();
+    let res = test::call_service(&unhealthy, req).await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[actix_web::test]
+async fn metrics_require_the_token_when_one_is_set() {
+    let mut state = test_state().await;
+    state.config.telemetry.metrics_token = Some("scrape-token".parse().unwrap());
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+    let scrape = |authorization: Option<&str>| {
+        let mut req = test::TestRequest::get().uri("/metrics");
+        if let Some(value) = authorization {
+            req = req.insert_header(("Authorization", value));
+        }
+        req.to_request()
+    };
+
+    for authorization in [None, Some("Bearer scrape"), Some("Bearer scrape-token-2"), Some("scrape-token")] {
+        let res = test::call_service(&app, scrape(authorization)).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{:?}", authorization);
+    }
+    let res = test::call_service(&app, scrape(Some("Bearer scrape-token"))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn organisations_are_only_visible_to_members() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let owner = Uuid::new_v4();
+    let organisation_id = create_organisation(&state, owner, "Acme").await.to_string();
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/organisations/{}", organisation_id))
+        .insert_header(("X-User", owner.to_string()))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["created_at"], json!(fixed_time()), "timestamps come from the injected clock");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/organisations/{}", organisation_id))
+        .insert_header(("X-User", Uuid::new_v4().to_string()))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_ne!(res.status(), StatusCode::OK);
+
+    for (caller, expected) in [(owner, vec![organisation_id.as_str()]), (Uuid::new_v4(), vec![])] {
+        let req = test::TestRequest::get()
+            .uri("/organisations")
+            .insert_header(("X-User", caller.to_string()))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        let ids: Vec<&str> = body["data"].as_array().unwrap().iter().map(|o| o["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, expected);
+    }
+}
+
+#[cfg(feature = "cloud")]
+#[actix_web::test]
+async fn organisations_are_created_with_the_caller_as_owner() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+    let owner = Uuid::new_v4();
+    let create = |avatar_url: Value| {
+        test::TestRequest::post()
+            .uri("/organisations/")
+            .insert_header(("X-User", owner.to_string()))
+            .set_json(json!({ "name": "Acme", "description": null, "avatar_url": avatar_url }))
+            .to_request()
+    };
+
+    let body: Value = test::call_and_read_body_json(&app, create(Value::Null)).await;
+    assert_eq!(body["organisation_member"]["role"], "Owner");
+    assert_eq!(body["organisation"]["created_by"], owner.to_string());
+    assert_eq!(
+        body["organisation"]["created_at"],
+        json!(fixed_time()),
+        "timestamps come from the injected clock"
+    );
+
+    let res = test::call_service(&app, create(json!("not a url"))).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["errors"][0]["field"], "avatar_url");
+    assert_eq!(body["errors"][0]["code"], "url");
+}
+
+#[actix_web::test]
+async fn projects_are_scoped_to_organisation_members() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let owner = Uuid::new_v4();
+    let outsider = Uuid::new_v4();
+    let organisation_id = create_organisation(&state, owner, "Projects Inc").await;
+
+    let req = test::TestRequest::post()
+        .uri("/projects")
+        .insert_header(("X-User", owner.to_string()))
+        .set_json(json!({
+            "name": "api",
+            "description": null,
+            "slug": "api",
+            "organisation_id": organisation_id,
+            "owner_id": owner,
+            "is_public": false,
+        }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let project: Value = test::read_body_json(res).await;
+    let project_uri = format!("/projects/{}", project["id"].as_str().unwrap());
+
+    let req = test::TestRequest::get()
+        .uri("/projects")
+        .insert_header(("X-User", owner.to_string()))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["pagination"]["total"], 1);
+
+    let req = test::TestRequest::get()
+        .uri("/projects")
+        .insert_header(("X-User", outsider.to_string()))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["pagination"]["total"], 0);
+
+    let req = test::TestRequest::delete()
+        .uri(&project_uri)
+        .insert_header(("X-User", outsider.to_string()))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_ne!(res.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::delete()
+        .uri(&project_uri)
+        .insert_header(("X-User", owner.to_string()))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+}
+
+#[actix_web::test]
+async fn projects_are_changed_by_admins_only() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let owner = Uuid::new_v4();
+    let organisation_id = create_organisation(&state, owner, "Roles Ltd").await;
+
+    let mut members = Vec::new();
+    for role in [OrganisationRole::Viewer, OrganisationRole::Member, OrganisationRole::Admin] {
+        let identity_id = Uuid::new_v4();
+        OrganisationMemberActiveModel {
+            id: Set(Uuid::new_v4()),
+            organisation_id: Set(organisation_id),
+            identity_id: Set(identity_id),
+            role: Set(role),
+            is_active: Set(true),
+            joined_at: Set(fixed_time()),
+            invited_by: Set(owner),
+            invited_at: Set(fixed_time()),
+            invitation_accepted_at: Set(fixed_time()),
+        }
+        .insert(&state.db)
+        .await
+        .unwrap();
+        members.push(identity_id);
+    }
+    let [viewer, member, admin] = members[..] else { unreachable!() };
+
+    let req = test::TestRequest::post()
+        .uri("/projects")
+        .insert_header(("X-User", member.to_string()))
+        .set_json(json!({
+            "name": "api",
+            "description": null,
+            "slug": "api",
+            "organisation_id": organisation_id,
+            "owner_id": member,
+            "is_public": false,
+        }))
+        .to_request();
+    let project: Value = test::call_and_read_body_json(&app, req).await;
+    let uri = format!("/projects/{}", project["id"].as_str().unwrap());
+    let update = |user: Uuid, body: Value| {
+        test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(("X-User", user.to_string()))
+            .set_json(body)
+            .to_request()
+    };
+    let delete = |user: Uuid| {
+        test::TestRequest::delete()
+            .uri(&uri)
+            .insert_header(("X-User", user.to_string()))
+            .to_request()
+    };
+
+    for user in [viewer, member] {
+        for req in [
+            update(user, json!({ "name": "renamed" })),
+            update(user, json!({ "is_archived": true })),
+            delete(user),
+        ] {
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::FORBIDDEN);
+            let body: Value = test::read_body_json(res).await;
+            assert_eq!(body["code"], "insufficient_role");
+        }
+    }
+    let req = test::TestRequest::get()
+        .uri(&uri)
+        .insert_header(("X-User", viewer.to_string()))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["name"], "api", "reading stays open to every member");
+
+    let res = test::call_service(&app, update(admin, json!({ "name": "renamed" }))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, delete(admin)).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+}
+
+#[actix_web::test]
+async fn archived_projects_are_read_only_until_unarchived() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let owner = Uuid::new_v4();
+    let organisation_id = create_organisation(&state, owner, "Archive Co").await;
+
+    let mut uris = Vec::new();
+    for is_public in [false, true] {
+        let req = test::TestRequest::post()
+            .uri("/projects")
+            .insert_header(("X-User", owner.to_string()))
+            .set_json(json!({
+                "name": "site",
+                "description": null,
+                "slug": "site",
+                "organisation_id": organisation_id,
+                "owner_id": owner,
+                "is_public": is_public,
+            }))
+            .to_request();
+        let project: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(project["is_public"], is_public);
+        uris.push(format!("/projects/{}", project["id"].as_str().unwrap()));
+    }
+    let update = |uri: &str, body: Value| {
+        test::TestRequest::put()
+            .uri(uri)
+            .insert_header(("X-User", owner.to_string()))
+            .set_json(body)
+            .to_request()
+    };
+
+    let res = test::call_service(&app, update(&uris[1], json!({ "is_archived": true }))).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "cannot_archive_public_project");
+
+    let res = test::call_service(&app, update(&uris[0], json!({ "is_archived": true }))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, update(&uris[0], json!({ "name": "renamed" }))).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "project_archived");
+    let req = test::TestRequest::delete()
+        .uri(&uris[0])
+        .insert_header(("X-User", owner.to_string()))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT, "deleting is a change too");
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "project_archived");
+
+    let res = test::call_service(&app, update(&uris[0], json!({ "name": "renamed", "is_archived": false }))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["name"], "renamed");
+    assert_eq!(body["is_archived"], false);
+}
+
+#[actix_web::test]
+async fn projects_move_between_organisations_the_caller_administers() {
+    let state = test_state().await;
+    let db = state.db.clone();
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let owner = Uuid::new_v4();
+    let other = Uuid::new_v4();
+
+    let source = create_organisation(&state, owner, "Moving Co").await;
+    let destination = create_organisation(&state, owner, "Moving Co").await;
+    let foreign = create_organisation(&state, other, "Moving Co").await;
+
+    let req = test::TestRequest::post()
+        .uri("/projects")
+        .insert_header(("X-User", owner.to_string()))
+        .set_json(json!({
+            "name": "api",
+            "description": null,
+            "slug": "api",
+            "organisation_id": source,
+            "owner_id": owner,
+            "is_public": false,
+        }))
+        .to_request();
+    let project: Value = test::call_and_read_body_json(&app, req).await;
+    let uri = format!("/projects/{}/transfer", project["id"].as_str().unwrap());
+    let transfer = |user: Uuid, organisation_id: Uuid| {
+        test::TestRequest::post()
+            .uri(&uri)
+            .insert_header(("X-User", user.to_string()))
+            .set_json(json!({ "organisation_id": organisation_id }))
+            .to_request()
+    };
+
+    let res = test::call_service(&app, transfer(other, foreign)).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND, "not a member of the source");
+    let res = test::call_service(&app, transfer(owner, foreign)).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND, "not a member of the destination");
+
+    OrganisationMemberActiveModel {
+        id: Set(Uuid::new_v4()),
+        organisation_id: Set(foreign),
+        identity_id: Set(owner),
+        role: Set(OrganisationRole::Member),
+        is_active: Set(true),
+        joined_at: Set(fixed_time()),
+        invited_by: Set(other),
+        invited_at: Set(fixed_time()),
+        invitation_accepted_at: Set(fixed_time()),
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+    let res = test::call_service(&app, transfer(owner, foreign)).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "insufficient_role");
+
+    let project_id: Uuid = project["id"].as_str().unwrap().parse().unwrap();
+    let audit_log = || {
+        AuditLog::find()
+            .filter(audit_log::Column::ProjectId.eq(project_id))
+            .order_by_asc(audit_log::Column::Action)
+            .all(&db)
+    };
+    assert!(audit_log().await.unwrap().is_empty(), "refused transfers leave no entries");
+
+    let res = test::call_service(&app, transfer(owner, destination)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["organisation_id"], destination.to_string());
+
+    let entries = audit_log().await.unwrap();
+    let recorded: Vec<_> = entries
+        .iter()
+        .map(|entry| (entry.organisation_id, entry.action, entry.actor_id))
+        .collect();
+    assert_eq!(
+        recorded,
+        [
+            (destination, AuditAction::ProjectTransferredIn, owner),
+            (source, AuditAction::ProjectTransferredOut, owner),
+        ]
+    );
+    for entry in entries {
+        assert_eq!(entry.details, json!({ "from": source, "to": destination }));
+        assert_eq!(entry.created_at, fixed_time());
+    }
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/projects?filter[organisation_id]={}", source))
+        .insert_header(("X-User", owner.to_string()))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["pagination"]["total"], 0);
+}
+
+#[actix_web::test]
+async fn invalid_fields_are_reported_together() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+    let owner = Uuid::new_v4();
+
+    let req = test::TestRequest::post()
+        .uri("/projects")
+        .insert_header(("X-User", owner.to_string()))
+        .set_json(json!({
+            "name": "  ",
+            "description": null,
+            "slug": "Not A Slug",
+            "organisation_id": Uuid::new_v4(),
+            "owner_id": owner,
+            "is_public": false,
+        }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "validation_failed");
+    assert_eq!(
+        body["errors"],
+        json!([
+            { "field": "name", "code": "blank", "message": "must not be blank" },
+            {
+                "field": "slug",
+                "code": "slug",
+                "message": "must be lowercase letters and digits separated by hyphens",
+            },
+        ])
+    );
+}
+
+#[actix_web::test]
+async fn unreadable_bodies_use_the_error_shape() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+    let owner = Uuid::new_v4().to_string();
+
+    let req = test::TestRequest::post()
+        .uri("/projects")
+        .insert_header(("X-User", owner.as_str()))
+        .insert_header(("Content-Type", "application/json"))
+        .set_payload("{\"name\": ")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "malformed_json");
+    assert!(body["request_id"].is_string());
+
+    let req = test::TestRequest::post()
+        .uri("/projects")
+        .insert_header(("X-User", owner.as_str()))
+        .set_json(json!({ "description": "no name" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "invalid_body");
+}
+
+#[actix_web::test]
+async fn missing_credentials_use_the_error_shape() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+
+    for uri in ["/projects", "/hooks/after-registration"] {
+        let req = test::TestRequest::post().uri(uri).set_json(json!({})).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{}", uri);
+        assert_eq!(
+            res.headers().get("content-type").unwrap(),
+            "application/problem+json",
+            "{}",
+            uri
+        );
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["code"], "unauthorized", "{}", uri);
+    }
+}
+
+#[actix_web::test]
+async fn projects_can_be_paged_by_cursor() {
+    let state = test_state().await;
+    let db = state.db.clone();
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let owner = Uuid::new_v4();
+    let organisation_id = create_organisation(&state, owner, "Cursors Ltd").await;
+    set_plan(&db, organisation_id, Plan::Team).await;
+
+    // The test clock gives every project the same created_at, so ties are broken by id
+    for i in 0..5 {
+        let req = test::TestRequest::post()
+            .uri("/projects")
+            .insert_header(("X-User", owner.to_string()))
+            .set_json(json!({
+                "name": format!("project {}", i),
+                "description": null,
+                "slug": format!("project-{}", i),
+                "organisation_id": organisation_id,
+                "owner_id": owner,
+                "is_public": false,
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+    }
+
+    let get = |uri: String| {
+        test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(("X-User", owner.to_string()))
+            .to_request()
+    };
+    let ids = |body: &Value| -> Vec<String> {
+        body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["id"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let body: Value = test::call_and_read_body_json(&app, get("/projects?per_page=5".into())).await;
+    let all = ids(&body);
+
+    let res = test::call_service(&app, get("/projects?per_page=2".into())).await;
+    let link = res.headers().get("Link").unwrap().to_str().unwrap().to_string();
+    assert_eq!(link, "</projects?per_page=2&page=2>; rel=\"next\"");
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["pagination"]["total"], 5);
+
+    // Following next_cursor walks the same order without gaps or repeats
+    let mut walked = ids(&body);
+    let mut cursor = body["pagination"]["next_cursor"].as_str().unwrap().to_string();
+    loop {
+        let res = test::call_service(&app, get(format!("/projects?per_page=2&after={}", cursor))).await;
+        let link = res.headers().get("Link").unwrap().to_str().unwrap().to_string();
+        assert!(link.contains("rel=\"prev\""), "{}", link);
+        let body: Value = test::read_body_json(res).await;
+        assert!(body["pagination"].get("total").is_none(), "cursor pages aren't counted");
+        walked.extend(ids(&body));
+        match body["pagination"]["next_cursor"].as_str() {
+            Some(next) => cursor = next.to_string(),
+            None => {
+                assert_eq!(body["pagination"]["has_next"], false);
+                let prev = body["pagination"]["prev_cursor"].as_str().unwrap();
+                let body: Value =
+                    test::call_and_read_body_json(&app, get(format!("/projects?per_page=2&before={}", prev))).await;
+                assert_eq!(ids(&body), all[2..4]);
+                break;
+            }
+        }
+    }
+    assert_eq!(walked, all);
+
+    let res = test::call_service(&app, get(format!("/projects?after={}&before={}", cursor, cursor))).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["errors"][0]["field"], "before");
+
+    let res = test::call_service(&app, get("/projects?after=not-a-cursor".into())).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["errors"][0]["code"], "cursor");
+}
+
+#[actix_web::test]
+async fn projects_can_be_filtered_sorted_and_searched() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let owner = Uuid::new_v4();
+    let organisation_id = create_organisation(&state, owner, "Listings plc").await;
+
+    let mut ids = Vec::new();
+    for (name, description) in [("beta", "Second"), ("alpha", "First of all"), ("gamma", "100% done")] {
+        let req = test::TestRequest::post()
+            .uri("/projects")
+            .insert_header(("X-User", owner.to_string()))
+            .set_json(json!({
+                "name": name,
+                "description": description,
+                "slug": name,
+                "organisation_id": organisation_id,
+                "owner_id": owner,
+                "is_public": false,
+            }))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+    let req = test::TestRequest::put()
+        .uri(&format!("/projects/{}", ids[2]))
+        .insert_header(("X-User", owner.to_string()))
+        .set_json(json!({ "is_archived": true }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let get = |uri: &str| {
+        test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("X-User", owner.to_string()))
+            .to_request()
+    };
+    let names = |body: &Value| -> Vec<String> {
+        body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let body: Value = test::call_and_read_body_json(&app, get("/projects?sort=name")).await;
+    assert_eq!(names(&body), ["alpha", "beta", "gamma"]);
+    assert!(body["pagination"].get("next_cursor").is_none());
+
+    let body: Value =
+        test::call_and_read_body_json(&app, get("/projects?filter[is_archived]=false&sort=-name")).await;
+    assert_eq!(names(&body), ["beta", "alpha"]);
+
+    let uri = format!("/projects?filter%5Bowner_id%5D={},{}", Uuid::new_v4(), owner);
+    let body: Value = test::call_and_read_body_json(&app, get(&uri)).await;
+    assert_eq!(body["pagination"]["total"], 3);
+
+    let body: Value = test::call_and_read_body_json(&app, get("/projects?q=FIRST")).await;
+    assert_eq!(names(&body), ["alpha"]);
+    let body: Value = test::call_and_read_body_json(&app, get("/projects?q=0%25")).await;
+    assert_eq!(names(&body), ["gamma"], "% is matched literally");
+
+    let res = test::call_service(&app, get("/projects?sort=name&per_page=1&q=a")).await;
+    let link = res.headers().get("Link").unwrap().to_str().unwrap();
+    assert_eq!(link, "</projects?sort=name&per_page=1&q=a&page=2>; rel=\"next\"");
+
+    let res = test::call_service(&app, get("/projects?filter[secret]=x&filter[is_archived]=maybe&sort=owner_id")).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(res).await;
+    let fields: Vec<(&str, &str)> = body["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| (e["field"].as_str().unwrap(), e["code"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            ("filter[secret]", "unknown_field"),
+            ("filter[is_archived]", "invalid_value"),
+            ("sort", "unknown_field"),
+        ]
+    );
+
+    let body: Value = test::call_and_read_body_json(&app, get("/projects?per_page=1")).await;
+    let cursor = body["pagination"]["next_cursor"].as_str().unwrap();
+    let res = test::call_service(&app, get(&format!("/projects?sort=name&after={}", cursor))).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[actix_web::test]
+async fn members_are_filtered_by_role_and_activity() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let owner = Uuid::new_v4();
+    let organisation_id = create_organisation(&state, owner, "Members Ltd").await;
+
+    let mut members = Vec::new();
+    for (hours, role, is_active) in [
+        (2, OrganisationRole::Admin, true),
+        (1, OrganisationRole::Viewer, true),
+        (3, OrganisationRole::Member, false),
+    ] {
+        let identity_id = Uuid::new_v4();
+        OrganisationMemberActiveModel {
+            id: Set(Uuid::new_v4()),
+            organisation_id: Set(organisation_id),
+            identity_id: Set(identity_id),
+            role: Set(role),
+            is_active: Set(is_active),
+            joined_at: Set(fixed_time() + chrono::TimeDelta::hours(hours)),
+            invited_by: Set(owner),
+            invited_at: Set(fixed_time()),
+            invitation_accepted_at: Set(fixed_time()),
+        }
+        .insert(&state.db)
+        .await
+        .unwrap();
+        members.push(identity_id.to_string());
+    }
+    let [admin, viewer, former] = &members[..] else { unreachable!() };
+
+    let get = |query: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/organisations/{}/members?{}", organisation_id, query))
+            .insert_header(("X-User", owner.to_string()))
+            .to_request()
+    };
+    let ids = |body: &Value| -> Vec<String> {
+        body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["identity_id"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let body: Value = test::call_and_read_body_json(&app, get("")).await;
+    assert_eq!(body["pagination"]["total"], 3, "former members are left out");
+
+    let body: Value = test::call_and_read_body_json(&app, get("filter[role]=Admin,Viewer&sort=-created_at")).await;
+    assert_eq!(ids(&body), [admin.as_str(), viewer.as_str()]);
+
+    let body: Value = test::call_and_read_body_json(&app, get("filter[is_active]=false")).await;
+    assert_eq!(ids(&body), [former.as_str()]);
+
+    let res = test::call_service(&app, get("filter[role]=admin&filter[identity_id]=x&sort=role&q=a")).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(res).await;
+    let fields: Vec<(&str, &str)> = body["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| (e["field"].as_str().unwrap(), e["code"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            ("filter[role]", "invalid_value"),
+            ("filter[identity_id]", "unknown_field"),
+            ("sort", "unknown_field"),
+            ("q", "unknown_field"),
+        ]
+    );
+}
@@ -0,0 +1,302 @@
+#![cfg(feature = "cloud")]
+
+mod common;
+
+use std::sync::Arc;
+
+use actix_web::http::StatusCode;
+use actix_web::{ResponseError, test, web};
+use c_plane::create_app;
+use c_plane::models::entities::{InvoiceModel, InvoiceStatus};
+use c_plane::services::billing::{
+    CHARGE_CLAIM_TIMEOUT, charge_invoice, invoice_period, previous_month,
+};
+use c_plane::state::State;
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use sea_orm::{ActiveModelTrait, IntoActiveModel, Set};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use common::{FakePayments, METERING_API_KEY, fixed_time, test_state};
+
+const OWNER: Uuid = Uuid::from_u128(0x0b11_1ee5);
+
+fn create_organisation() -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/organisations/")
+        .insert_header(("X-User", OWNER.to_string()))
+        .set_json(json!({ "name": "Metered", "description": null, "avatar_url": null }))
+}
+
+fn create_project(organisation_id: Uuid) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/projects")
+        .insert_header(("X-User", OWNER.to_string()))
+        .set_json(json!({
+            "name": "Metered",
+            "description": null,
+            "slug": "metered",
+            "organisation_id": organisation_id,
+            "owner_id": OWNER,
+            "is_public": false,
+        }))
+}
+
+fn id(body: &Value) -> Uuid {
+    body["id"].as_str().unwrap().parse().unwrap()
+}
+
+fn january() -> (DateTime<Utc>, DateTime<Utc>) {
+    (
+        Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap(),
+        Utc.with_ymd_and_hms(2026, 2, 1, 0, 0, 0).unwrap(),
+    )
+}
+
+fn report(key: &str, records: Value) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/metering/usage")
+        .insert_header(("X-API-KEY", key))
+        .set_json(json!({ "records": records }))
+}
+
+fn record(id: Uuid, project_id: Uuid, metric: &str, quantity: i64, recorded_at: &str) -> Value {
+    json!({
+        "id": id,
+        "project_id": project_id,
+        "deployment_id": Uuid::nil(),
+        "metric": metric,
+        "quantity": quantity,
+        "recorded_at": recorded_at,
+    })
+}
+
+/// A fake payment provider in place of the logging one, returned for the test to steer
+fn with_payments(state: &mut State) -> Arc<FakePayments> {
+    let payments = Arc::new(FakePayments::default());
+    state.payments = payments.clone();
+    payments
+}
+
+/// January's invoice of the organisation, built from the usage reported so far
+async fn invoice_january(state: &State, organisation_id: Uuid) -> Option<InvoiceModel> {
+    let (start, end) = january();
+    invoice_period(&state.db, state.clock.as_ref(), &state.config.billing.prices, start, end)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|invoice| invoice.organisation_id == Some(organisation_id))
+}
+
+#[actix_web::test]
+async fn reported_usage_is_invoiced_once_per_month() {
+    let mut state = test_state().await;
+    let payments = with_payments(&mut state);
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let body: Value = test::call_and_read_body_json(&app, create_organisation().to_request()).await;
+    let organisation_id = id(&body["organisation"]);
+    let body: Value = test::call_and_read_body_json(&app, create_project(organisation_id).to_request()).await;
+    let project_id = id(&body);
+
+    let records = json!([
+        record(Uuid::new_v4(), project_id, "runtime_seconds", 7200, "2026-01-10T00:00:00Z"),
+        record(Uuid::new_v4(), project_id, "cpu_seconds", 5400, "2026-01-20T00:00:00Z"),
+        record(Uuid::new_v4(), project_id, "storage_gb_hours", 1000, "2026-01-31T23:59:59Z"),
+        record(Uuid::new_v4(), project_id, "runtime_seconds", 3600, "2026-02-01T00:00:00Z"),
+    ]);
+    let res = test::call_service(&app, report("wrong", records.clone()).to_request()).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let res = test::call_service(&app, report(METERING_API_KEY, records.clone()).to_request()).await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["accepted"], 4);
+    let body: Value =
+        test::call_and_read_body_json(&app, report(METERING_API_KEY, records).to_request()).await;
+    assert_eq!(body["accepted"], 0, "resent records are only counted once");
+
+    let invoice = invoice_january(&state, organisation_id).await.expect("January is invoiced");
+    assert!(invoice_january(&state, organisation_id).await.is_none(), "invoiced only once");
+    // 2 runtime hours at 1 cent, 1.5 CPU hours at 4 cents and 1000/730 GB-months at
+    // 10 cents, each rounded up; February's hour waits for the next invoice
+    assert_eq!(invoice.total_cents, 2 + 6 + 14);
+    assert_eq!(invoice.status, InvoiceStatus::Open);
+
+    let invoice = charge_invoice(&state.db, state.clock.as_ref(), state.payments.as_ref(), invoice)
+        .await
+        .unwrap();
+    assert_eq!(invoice.status, InvoiceStatus::Paid);
+    assert_eq!(invoice.payment_reference, Some(format!("fake-{}", invoice.id)));
+    assert_eq!(payments.charges.lock().unwrap().len(), 1);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/billing/{}/invoices", organisation_id))
+        .insert_header(("X-User", OWNER.to_string()))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["data"][0]["status"], "paid");
+    assert_eq!(body["data"][0]["currency"], "eur");
+    assert_eq!(
+        body["data"][0]["lines"],
+        json!([
+            { "metric": "cpu_seconds", "quantity": 5400, "unit_price_cents": 4, "amount_cents": 6 },
+            { "metric": "runtime_seconds", "quantity": 7200, "unit_price_cents": 1, "amount_cents": 2 },
+            { "metric": "storage_gb_hours", "quantity": 1000, "unit_price_cents": 10, "amount_cents": 14 },
+        ])
+    );
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/billing/{}/invoices", organisation_id))
+        .insert_header(("X-User", Uuid::new_v4().to_string()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn failed_payments_suspend_new_projects_until_the_invoice_is_paid() {
+    let mut state = test_state().await;
+    let payments = with_payments(&mut state);
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let body: Value = test::call_and_read_body_json(&app, create_organisation().to_request()).await;
+    let organisation_id = id(&body["organisation"]);
+    let body: Value = test::call_and_read_body_json(&app, create_project(organisation_id).to_request()).await;
+    let project_id = id(&body);
+    let body: Value = test::call_and_read_body_json(&app, create_project(organisation_id).to_request()).await;
+    let deleted_id = id(&body);
+
+    let records = json!([record(Uuid::new_v4(), project_id, "runtime_seconds", 3600, "2026-01-15T12:00:00Z")]);
+    let res = test::call_service(&app, report(METERING_API_KEY, records).to_request()).await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let invoice = invoice_january(&state, organisation_id).await.unwrap();
+
+    let archive = |is_archived: bool| {
+        test::TestRequest::put()
+            .uri(&format!("/projects/{}", project_id))
+            .insert_header(("X-User", OWNER.to_string()))
+            .set_json(json!({ "is_archived": is_archived }))
+            .to_request()
+    };
+    let restore = || {
+        test::TestRequest::post()
+            .uri(&format!("/projects/{}/restore", deleted_id))
+            .insert_header(("X-User", OWNER.to_string()))
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, archive(true)).await.status(), StatusCode::OK);
+    let req = test::TestRequest::delete()
+        .uri(&format!("/projects/{}", deleted_id))
+        .insert_header(("X-User", OWNER.to_string()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+
+    payments.decline(true);
+    let err = charge_invoice(&state.db, state.clock.as_ref(), state.payments.as_ref(), invoice.clone())
+        .await
+        .unwrap_err();
+    assert_eq!(err.status_code(), StatusCode::BAD_GATEWAY);
+
+    for req in [create_project(organisation_id).to_request(), archive(false), restore()] {
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PAYMENT_REQUIRED);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["code"], "subscription_suspended");
+    }
+
+    let subscription = || {
+        test::TestRequest::get()
+            .uri(&format!("/billing/{}/subscription", organisation_id))
+            .insert_header(("X-User", OWNER.to_string()))
+            .to_request()
+    };
+    let body: Value = test::call_and_read_body_json(&app, subscription()).await;
+    assert_eq!(body["status"], "suspended");
+    assert!(body["suspended_at"].is_string());
+
+    let pay = || {
+        test::TestRequest::post()
+            .uri(&format!("/billing/{}/invoices/{}/pay", organisation_id, invoice.id))
+            .insert_header(("X-User", OWNER.to_string()))
+            .to_request()
+    };
+    let res = test::call_service(&app, pay()).await;
+    assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+
+    payments.decline(false);
+    let body: Value = test::call_and_read_body_json(&app, pay()).await;
+    assert_eq!(body["status"], "paid");
+    assert_eq!(body["failure_reason"], Value::Null);
+    let body: Value = test::call_and_read_body_json(&app, subscription()).await;
+    assert_eq!(body, json!({ "status": "active", "suspended_at": null }));
+
+    let res = test::call_service(&app, create_project(organisation_id).to_request()).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(test::call_service(&app, archive(false)).await.status(), StatusCode::OK);
+    assert_eq!(test::call_service(&app, restore()).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn an_invoice_is_charged_by_one_caller_at_a_time() {
+    let mut state = test_state().await;
+    let payments = with_payments(&mut state);
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let body: Value = test::call_and_read_body_json(&app, create_organisation().to_request()).await;
+    let organisation_id = id(&body["organisation"]);
+    let body: Value = test::call_and_read_body_json(&app, create_project(organisation_id).to_request()).await;
+    let records = json!([record(Uuid::new_v4(), id(&body), "runtime_seconds", 3600, "2026-01-15T12:00:00Z")]);
+    let res = test::call_service(&app, report(METERING_API_KEY, records).to_request()).await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let invoice = invoice_january(&state, organisation_id).await.unwrap();
+
+    // As another caller leaves it while its charge is in flight
+    let claim = |claimed_at: DateTime<Utc>| {
+        let mut claimed = invoice.clone().into_active_model();
+        claimed.status = Set(InvoiceStatus::Processing);
+        claimed.updated_at = Set(claimed_at);
+        claimed.update(&state.db)
+    };
+    let charge = || charge_invoice(&state.db, state.clock.as_ref(), state.payments.as_ref(), invoice.clone());
+
+    claim(fixed_time()).await.unwrap();
+    let err = charge().await.unwrap_err();
+    assert_eq!(err.status_code(), StatusCode::CONFLICT);
+    assert!(payments.charges.lock().unwrap().is_empty());
+
+    claim(fixed_time() - CHARGE_CLAIM_TIMEOUT - TimeDelta::seconds(1)).await.unwrap();
+    let paid = charge().await.expect("abandoned charges are taken over");
+    assert_eq!(paid.status, InvoiceStatus::Paid);
+    {
+        let charges = payments.charges.lock().unwrap();
+        assert_eq!(charges.len(), 1);
+        assert_eq!(charges[0].idempotency_key, invoice.id);
+    }
+
+    let again = charge().await.unwrap();
+    assert_eq!(again.status, InvoiceStatus::Paid);
+    assert_eq!(payments.charges.lock().unwrap().len(), 1, "paid invoices aren't charged again");
+}
+
+#[actix_web::test]
+async fn usage_reports_are_validated() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+
+    let res = test::call_service(&app, report(METERING_API_KEY, json!([])).to_request()).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let records = json!([record(Uuid::new_v4(), Uuid::new_v4(), "cpu_seconds", -1, "2026-01-01T00:00:00Z")]);
+    let res = test::call_service(&app, report(METERING_API_KEY, records).to_request()).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["errors"][0]["field"], "records[0].quantity");
+
+    let records = json!([record(Uuid::new_v4(), Uuid::new_v4(), "cpu_seconds", 1, "2026-01-01T00:00:00Z")]);
+    let res = test::call_service(&app, report(METERING_API_KEY, records).to_request()).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn invoices_cover_the_previous_calendar_month() {
+    let at = |y, m, d| Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap();
+    assert_eq!(previous_month(Utc.with_ymd_and_hms(2026, 3, 1, 4, 0, 0).unwrap()), (at(2026, 2, 1), at(2026, 3, 1)));
+    assert_eq!(previous_month(at(2026, 1, 31)), (at(2025, 12, 1), at(2026, 1, 1)));
+}
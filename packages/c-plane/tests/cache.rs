@@ -0,0 +1,237 @@
+mod common;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use actix_web::{test, web};
+use async_trait::async_trait;
+use c_plane::config::ValkeyConfig;
+use c_plane::create_app;
+use c_plane::errors::AppError;
+use c_plane::models::entities::{OrganisationMemberActiveModel, OrganisationRole};
+use c_plane::services::cache::{Cache, MemoryCache, ValkeyCache, cached};
+use c_plane::services::organisations::{
+    CreateOrganisationData, cached_member, create_organisation, delete_organisation,
+};
+use c_plane::services::valkey::Valkey;
+use c_plane::state::State;
+use c_plane::utils::metrics::metrics;
+use sea_orm::{ActiveModelTrait, IntoActiveModel, Set};
+use serde_json::Value;
+use uuid::Uuid;
+
+use common::{FakeKratos, fixed_time, test_state};
+
+const TTL: Duration = Duration::from_secs(60);
+
+fn valkey(url: &str) -> Arc<Valkey> {
+    let config = ValkeyConfig {
+        url: url.to_string(),
+        timeout_ms: 500,
+    };
+    Arc::new(Valkey::new(&config).unwrap())
+}
+
+#[actix_web::test]
+async fn memberships_are_cached_until_the_organisation_is_deleted() {
+    let state = test_state().await;
+    let owner = Uuid::new_v4();
+    let (organisation, member) = create_organisation(
+        &state.db,
+        state.clock.as_ref(),
+        CreateOrganisationData {
+            name: "Cached".to_string(),
+            description: None,
+            avatar_url: None,
+            identity_id: owner,
+        },
+    )
+    .await
+    .unwrap();
+    let lookup = || cached_member(&state.db, state.cache.as_ref(), TTL, organisation.id, owner);
+
+    assert_eq!(lookup().await.unwrap(), member);
+
+    // Only visible through the database until the entry goes
+    let mut inactive = member.clone().into_active_model();
+    inactive.is_active = Set(false);
+    inactive.update(&state.db).await.unwrap();
+    assert_eq!(lookup().await.unwrap(), member, "served from the cache");
+    member.clone().into_active_model().reset_all().update(&state.db).await.unwrap();
+
+    delete_organisation(&state.db, state.clock.as_ref(), state.cache.as_ref(), TTL, owner, organisation.id)
+        .await
+        .unwrap();
+    assert!(lookup().await.is_err(), "deleting the organisation drops its memberships");
+
+    let stranger = cached_member(&state.db, state.cache.as_ref(), TTL, organisation.id, Uuid::new_v4());
+    assert!(stranger.await.is_err());
+}
+
+/// Deletes the organisation right before the first membership entry is stored, as if
+/// the deletion landed between a lookup's database read and its cache write
+struct RacingCache {
+    inner: MemoryCache,
+    state: State,
+    organisation_id: Uuid,
+    owner: Uuid,
+    raced: AtomicBool,
+}
+
+#[async_trait]
+impl Cache for RacingCache {
+    async fn get(&self, key: &str) -> Result<Option<String>, AppError> {
+        self.inner.get(key).await
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), AppError> {
+        if key.starts_with("membership:") && !self.raced.swap(true, Ordering::SeqCst) {
+            let state = &self.state;
+            delete_organisation(&state.db, state.clock.as_ref(), &self.inner, TTL, self.owner, self.organisation_id)
+                .await
+                .unwrap();
+        }
+        self.inner.set(key, value, ttl).await
+    }
+
+    async fn delete(&self, keys: &[String]) -> Result<(), AppError> {
+        self.inner.delete(keys).await
+    }
+}
+
+#[actix_web::test]
+async fn memberships_read_before_a_deletion_are_never_served_after_it() {
+    let state = test_state().await;
+    let owner = Uuid::new_v4();
+    let organisation_id = common::create_organisation(&state, owner, "Racing").await;
+    let cache = RacingCache {
+        inner: MemoryCache::default(),
+        state: state.clone(),
+        organisation_id,
+        owner,
+        raced: AtomicBool::new(false),
+    };
+
+    assert!(cached_member(&state.db, &cache, TTL, organisation_id, owner).await.is_ok());
+    let again = cached_member(&state.db, &cache, TTL, organisation_id, owner).await;
+    assert!(again.is_err(), "the entry stored after the deletion is left unread");
+}
+
+#[actix_web::test]
+async fn member_identities_are_served_from_the_cache_while_kratos_is_down() {
+    let state = test_state().await;
+    let owner = Uuid::new_v4();
+    let organisation_id = common::create_organisation(&state, owner, "Members").await;
+    let list = || {
+        test::TestRequest::get()
+            .uri(&format!("/organisations/{}/members", organisation_id))
+            .insert_header(("X-User", owner.to_string()))
+            .to_request()
+    };
+    let member = |body: &Value, identity_id: Uuid| {
+        body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|member| member["identity_id"] == identity_id.to_string())
+            .cloned()
+            .unwrap()
+    };
+
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let body: Value = test::call_and_read_body_json(&app, list()).await;
+    assert_eq!(body["pagination"]["total"], 1);
+    let listed = member(&body, owner);
+    assert_eq!(listed["role"], "Owner");
+    assert_eq!(listed["name"], "Ada Lovelace");
+    assert_eq!(listed["email"], "ada@example.com");
+
+    let joined = Uuid::new_v4();
+    OrganisationMemberActiveModel {
+        id: Set(Uuid::new_v4()),
+        organisation_id: Set(organisation_id),
+        identity_id: Set(joined),
+        role: Set(OrganisationRole::Member),
+        is_active: Set(true),
+        joined_at: Set(fixed_time()),
+        invited_by: Set(owner),
+        invited_at: Set(fixed_time()),
+        invitation_accepted_at: Set(fixed_time()),
+    }
+    .insert(&state.db)
+    .await
+    .unwrap();
+
+    let mut down = state.clone();
+    down.kratos = Arc::new(FakeKratos { healthy: false });
+    let app = test::init_service(create_app(web::Data::new(down))).await;
+    let body: Value = test::call_and_read_body_json(&app, list()).await;
+    assert_eq!(member(&body, owner)["name"], "Ada Lovelace", "served from the cache");
+    let listed = member(&body, joined);
+    assert_eq!(listed["role"], "Member");
+    assert_eq!(listed["name"], Value::Null, "listed without what Kratos knows");
+
+    let rendered = metrics().render(&state.db).await.unwrap();
+    for outcome in ["hit", "miss"] {
+        let series = format!("cplane_cache_requests_total{{cache=\"identity\",outcome=\"{}\"}}", outcome);
+        assert!(rendered.contains(&series), "{} is missing", series);
+    }
+}
+
+#[actix_web::test]
+async fn entries_expire_and_a_zero_ttl_bypasses_the_cache() {
+    let cache = MemoryCache::default();
+    cache.set("short", "1", Duration::from_millis(200)).await.unwrap();
+    assert_eq!(cache.get("short").await.unwrap().as_deref(), Some("1"));
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(cache.get("short").await.unwrap(), None);
+
+    let value: u32 = cached(&cache, "test", "uncached", Duration::ZERO, || async { Ok(7) })
+        .await
+        .unwrap();
+    assert_eq!(value, 7);
+    assert_eq!(cache.get("uncached").await.unwrap(), None);
+}
+
+#[actix_web::test]
+async fn the_least_recently_used_entry_makes_room() {
+    let cache = MemoryCache::default();
+    let ttl = Duration::from_secs(60);
+    for i in 0..10_000 {
+        cache.set(&i.to_string(), "1", ttl).await.unwrap();
+    }
+    assert!(cache.get("0").await.unwrap().is_some());
+
+    cache.set("new", "1", ttl).await.unwrap();
+    assert!(cache.get("0").await.unwrap().is_some(), "read recently");
+    assert_eq!(cache.get("1").await.unwrap(), None, "pushed out");
+    assert!(cache.get("new").await.unwrap().is_some());
+}
+
+#[actix_web::test]
+async fn unreachable_valkey_loads_every_time() {
+    // Nothing listens on the discard port
+    let cache = ValkeyCache::new(valkey("redis://127.0.0.1:9/0"));
+    for expected in [1, 2] {
+        let value: u32 = cached(&cache, "test", "unreachable", TTL, || async move { Ok(expected) })
+            .await
+            .unwrap();
+        assert_eq!(value, expected);
+    }
+}
+
+/// Runs against the Valkey or Redis in `TEST_VALKEY_URL`, skipped without one
+#[actix_web::test]
+async fn instances_share_entries_through_valkey() {
+    let Ok(url) = std::env::var("TEST_VALKEY_URL") else {
+        return;
+    };
+    let (first, second) = (ValkeyCache::new(valkey(&url)), ValkeyCache::new(valkey(&url)));
+    let key = format!("test:{}", Uuid::new_v4());
+
+    first.set(&key, "shared", TTL).await.unwrap();
+    assert_eq!(second.get(&key).await.unwrap().as_deref(), Some("shared"));
+    second.delete(std::slice::from_ref(&key)).await.unwrap();
+    assert_eq!(first.get(&key).await.unwrap(), None);
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use actix_web::dev::{Server, ServerHandle};
+use actix_web::{App, HttpResponse, HttpServer, web};
+use c_plane::create_app;
+use c_plane::state::State;
+use c_plane_client::types::{
+    CreateOrganisationRequest, CreateProjectRequest, ProjectResponse, UpdateProjectRequest,
+};
+use c_plane_client::{Auth, Client, ClientError, RetryPolicy};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use uuid::Uuid;
+
+use common::{create_organisation, test_state};
+
+/// A listener on a random local port and the base URL to reach it
+fn listen() -> (TcpListener, String) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    (listener, url)
+}
+
+fn spawn(server: Server) -> ServerHandle {
+    let handle = server.handle();
+    actix_web::rt::spawn(server);
+    handle
+}
+
+/// Serves the real app, returning its base URL
+fn serve(state: State) -> (String, ServerHandle) {
+    let (listener, url) = listen();
+    let state = web::Data::new(state);
+    let server = HttpServer::new(move || create_app(state.clone()))
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+    (url, spawn(server))
+}
+
+fn fast_retries() -> RetryPolicy {
+    RetryPolicy {
+        max_retries: 2,
+        base_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(50),
+    }
+}
+
+#[actix_web::test]
+async fn manages_projects_end_to_end() {
+    let state = test_state().await;
+    let owner = Uuid::new_v4();
+    let organisation_id = create_organisation(&state, owner, "Client Co").await;
+    let (url, server) = serve(state);
+    let client = Client::new(&url).with_auth(Auth::Identity(owner));
+
+    let organisation = client.get_organisation(organisation_id).await.unwrap();
+    assert_eq!(organisation.created_by, owner);
+
+    for name in ["one", "two", "three"] {
+        client
+            .create_project(&CreateProjectRequest {
+                name: name.to_string(),
+                description: Some(format!("project {}", name)),
+                slug: name.to_string(),
+                organisation_id,
+                owner_id: owner,
+                is_public: false,
+            })
+            .await
+            .unwrap();
+    }
+
+    let projects: Vec<ProjectResponse> = client.projects(2).try_collect().await.unwrap();
+    let mut names: Vec<&str> = projects.iter().map(|p| p.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, ["one", "three", "two"], "all pages are walked");
+
+    let project = &projects[0];
+    let updated = client
+        .update_project(
+            project.id,
+            &UpdateProjectRequest {
+                description: Some(None),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.name, project.name, "omitted fields are kept");
+    assert_eq!(updated.description, None, "null clears the description");
+
+    client.delete_project(project.id).await.unwrap();
+    let err = client.get_project(project.id).await.unwrap_err();
+    match err {
+        ClientError::Api { status, error } => {
+            assert_eq!(status, 404);
+            assert_eq!(error.code, "project_not_found");
+            assert!(error.request_id.is_some());
+        }
+        other => panic!("expected an API error, got {:?}", other),
+    }
+
+    server.stop(true).await;
+}
+
+#[actix_web::test]
+async fn unauthenticated_requests_fail_with_their_status() {
+    let (url, server) = serve(test_state().await);
+    let client = Client::new(&url);
+
+    let err = client.list_projects(1, 10).await.unwrap_err();
+    assert_eq!(err.status(), Some(401));
+
+    server.stop(true).await;
+}
+
+#[actix_web::test]
+async fn retries_idempotent_requests_only() {
+    let calls = Arc::new(AtomicU32::new(0));
+    let counter = calls.clone();
+    // Unavailable for the first two calls of any kind, then answers normally
+    let (listener, url) = listen();
+    let server = HttpServer::new(move || {
+        let calls = counter.clone();
+        App::new()
+            .app_data(web::Data::new(calls))
+            .default_service(web::to(|calls: web::Data<Arc<AtomicU32>>| async move {
+                if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                    return HttpResponse::ServiceUnavailable().finish();
+                }
+                HttpResponse::Ok().json(ProjectResponse {
+                    id: Uuid::nil(),
+                    name: "flaky".to_string(),
+                    description: None,
+                    organisation_id: Uuid::nil(),
+                    owner_id: Uuid::nil(),
+                    is_archived: false,
+                    is_public: false,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                })
+            }))
+    })
+    .workers(1)
+    .listen(listener)
+    .unwrap()
+    .run();
+    let server = spawn(server);
+    let client = Client::new(&url)
+        .with_auth(Auth::Jwt("token".to_string()))
+        .with_retry_policy(fast_retries());
+
+    let project = client.get_project(Uuid::nil()).await.unwrap();
+    assert_eq!(project.name, "flaky");
+    assert_eq!(calls.load(Ordering::SeqCst), 3, "two retries before succeeding");
+
+    calls.store(0, Ordering::SeqCst);
+    let err = client
+        .create_organisation(&CreateOrganisationRequest {
+            name: "never retried".to_string(),
+            description: None,
+            avatar_url: None,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(503));
+    assert_eq!(calls.load(Ordering::SeqCst), 1, "creating requests are sent once");
+
+    server.stop(true).await;
+}
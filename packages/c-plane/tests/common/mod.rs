@@ -0,0 +1,263 @@
+#![allow(dead_code)]
+
+#[cfg(feature = "cloud")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use c_plane::config::{
+    AuthConfig, CacheConfig, Config, DatabaseConfig, FeaturesConfig, JobsConfig,
+    RateLimitConfig, RetentionConfig, ServerConfig, TelemetryConfig,
+};
+#[cfg(feature = "cloud")]
+use c_plane::config::{BillingConfig, Prices};
+#[cfg(not(feature = "cloud"))]
+use c_plane::config::InstanceConfig;
+use c_plane::errors::{AppError, ExternalError};
+use c_plane::models::entities::{Organisation, OrganisationActiveModel, Plan};
+#[cfg(feature = "cloud")]
+use c_plane::services::billing::{Charge, PaymentProvider};
+use c_plane::services::kratos::{IdentityDetails, KratosClient};
+use c_plane::services::mailer::{Email, Mailer};
+use c_plane::services::organisations::CreateOrganisationData;
+use c_plane::services::cache::MemoryCache;
+use c_plane::services::rate_limit::MemoryStore;
+use c_plane::state::{self, State};
+use c_plane::utils::clock::Clock;
+use c_plane::utils::logger::LogFormat;
+use chrono::{DateTime, TimeZone, Utc};
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set};
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+pub const API_KEY: &str = "test-api-key";
+pub const METERING_API_KEY: &str = "test-metering-key";
+pub const INSTANCE_ORGANISATION_ID: Uuid = Uuid::from_u128(0x1257_a7ce);
+pub const INSTANCE_OWNER_EMAIL: &str = "owner@instance.test";
+
+/// Kratos stand-in reporting a fixed version and the same details for every
+/// identity, or failing when `healthy` is false
+pub struct FakeKratos {
+    pub healthy: bool,
+}
+
+#[async_trait]
+impl KratosClient for FakeKratos {
+    async fn admin_version(&self) -> Result<String, AppError> {
+        if self.healthy {
+            Ok("v-test".to_string())
+        } else {
+            Err(AppError::External(ExternalError::OryApiError(
+                "connection refused".to_string(),
+            )))
+        }
+    }
+
+    async fn identity(&self, identity_id: Uuid) -> Result<IdentityDetails, AppError> {
+        if !self.healthy {
+            return Err(AppError::External(ExternalError::OryApiError(
+                "connection refused".to_string(),
+            )));
+        }
+        Ok(IdentityDetails {
+            id: identity_id,
+            email: Some("ada@example.com".to_string()),
+            name: Some("Ada Lovelace".to_string()),
+        })
+    }
+}
+
+/// Keeps every email instead of sending it
+#[derive(Default)]
+pub struct RecordingMailer {
+    pub sent: Mutex<Vec<Email>>,
+}
+
+#[async_trait]
+impl Mailer for RecordingMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        self.sent.lock().unwrap().push(email);
+        Ok(())
+    }
+}
+
+/// Keeps every charge, declining them while `decline` is set
+#[cfg(feature = "cloud")]
+#[derive(Default)]
+pub struct FakePayments {
+    pub charges: Mutex<Vec<Charge>>,
+    pub decline: AtomicBool,
+}
+
+#[cfg(feature = "cloud")]
+impl FakePayments {
+    pub fn decline(&self, decline: bool) {
+        self.decline.store(decline, Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "cloud")]
+#[async_trait]
+impl PaymentProvider for FakePayments {
+    async fn charge(&self, charge: Charge) -> Result<String, AppError> {
+        if self.decline.load(Ordering::SeqCst) {
+            return Err(AppError::External(ExternalError::PaymentProviderError(
+                "card declined".to_string(),
+            )));
+        }
+        let reference = format!("fake-{}", charge.invoice_id);
+        self.charges.lock().unwrap().push(charge);
+        Ok(reference)
+    }
+}
+
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+pub fn fixed_time() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap()
+}
+
+pub fn config(database_url: &str) -> Config {
+    Config {
+        server: ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            cors_allowed_origins: Vec::new(),
+            request_timeout_secs: 30,
+            shutdown_timeout_secs: 1,
+        },
+        database: DatabaseConfig {
+            url: database_url.to_string(),
+            max_connections: 5,
+            min_connections: 1,
+            connect_timeout_secs: 5,
+            acquire_timeout_secs: 5,
+            idle_timeout_secs: 60,
+            statement_timeout_ms: 10_000,
+            slow_statement_threshold_ms: 0,
+            log_statements: false,
+        },
+        auth: AuthConfig {
+            kratos_api_key: API_KEY.parse().unwrap(),
+            kratos_admin_url: "http://kratos.invalid".to_string(),
+            jwks_url: None,
+        },
+        smtp: None,
+        valkey: None,
+        jobs: JobsConfig { workers: 1 },
+        rate_limit: RateLimitConfig {
+            requests_per_minute: 600,
+            expensive_requests_per_minute: 60,
+            expensive_paths: vec!["/search".to_string()],
+            trust_forwarded_for: false,
+        },
+        cache: CacheConfig {
+            membership_ttl_secs: 60,
+            identity_ttl_secs: 300,
+        },
+        retention: RetentionConfig { deleted_days: 30 },
+        telemetry: TelemetryConfig {
+            log_format: LogFormat::Pretty,
+            otlp_endpoint: None,
+            service_name: "c-plane-test".to_string(),
+            metrics_token: None,
+        },
+        features: FeaturesConfig {
+            metrics_endpoint: true,
+            background_jobs: false,
+            rate_limiting: true,
+        },
+        #[cfg(feature = "cloud")]
+        billing: BillingConfig {
+            metering_api_key: METERING_API_KEY.parse().unwrap(),
+            prices: Prices {
+                currency: "eur".to_string(),
+                runtime_hour_cents: 1,
+                cpu_hour_cents: 4,
+                storage_gb_month_cents: 10,
+            },
+        },
+        #[cfg(not(feature = "cloud"))]
+        instance: InstanceConfig {
+            organisation_id: INSTANCE_ORGANISATION_ID,
+            organisation_name: "Instance".to_string(),
+            owner_email: INSTANCE_OWNER_EMAIL.to_string(),
+        },
+    }
+}
+
+static MIGRATED: OnceCell<()> = OnceCell::const_new();
+
+/// State with fakes for Kratos, email and the clock. Runs against the database in
+/// `TEST_DATABASE_URL`, migrated once per test binary, or otherwise against a fresh
+/// in-memory SQLite database per test, which goes away with its connection pool.
+pub async fn test_state() -> State {
+    let (database_url, shared) = match std::env::var("TEST_DATABASE_URL") {
+        Ok(url) => (url, true),
+        // Every pool gets a database of its own, shared by its connections
+        Err(_) => ("sqlite::memory:".to_string(), false),
+    };
+
+    let config = config(&database_url);
+    let db = state::connect(&config.database)
+        .await
+        .expect("test database is reachable");
+    if shared {
+        MIGRATED
+            .get_or_init(|| async {
+                Migrator::up(&db, None).await.expect("migrations apply");
+            })
+            .await;
+    } else {
+        Migrator::up(&db, None).await.expect("migrations apply");
+    }
+
+    State {
+        db,
+        config,
+        kratos: Arc::new(FakeKratos { healthy: true }),
+        mailer: Arc::new(RecordingMailer::default()),
+        clock: Arc::new(FixedClock(fixed_time())),
+        rate_limits: Arc::new(MemoryStore::default()),
+        cache: Arc::new(MemoryCache::default()),
+        #[cfg(feature = "cloud")]
+        payments: Arc::new(FakePayments::default()),
+    }
+}
+
+/// Moves the organisation to `plan`, for tests needing more than the free plan allows
+pub async fn set_plan(db: &DatabaseConnection, organisation_id: Uuid, plan: Plan) {
+    let mut organisation: OrganisationActiveModel = Organisation::find_by_id(organisation_id)
+        .one(db)
+        .await
+        .unwrap()
+        .expect("organisation exists")
+        .into();
+    organisation.plan = Set(plan);
+    organisation.update(db).await.unwrap();
+}
+
+/// Creates an organisation owned by `owner` through the service, as the route for it
+/// only exists in the `cloud` build
+pub async fn create_organisation(state: &State, owner: Uuid, name: &str) -> Uuid {
+    let (organisation, _) = c_plane::services::organisations::create_organisation(
+        &state.db,
+        state.clock.as_ref(),
+        CreateOrganisationData {
+            name: name.to_string(),
+            description: None,
+            avatar_url: None,
+            identity_id: owner,
+        },
+    )
+    .await
+    .unwrap();
+    organisation.id
+}
@@ -0,0 +1,275 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::{ResponseError, test, web};
+use c_plane::create_app;
+use c_plane::errors::{
+    AppError, ConfigError, DatabaseError, ErrorResponse, ExternalError, FieldError, JobError,
+    OrganisationError, PROBLEM_JSON, ProjectError, UserError, ValidationError,
+};
+use c_plane::models::entities::{OrganisationActiveModel, Plan, ProjectActiveModel};
+use sea_orm::{ActiveModelTrait, Set};
+use uuid::Uuid;
+
+use common::{fixed_time, test_state};
+
+fn every_variant() -> Vec<(AppError, StatusCode, &'static str)> {
+    let id = Uuid::nil();
+    let text = || "text".to_string();
+    vec![
+        (ProjectError::InvalidSlug(text()).into(), StatusCode::UNPROCESSABLE_ENTITY, "invalid_slug"),
+        (ProjectError::SlugAlreadyExists(text()).into(), StatusCode::CONFLICT, "slug_already_exists"),
+        (ProjectError::ProjectNotFound(id).into(), StatusCode::NOT_FOUND, "project_not_found"),
+        (ProjectError::ProjectArchived(id).into(), StatusCode::CONFLICT, "project_archived"),
+        (
+            ProjectError::CannotArchivePublicProject.into(),
+            StatusCode::CONFLICT,
+            "cannot_archive_public_project",
+        ),
+        (
+            ProjectError::OwnerCannotLeaveProject.into(),
+            StatusCode::CONFLICT,
+            "owner_cannot_leave_project",
+        ),
+        (
+            ProjectError::ProjectLimitExceeded { current: 3, limit: 3 }.into(),
+            StatusCode::FORBIDDEN,
+            "project_limit_exceeded",
+        ),
+        (
+            AppError::Organisation(OrganisationError::OrganisationNotFound(id)),
+            StatusCode::NOT_FOUND,
+            "organisation_not_found",
+        ),
+        (
+            AppError::Organisation(OrganisationError::UserNotMember(id)),
+            StatusCode::FORBIDDEN,
+            "not_a_member",
+        ),
+        (
+            AppError::Organisation(OrganisationError::InsufficientRole {
+                required: "Admin".to_string(),
+                current: "Viewer".to_string(),
+            }),
+            StatusCode::FORBIDDEN,
+            "insufficient_role",
+        ),
+        (
+            AppError::Organisation(OrganisationError::CannotRemoveLastOwner),
+            StatusCode::CONFLICT,
+            "cannot_remove_last_owner",
+        ),
+        (
+            AppError::Organisation(OrganisationError::CannotDeleteInstanceOrganisation),
+            StatusCode::CONFLICT,
+            "cannot_delete_instance_organisation",
+        ),
+        (
+            AppError::Organisation(OrganisationError::MemberLimitExceeded { current: 5, limit: 5 }),
+            StatusCode::FORBIDDEN,
+            "member_limit_exceeded",
+        ),
+        (
+            AppError::Organisation(OrganisationError::SubscriptionSuspended(id)),
+            StatusCode::PAYMENT_REQUIRED,
+            "subscription_suspended",
+        ),
+        (AppError::User(UserError::UserNotFound(id)), StatusCode::NOT_FOUND, "user_not_found"),
+        (
+            AppError::User(UserError::EmailAlreadyExists(text())),
+            StatusCode::CONFLICT,
+            "email_already_exists",
+        ),
+        (AppError::User(UserError::AccountDeactivated), StatusCode::FORBIDDEN, "account_deactivated"),
+        (
+            AppError::User(UserError::InsufficientPermissions),
+            StatusCode::FORBIDDEN,
+            "insufficient_permissions",
+        ),
+        (
+            ValidationError::InvalidFields(vec![FieldError {
+                field: "name".to_string(),
+                code: "blank",
+                message: "must not be blank".to_string(),
+            }])
+            .into(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "validation_failed",
+        ),
+        (ValidationError::MalformedBody(text()).into(), StatusCode::BAD_REQUEST, "malformed_json"),
+        (ValidationError::InvalidBody(text()).into(), StatusCode::UNPROCESSABLE_ENTITY, "invalid_body"),
+        (
+            ValidationError::UnsupportedContentType.into(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "unsupported_media_type",
+        ),
+        (
+            ValidationError::BodyTooLarge { limit: 1 }.into(),
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "payload_too_large",
+        ),
+        (
+            AppError::Database(DatabaseError::ConnectionFailed(text())),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+        ),
+        (
+            AppError::Database(DatabaseError::QueryFailed(text())),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+        ),
+        (
+            AppError::Database(DatabaseError::TransactionFailed(text())),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+        ),
+        (
+            AppError::Database(DatabaseError::ConstraintViolation(text())),
+            StatusCode::CONFLICT,
+            "conflict",
+        ),
+        (AppError::Database(DatabaseError::Timeout), StatusCode::SERVICE_UNAVAILABLE, "timeout"),
+        (
+            AppError::External(ExternalError::OryApiError(text())),
+            StatusCode::BAD_GATEWAY,
+            "upstream_error",
+        ),
+        (
+            AppError::External(ExternalError::EmailServiceError(text())),
+            StatusCode::BAD_GATEWAY,
+            "upstream_error",
+        ),
+        (
+            AppError::External(ExternalError::PaymentProviderError(text())),
+            StatusCode::BAD_GATEWAY,
+            "upstream_error",
+        ),
+        (
+            AppError::External(ExternalError::ValkeyError(text())),
+            StatusCode::BAD_GATEWAY,
+            "upstream_error",
+        ),
+        (
+            AppError::External(ExternalError::NetworkTimeout),
+            StatusCode::GATEWAY_TIMEOUT,
+            "upstream_timeout",
+        ),
+        (
+            AppError::External(ExternalError::ServiceUnavailable(text())),
+            StatusCode::SERVICE_UNAVAILABLE,
+            "upstream_unavailable",
+        ),
+        (
+            JobError::ExecutionFailed(text()).into(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+        ),
+        (
+            ConfigError::InvalidArgument(text()).into(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "configuration_error",
+        ),
+        (AppError::Unauthorized(text()), StatusCode::UNAUTHORIZED, "unauthorized"),
+        (AppError::Forbidden(text()), StatusCode::FORBIDDEN, "forbidden"),
+        (AppError::NotFound(text()), StatusCode::NOT_FOUND, "not_found"),
+        (AppError::Conflict(text()), StatusCode::CONFLICT, "conflict"),
+        (
+            AppError::RateLimited { retry_after_secs: 30 },
+            StatusCode::TOO_MANY_REQUESTS,
+            "rate_limited",
+        ),
+        (AppError::Internal(text()), StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+    ]
+}
+
+#[actix_web::test]
+async fn every_variant_has_its_status_and_code() {
+    for (error, status, code) in every_variant() {
+        let response = error.error_response();
+        assert_eq!(response.status(), status, "status of {:?}", error);
+        assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), PROBLEM_JSON);
+
+        let body = actix_web::body::to_bytes(response.into_body()).await.unwrap();
+        let problem: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem.code, code, "code of {:?}", error);
+        assert_eq!(problem.status, status.as_u16());
+        assert_eq!(problem.problem_type, format!("urn:c-plane:problem:{}", code));
+    }
+}
+
+#[actix_web::test]
+async fn internal_details_are_not_exposed() {
+    let error = AppError::Database(DatabaseError::QueryFailed("relation \"secret\"".to_string()));
+    assert_eq!(error.problem().detail, "An internal error occurred");
+}
+
+#[actix_web::test]
+async fn problems_identify_the_request() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/projects/{}", Uuid::new_v4()))
+        .insert_header(("X-User", Uuid::new_v4().to_string()))
+        .insert_header(("X-Request-Id", "req-123"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), PROBLEM_JSON);
+
+    let problem: ErrorResponse = test::read_body_json(res).await;
+    assert_eq!(problem.code, "project_not_found");
+    assert_eq!(problem.title, "Not Found");
+    assert_eq!(problem.instance.as_deref(), Some("urn:c-plane:request:req-123"));
+    assert_eq!(problem.request_id.as_deref(), Some("req-123"));
+}
+
+#[actix_web::test]
+async fn constraint_violations_are_conflicts() {
+    let state = test_state().await;
+    let id = Uuid::new_v4();
+    let organisation = || OrganisationActiveModel {
+        id: Set(id),
+        name: Set("Acme".to_string()),
+        description: Set(None),
+        avatar_url: Set(None),
+        is_active: Set(true),
+        created_at: Set(fixed_time()),
+        updated_at: Set(fixed_time()),
+        created_by: Set(Uuid::new_v4()),
+        plan: Set(Plan::Free),
+        custom_limits: Set(None),
+        deleted_at: Set(None),
+        deleted_by: Set(None),
+    };
+    organisation().insert(&state.db).await.unwrap();
+
+    let duplicate = AppError::from(organisation().insert(&state.db).await.unwrap_err());
+    assert!(
+        matches!(duplicate, AppError::Database(DatabaseError::ConstraintViolation(_))),
+        "unique violation: {:?}",
+        duplicate
+    );
+    assert_eq!(duplicate.status_code(), StatusCode::CONFLICT);
+
+    let orphan = ProjectActiveModel {
+        id: Set(Uuid::new_v4()),
+        name: Set("Orphan".to_string()),
+        description: Set(None),
+        organisation_id: Set(Uuid::new_v4()),
+        owner_id: Set(Uuid::new_v4()),
+        is_archived: Set(false),
+        is_public: Set(false),
+        created_at: Set(fixed_time()),
+        updated_at: Set(fixed_time()),
+        deleted_at: Set(None),
+        deleted_by: Set(None),
+    };
+    let missing_parent = AppError::from(orphan.insert(&state.db).await.unwrap_err());
+    assert!(
+        matches!(missing_parent, AppError::Database(DatabaseError::ConstraintViolation(_))),
+        "foreign key violation: {:?}",
+        missing_parent
+    );
+}
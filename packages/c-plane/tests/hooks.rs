@@ -0,0 +1,185 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, web};
+use c_plane::create_app;
+use c_plane::models::entities::{OrganisationMember, OrganisationRole, organisation_member};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use common::{API_KEY, test_state};
+
+fn after_registration(key: &str, identity_id: Uuid, email: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/hooks/after-registration")
+        .insert_header(("X-API-KEY", key))
+        .set_json(json!({
+            "flow_id": Uuid::new_v4().to_string(),
+            "identity": {
+                "id": identity_id.to_string(),
+                "schema_id": "default",
+                "schema_url": "http://kratos.invalid/schemas/default",
+                "traits": {
+                    "name": { "first": "Ada", "last": "Lovelace" },
+                    "email": email,
+                },
+            },
+        }))
+}
+
+#[actix_web::test]
+async fn the_hook_requires_the_kratos_key() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+
+    let res = test::call_service(&app, after_registration("wrong", Uuid::new_v4(), "ada@example.com").to_request()).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[cfg(feature = "cloud")]
+#[actix_web::test]
+async fn registrations_get_an_organisation_each() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let identity_id = Uuid::new_v4();
+
+    let res = test::call_service(&app, after_registration(API_KEY, identity_id, "ada@example.com").to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let member = OrganisationMember::find()
+        .filter(organisation_member::Column::IdentityId.eq(identity_id))
+        .one(&state.db)
+        .await
+        .unwrap()
+        .expect("the identity has an organisation");
+    assert_eq!(member.role, OrganisationRole::Owner);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/organisations/{}", member.organisation_id))
+        .insert_header(("X-User", identity_id.to_string()))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["name"], "Ada Lovelace");
+}
+
+#[cfg(not(feature = "cloud"))]
+#[actix_web::test]
+async fn registrations_join_the_instance_organisation() {
+    use c_plane::models::entities::Plan;
+    use c_plane::services::instance::bootstrap;
+    use common::{INSTANCE_OWNER_EMAIL, set_plan};
+
+    let mut state = test_state().await;
+    // Its own organisation, so earlier runs on a shared database don't hold the Owner
+    state.config.instance.organisation_id = Uuid::new_v4();
+    let organisation_id = state.config.instance.organisation_id;
+    let organisation = bootstrap(&state.db, state.clock.as_ref(), &state.config.instance)
+        .await
+        .unwrap();
+    assert_eq!(organisation.name, "Instance");
+    assert_eq!(organisation.plan, Plan::Enterprise);
+    set_plan(&state.db, organisation_id, Plan::Free).await;
+    let again = bootstrap(&state.db, state.clock.as_ref(), &state.config.instance)
+        .await
+        .unwrap();
+    assert_eq!(again.id, organisation.id, "bootstrapping again keeps the organisation");
+    assert_eq!(again.plan, Plan::Enterprise, "and moves it back to the enterprise plan");
+
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let (first, owner) = (Uuid::new_v4(), Uuid::new_v4());
+    let registrations = [
+        (first, "first@example.com"),
+        (owner, &INSTANCE_OWNER_EMAIL.to_uppercase()[..]),
+        (first, "first@example.com"),
+    ];
+    for (identity_id, email) in registrations {
+        let res = test::call_service(&app, after_registration(API_KEY, identity_id, email).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let members = OrganisationMember::find()
+        .filter(organisation_member::Column::OrganisationId.eq(organisation_id))
+        .all(&state.db)
+        .await
+        .unwrap();
+    assert_eq!(members.len(), 2, "a retried webhook doesn't join twice");
+    let role = |identity_id| {
+        members
+            .iter()
+            .find(|member| member.identity_id == identity_id)
+            .map(|member| member.role.clone())
+    };
+    assert_eq!(role(first), Some(OrganisationRole::Member), "registering first doesn't make an Owner");
+    assert_eq!(role(owner), Some(OrganisationRole::Owner));
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/organisations/{}", organisation_id))
+        .insert_header(("X-User", first.to_string()))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["name"], "Instance");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/organisations/{}/usage", organisation_id))
+        .insert_header(("X-User", owner.to_string()))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND, "plans are compiled out");
+
+    let req = test::TestRequest::post()
+        .uri("/organisations/")
+        .insert_header(("X-User", owner.to_string()))
+        .set_json(json!({ "name": "Another", "description": null, "avatar_url": null }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND, "there is only the instance's organisation");
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/organisations/{}", organisation_id))
+        .insert_header(("X-User", owner.to_string()))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "cannot_delete_instance_organisation");
+}
+
+#[cfg(not(feature = "cloud"))]
+#[actix_web::test]
+async fn bootstrapping_brings_back_a_deleted_instance_organisation() {
+    use c_plane::models::entities::Organisation;
+    use c_plane::services::instance::bootstrap;
+    use common::fixed_time;
+    use sea_orm::{ActiveModelTrait, IntoActiveModel, Set};
+
+    let mut state = test_state().await;
+    state.config.instance.organisation_id = Uuid::new_v4();
+    let organisation = bootstrap(&state.db, state.clock.as_ref(), &state.config.instance)
+        .await
+        .unwrap()
+        .into_active_model();
+    let mut deleted = organisation;
+    deleted.deleted_at = Set(Some(fixed_time()));
+    deleted.deleted_by = Set(Some(Uuid::new_v4()));
+    deleted.update(&state.db).await.unwrap();
+
+    let organisation = bootstrap(&state.db, state.clock.as_ref(), &state.config.instance)
+        .await
+        .unwrap();
+    assert_eq!(organisation.deleted_at, None);
+    assert_eq!(organisation.deleted_by, None);
+
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let identity_id = Uuid::new_v4();
+    let res = test::call_service(&app, after_registration(API_KEY, identity_id, "new@example.com").to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK, "registrations work again");
+    let member = Organisation::find_by_id(state.config.instance.organisation_id)
+        .find_also_related(OrganisationMember)
+        .one(&state.db)
+        .await
+        .unwrap()
+        .and_then(|(_, member)| member)
+        .expect("the identity joined");
+    assert_eq!(member.identity_id, identity_id);
+}
@@ -0,0 +1,233 @@
+// A test binary of its own, since the worker pool claims every due job in the database
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use c_plane::errors::JobError;
+use c_plane::jobs::{
+    EnqueueOptions, Job, JobContext, JobRegistry, WorkerConfig, WorkerPool, enqueue,
+};
+use c_plane::models::entities::{Job as JobEntity, JobModel, JobStatus};
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use common::test_state;
+
+/// One pool at a time, so a test's jobs are run with its own backoff
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Runs seen per job id, across every worker
+static RUNS: LazyLock<Mutex<HashMap<Uuid, i32>>> = LazyLock::new(Default::default);
+
+fn runs(job_id: Uuid) -> i32 {
+    RUNS.lock().unwrap().get(&job_id).copied().unwrap_or(0)
+}
+
+/// Succeeds once it has failed `failures` times
+#[derive(Serialize, Deserialize)]
+struct Flaky {
+    failures: i32,
+}
+
+impl Job for Flaky {
+    const KIND: &'static str = "test.flaky";
+    const MAX_ATTEMPTS: i32 = 3;
+
+    async fn run(self, ctx: JobContext) -> Result<(), JobError> {
+        *RUNS.lock().unwrap().entry(ctx.job_id).or_default() += 1;
+        if ctx.attempt <= self.failures {
+            return Err(JobError::ExecutionFailed(format!(
+                "attempt {}",
+                ctx.attempt
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn config(base_backoff: Duration) -> WorkerConfig {
+    WorkerConfig {
+        workers: 4,
+        poll_interval: Duration::from_millis(10),
+        schedule_interval: Duration::from_secs(3600),
+        lock_timeout: Duration::from_secs(300),
+        base_backoff,
+        max_backoff: Duration::from_secs(3600),
+    }
+}
+
+fn start(db: &DatabaseConnection, base_backoff: Duration) -> WorkerPool {
+    let mut registry = JobRegistry::new();
+    registry.register::<Flaky>();
+    WorkerPool::start(db.clone(), registry, config(base_backoff))
+}
+
+async fn flaky(
+    db: &DatabaseConnection,
+    failures: i32,
+    options: EnqueueOptions,
+) -> Option<JobModel> {
+    enqueue(db, &Flaky { failures }, options).await.unwrap()
+}
+
+/// The job once `done` holds for it, failing the test after a few seconds
+async fn wait_for(
+    db: &DatabaseConnection,
+    job_id: Uuid,
+    done: impl Fn(&JobModel) -> bool,
+) -> JobModel {
+    for _ in 0..500 {
+        let job = JobEntity::find_by_id(job_id)
+            .one(db)
+            .await
+            .unwrap()
+            .unwrap();
+        if done(&job) {
+            return job;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("job {} didn't get there in time", job_id);
+}
+
+fn finished(job: &JobModel) -> bool {
+    matches!(job.status, JobStatus::Completed | JobStatus::Failed)
+}
+
+#[actix_web::test]
+async fn every_job_is_claimed_by_exactly_one_worker() {
+    let _serial = SERIAL.lock().await;
+    let state = test_state().await;
+    let mut ids = Vec::new();
+    for _ in 0..12 {
+        ids.push(
+            flaky(&state.db, 0, EnqueueOptions::default())
+                .await
+                .unwrap()
+                .id,
+        );
+    }
+
+    let pool = start(&state.db, Duration::ZERO);
+    for id in &ids {
+        let job = wait_for(&state.db, *id, finished).await;
+        assert_eq!(job.status, JobStatus::Completed);
+        assert_eq!(job.attempts, 1);
+        assert_eq!(job.locked_by, None);
+    }
+    pool.shutdown().await;
+
+    for id in ids {
+        assert_eq!(runs(id), 1, "job {} ran more than once", id);
+    }
+}
+
+#[actix_web::test]
+async fn failed_attempts_are_retried_after_the_backoff() {
+    let _serial = SERIAL.lock().await;
+    let state = test_state().await;
+    let job = flaky(&state.db, 1, EnqueueOptions::default())
+        .await
+        .unwrap();
+
+    let pool = start(&state.db, Duration::from_secs(600));
+    let retried = wait_for(&state.db, job.id, |job| {
+        job.attempts == 1 && job.status == JobStatus::Pending
+    })
+    .await;
+    pool.shutdown().await;
+
+    assert_eq!(
+        retried.last_error.as_deref(),
+        Some("Job execution failed: attempt 1")
+    );
+    assert_eq!(retried.locked_at, None);
+    assert_eq!(
+        (retried.run_at - retried.updated_at).num_seconds(),
+        600,
+        "the first retry waits the base backoff"
+    );
+}
+
+#[actix_web::test]
+async fn jobs_fail_once_their_attempts_run_out() {
+    let _serial = SERIAL.lock().await;
+    let state = test_state().await;
+    let exhausted = flaky(&state.db, 10, EnqueueOptions::default())
+        .await
+        .unwrap();
+    let recovered = flaky(&state.db, 2, EnqueueOptions::default())
+        .await
+        .unwrap();
+    let overridden = flaky(
+        &state.db,
+        10,
+        EnqueueOptions {
+            max_attempts: Some(1),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let pool = start(&state.db, Duration::ZERO);
+    let exhausted = wait_for(&state.db, exhausted.id, finished).await;
+    let recovered = wait_for(&state.db, recovered.id, finished).await;
+    let overridden = wait_for(&state.db, overridden.id, finished).await;
+    pool.shutdown().await;
+
+    assert_eq!(exhausted.status, JobStatus::Failed);
+    assert_eq!(exhausted.attempts, Flaky::MAX_ATTEMPTS);
+    assert_eq!(
+        exhausted.last_error.as_deref(),
+        Some("Job execution failed: attempt 3")
+    );
+    assert_eq!(runs(exhausted.id), 3);
+
+    assert_eq!(
+        recovered.status,
+        JobStatus::Completed,
+        "the last attempt may still succeed"
+    );
+    assert_eq!(recovered.attempts, 3);
+    assert_eq!(recovered.last_error, None);
+
+    assert_eq!(overridden.status, JobStatus::Failed);
+    assert_eq!(overridden.attempts, 1);
+}
+
+#[actix_web::test]
+async fn unique_keys_only_hold_while_a_job_is_unfinished() {
+    let _serial = SERIAL.lock().await;
+    let state = test_state().await;
+    let unique = Uuid::new_v4();
+    let key = || EnqueueOptions {
+        unique_key: Some(format!("test.flaky:{}", unique)),
+        ..Default::default()
+    };
+
+    let first = flaky(&state.db, 0, key()).await.expect("the key is free");
+    assert!(
+        flaky(&state.db, 0, key()).await.is_none(),
+        "taken by the pending job"
+    );
+    assert!(
+        flaky(&state.db, 0, EnqueueOptions::default())
+            .await
+            .is_some(),
+        "jobs without a key don't collide"
+    );
+
+    let pool = start(&state.db, Duration::ZERO);
+    wait_for(&state.db, first.id, finished).await;
+    pool.shutdown().await;
+
+    let second = flaky(&state.db, 0, key())
+        .await
+        .expect("finished jobs release their key");
+    assert_ne!(second.id, first.id);
+}
@@ -0,0 +1,132 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use actix_web::dev::{ResourceMap, Service};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{HttpRequest, HttpResponse, test, web};
+use c_plane::create_app;
+use serde_json::Value;
+use uuid::Uuid;
+
+use common::test_state;
+
+/// Answers requests no route takes, so probes can tell them apart from handler errors
+const UNROUTED: StatusCode = StatusCode::IM_A_TEAPOT;
+
+const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+/// Registered on purpose without an operation of their own
+const UNDOCUMENTED: [&str; 1] = ["/docs"];
+
+/// Names of the resources the app registered. actix-web has no public way to list
+/// them, so they are read off the resource map's debug output; the route macros name
+/// each resource after its handler, which is also the operation id utoipa gives it.
+fn resource_names(map: &ResourceMap) -> BTreeSet<String> {
+    let debug = format!("{:?}", map);
+    debug
+        .split("name: Some(\"")
+        .skip(1)
+        .filter_map(|rest| rest.split_once('"').map(|(name, _)| name.to_string()))
+        .collect()
+}
+
+/// `(operation id, path)` of every named resource, with the path as the router
+/// matches it, scope included
+fn registered_routes(req: &HttpRequest) -> BTreeSet<(String, String)> {
+    let map = req.resource_map();
+    let id = Uuid::nil().to_string();
+    resource_names(map)
+        .into_iter()
+        .map(|name| {
+            let url = req
+                .url_for(&name, [&id; 4])
+                .unwrap_or_else(|err| panic!("{} has no path: {}", name, err));
+            let pattern = map.match_pattern(url.path()).expect("the generated path matches");
+            (name, pattern)
+        })
+        .collect()
+}
+
+/// Placeholder values for the path parameters of `path`
+fn concrete(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            if segment.starts_with('{') {
+                Uuid::nil().to_string()
+            } else {
+                segment.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[actix_web::test]
+async fn documented_operations_match_the_built_routes() {
+    let state = test_state().await;
+    let registered = Rc::new(RefCell::new(BTreeSet::new()));
+    let recorded = registered.clone();
+    let app = create_app(web::Data::new(state))
+        .default_service(web::to(|| async { HttpResponse::new(UNROUTED) }))
+        .wrap_fn(move |req, srv| {
+            recorded.replace(registered_routes(req.request()));
+            srv.call(req)
+        });
+    let app = test::init_service(app).await;
+
+    let req = test::TestRequest::get().uri("/openapi.json").to_request();
+    let spec: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3.1"));
+
+    let mut documented = BTreeSet::new();
+    let mut operations = Vec::new();
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for method in METHODS {
+            if let Some(id) = item[method]["operationId"].as_str() {
+                documented.insert((id.to_string(), path.clone()));
+                operations.push((method, path.clone()));
+            }
+        }
+    }
+
+    let registered: BTreeSet<(String, String)> = registered
+        .take()
+        .into_iter()
+        .filter(|(_, path)| !UNDOCUMENTED.contains(&path.as_str()))
+        .collect();
+    assert!(!registered.is_empty(), "no routes were registered");
+    let undocumented: Vec<_> = registered.difference(&documented).collect();
+    assert!(undocumented.is_empty(), "routes missing from the OpenAPI document: {:?}", undocumented);
+    let unregistered: Vec<_> = documented.difference(&registered).collect();
+    assert!(unregistered.is_empty(), "documented routes the app doesn't build: {:?}", unregistered);
+
+    // Paths alone don't tell the methods apart, so each one is sent and has to be routed
+    for method in METHODS {
+        for path in spec["paths"].as_object().unwrap().keys() {
+            let expected = operations.contains(&(method, path.clone()));
+            let req = test::TestRequest::default()
+                .method(Method::from_bytes(method.to_uppercase().as_bytes()).unwrap())
+                .uri(&concrete(path))
+                // Scopes check credentials are present before routing
+                .insert_header(("X-User", Uuid::new_v4().to_string()))
+                .insert_header(("X-API-KEY", "probe"))
+                .to_request();
+            let routed = test::call_service(&app, req).await.status() != UNROUTED;
+            assert_eq!(routed, expected, "{} {}", method.to_uppercase(), path);
+        }
+    }
+}
+
+#[actix_web::test]
+async fn docs_ui_is_served() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+
+    let req = test::TestRequest::get().uri("/docs").to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert!(res.status().is_success());
+}
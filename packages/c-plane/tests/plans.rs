@@ -0,0 +1,122 @@
+#![cfg(feature = "cloud")]
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, web};
+use c_plane::create_app;
+use c_plane::models::entities::{Organisation, OrganisationActiveModel, Plan, PlanLimits};
+use sea_orm::{ActiveModelTrait, EntityTrait, Set};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use common::test_state;
+
+fn create_project(owner: Uuid, organisation_id: Uuid, name: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/projects")
+        .insert_header(("X-User", owner.to_string()))
+        .set_json(json!({
+            "name": name,
+            "description": null,
+            "slug": "quota",
+            "organisation_id": organisation_id,
+            "owner_id": owner,
+            "is_public": false,
+        }))
+}
+
+#[actix_web::test]
+async fn the_free_plan_caps_projects_until_one_is_deleted() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+    let owner = Uuid::new_v4();
+
+    let req = test::TestRequest::post()
+        .uri("/organisations/")
+        .insert_header(("X-User", owner.to_string()))
+        .set_json(json!({ "name": "Frugal", "description": null, "avatar_url": null }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let organisation_id: Uuid = body["organisation"]["id"].as_str().unwrap().parse().unwrap();
+
+    let first: Value =
+        test::call_and_read_body_json(&app, create_project(owner, organisation_id, "one").to_request()).await;
+    for name in ["two", "three"] {
+        let res = test::call_service(&app, create_project(owner, organisation_id, name).to_request()).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+    }
+    let res = test::call_service(&app, create_project(owner, organisation_id, "four").to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "project_limit_exceeded");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/organisations/{}/usage", organisation_id))
+        .insert_header(("X-User", owner.to_string()))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["plan"], "free");
+    assert_eq!(body["projects"], json!({ "used": 3, "limit": 3 }));
+    assert_eq!(body["members"], json!({ "used": 1, "limit": 5 }));
+    assert_eq!(body["limits"]["log_retention_days"], 7);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/projects/{}", first["id"].as_str().unwrap()))
+        .insert_header(("X-User", owner.to_string()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+    let res = test::call_service(&app, create_project(owner, organisation_id, "four").to_request()).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/projects/{}/restore", first["id"].as_str().unwrap()))
+        .insert_header(("X-User", owner.to_string()))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN, "restoring counts against the quota too");
+}
+
+#[actix_web::test]
+async fn custom_plans_use_the_organisation_limits() {
+    let state = test_state().await;
+    let db = state.db.clone();
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+    let owner = Uuid::new_v4();
+
+    let req = test::TestRequest::post()
+        .uri("/organisations/")
+        .insert_header(("X-User", owner.to_string()))
+        .set_json(json!({ "name": "Bespoke", "description": null, "avatar_url": null }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let organisation_id: Uuid = body["organisation"]["id"].as_str().unwrap().parse().unwrap();
+
+    let mut organisation: OrganisationActiveModel =
+        Organisation::find_by_id(organisation_id).one(&db).await.unwrap().unwrap().into();
+    organisation.plan = Set(Plan::Custom);
+    organisation.custom_limits = Set(Some(PlanLimits {
+        projects: Some(1),
+        members: None,
+        deployments: None,
+        agents: Some(25),
+        api_keys: None,
+        log_retention_days: 90,
+    }));
+    organisation.update(&db).await.unwrap();
+
+    let res = test::call_service(&app, create_project(owner, organisation_id, "only").to_request()).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let res = test::call_service(&app, create_project(owner, organisation_id, "extra").to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/organisations/{}/usage", organisation_id))
+        .insert_header(("X-User", owner.to_string()))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["plan"], "custom");
+    assert_eq!(body["projects"], json!({ "used": 1, "limit": 1 }));
+    assert_eq!(body["members"], json!({ "used": 1, "limit": null }));
+    assert_eq!(body["limits"]["agents"], 25);
+}
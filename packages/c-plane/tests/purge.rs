@@ -0,0 +1,149 @@
+// A test binary of its own, since purging touches every expired row of a shared database
+
+mod common;
+
+use c_plane::jobs::{Job, JobContext};
+use c_plane::jobs::purge::PurgeDeleted;
+use c_plane::models::entities::{
+    Organisation, OrganisationActiveModel, Plan, Project, ProjectActiveModel,
+};
+#[cfg(feature = "cloud")]
+use c_plane::models::entities::{
+    Invoice, InvoiceActiveModel, InvoiceStatus, UsageMetric, UsageRecord, UsageRecordActiveModel,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use c_plane::state::State;
+use sea_orm::{ActiveModelTrait, EntityTrait, Set};
+use uuid::Uuid;
+
+use common::{fixed_time, test_state};
+
+fn organisation(deleted_at: Option<DateTime<Utc>>) -> OrganisationActiveModel {
+    OrganisationActiveModel {
+        id: Set(Uuid::new_v4()),
+        name: Set("Acme".to_string()),
+        description: Set(None),
+        avatar_url: Set(None),
+        is_active: Set(deleted_at.is_none()),
+        created_at: Set(fixed_time() - TimeDelta::days(90)),
+        updated_at: Set(fixed_time() - TimeDelta::days(90)),
+        created_by: Set(Uuid::new_v4()),
+        plan: Set(Plan::Free),
+        custom_limits: Set(None),
+        deleted_at: Set(deleted_at),
+        deleted_by: Set(None),
+    }
+}
+
+fn project(organisation_id: Uuid, deleted_at: Option<DateTime<Utc>>) -> ProjectActiveModel {
+    ProjectActiveModel {
+        id: Set(Uuid::new_v4()),
+        name: Set("api".to_string()),
+        description: Set(None),
+        organisation_id: Set(organisation_id),
+        owner_id: Set(Uuid::new_v4()),
+        is_archived: Set(false),
+        is_public: Set(false),
+        created_at: Set(fixed_time() - TimeDelta::days(90)),
+        updated_at: Set(fixed_time() - TimeDelta::days(90)),
+        deleted_at: Set(deleted_at),
+        deleted_by: Set(None),
+    }
+}
+
+fn context(state: &State) -> JobContext {
+    JobContext {
+        db: state.db.clone(),
+        job_id: Uuid::new_v4(),
+        attempt: 1,
+        resources: Default::default(),
+        clock: state.clock.clone(),
+    }
+}
+
+#[actix_web::test]
+async fn purges_what_was_deleted_before_the_retention_period() {
+    let state = test_state().await;
+    let db = &state.db;
+    // Relative to the test clock, which is months behind the system one
+    let expired = Some(fixed_time() - TimeDelta::days(31));
+    let recent = Some(fixed_time() - TimeDelta::days(29));
+
+    let kept = organisation(None).insert(db).await.unwrap();
+    let live_project = project(kept.id, None).insert(db).await.unwrap();
+    let recent_project = project(kept.id, recent).insert(db).await.unwrap();
+    let expired_project = project(kept.id, expired).insert(db).await.unwrap();
+    let purged = organisation(expired).insert(db).await.unwrap();
+    let cascaded_project = project(purged.id, None).insert(db).await.unwrap();
+    let restorable = organisation(recent).insert(db).await.unwrap();
+
+    PurgeDeleted { retention_days: 30 }.run(context(&state)).await.unwrap();
+
+    for (id, exists) in [
+        (live_project.id, true),
+        (recent_project.id, true),
+        (expired_project.id, false),
+        (cascaded_project.id, false),
+    ] {
+        let found = Project::find_by_id(id).one(db).await.unwrap();
+        assert_eq!(found.is_some(), exists, "project {}", id);
+    }
+    for (id, exists) in [(kept.id, true), (purged.id, false), (restorable.id, true)] {
+        let found = Organisation::find_by_id(id).one(db).await.unwrap();
+        assert_eq!(found.is_some(), exists, "organisation {}", id);
+    }
+}
+
+#[cfg(feature = "cloud")]
+fn invoice(organisation_id: Uuid, status: InvoiceStatus) -> InvoiceActiveModel {
+    InvoiceActiveModel {
+        id: Set(Uuid::new_v4()),
+        organisation_id: Set(Some(organisation_id)),
+        period_start: Set(fixed_time() - TimeDelta::days(60)),
+        period_end: Set(fixed_time() - TimeDelta::days(30)),
+        currency: Set("eur".to_string()),
+        total_cents: Set(100),
+        status: Set(status),
+        payment_reference: Set(None),
+        failure_reason: Set(None),
+        created_at: Set(fixed_time() - TimeDelta::days(30)),
+        updated_at: Set(fixed_time() - TimeDelta::days(30)),
+    }
+}
+
+#[cfg(feature = "cloud")]
+#[actix_web::test]
+async fn billing_records_outlive_the_organisation_once_paid() {
+    let state = test_state().await;
+    let db = &state.db;
+    let expired = Some(fixed_time() - TimeDelta::days(31));
+
+    let unpaid = organisation(expired).insert(db).await.unwrap();
+    invoice(unpaid.id, InvoiceStatus::Failed).insert(db).await.unwrap();
+    let settled = organisation(expired).insert(db).await.unwrap();
+    let paid = invoice(settled.id, InvoiceStatus::Paid).insert(db).await.unwrap();
+    let usage = UsageRecordActiveModel {
+        id: Set(Uuid::new_v4()),
+        organisation_id: Set(Some(settled.id)),
+        project_id: Set(Uuid::new_v4()),
+        deployment_id: Set(Uuid::new_v4()),
+        metric: Set(UsageMetric::CpuSeconds),
+        quantity: Set(60),
+        recorded_at: Set(fixed_time() - TimeDelta::days(45)),
+        created_at: Set(fixed_time() - TimeDelta::days(45)),
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    PurgeDeleted { retention_days: 30 }.run(context(&state)).await.unwrap();
+
+    let found = Organisation::find_by_id(unpaid.id).one(db).await.unwrap();
+    assert!(found.is_some(), "kept until its invoice is paid");
+    let found = Organisation::find_by_id(settled.id).one(db).await.unwrap();
+    assert!(found.is_none());
+    let paid = Invoice::find_by_id(paid.id).one(db).await.unwrap().expect("invoices are kept");
+    assert_eq!(paid.organisation_id, None);
+    let usage = UsageRecord::find_by_id(usage.id).one(db).await.unwrap().expect("usage is kept");
+    assert_eq!(usage.organisation_id, None);
+}
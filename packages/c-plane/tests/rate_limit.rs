@@ -0,0 +1,221 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix_web::dev::ServiceResponse;
+use actix_web::http::StatusCode;
+use actix_web::http::header::{CONTENT_TYPE, RETRY_AFTER};
+use actix_web::{test, web};
+use c_plane::config::ValkeyConfig;
+use c_plane::create_app;
+use c_plane::errors::PROBLEM_JSON;
+use c_plane::services::rate_limit::{Budget, RateLimitStore, ValkeyStore};
+use c_plane::services::valkey::Valkey;
+use chrono::TimeDelta;
+use serde_json::Value;
+use uuid::Uuid;
+
+use common::{API_KEY, FixedClock, fixed_time, test_state};
+
+fn get(uri: &str, user: Uuid) -> test::TestRequest {
+    test::TestRequest::get().uri(uri).insert_header(("X-User", user.to_string()))
+}
+
+fn header<B>(res: &ServiceResponse<B>, name: &str) -> String {
+    res.headers().get(name).unwrap().to_str().unwrap().to_string()
+}
+
+fn valkey(url: &str) -> Arc<Valkey> {
+    let config = ValkeyConfig {
+        url: url.to_string(),
+        timeout_ms: 500,
+    };
+    Arc::new(Valkey::new(&config).unwrap())
+}
+
+#[actix_web::test]
+async fn each_user_gets_a_budget_that_refills_over_time() {
+    let mut state = test_state().await;
+    state.config.rate_limit.requests_per_minute = 2;
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let (user, other) = (Uuid::new_v4(), Uuid::new_v4());
+    let uri = format!("/organisations/{}", Uuid::new_v4());
+
+    for remaining in ["1", "0"] {
+        let res = test::call_service(&app, get(&uri, user).to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(header(&res, "ratelimit-limit"), "2");
+        assert_eq!(header(&res, "ratelimit-remaining"), remaining);
+    }
+
+    let res = test::call_service(&app, get(&uri, user).to_request()).await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&res, CONTENT_TYPE.as_str()), PROBLEM_JSON);
+    assert_eq!(header(&res, RETRY_AFTER.as_str()), "30", "a token takes half a minute at 2 a minute");
+    assert_eq!(header(&res, "ratelimit-remaining"), "0");
+    assert_eq!(header(&res, "ratelimit-reset"), "60");
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "rate_limited");
+    assert_eq!(body["status"], 429);
+
+    let req = get(&uri, other).peer_addr("10.0.0.9:40000".parse().unwrap());
+    let res = test::call_service(&app, req.to_request()).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND, "other users have their own bucket");
+
+    // Same buckets, half a minute later
+    state.clock = Arc::new(FixedClock(fixed_time() + TimeDelta::seconds(30)));
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+    let res = test::call_service(&app, get(&uri, user).to_request()).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(header(&res, "ratelimit-remaining"), "0");
+}
+
+#[actix_web::test]
+async fn expensive_routes_draw_from_their_own_budget() {
+    let mut state = test_state().await;
+    state.config.rate_limit.expensive_requests_per_minute = 1;
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+    let user = Uuid::new_v4();
+
+    let res = test::call_service(&app, get("/search?q=anything", user).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(header(&res, "ratelimit-limit"), "1");
+    let res = test::call_service(&app, get("/search?q=anything", user).to_request()).await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let uri = format!("/organisations/{}", Uuid::new_v4());
+    let res = test::call_service(&app, get(&uri, user).to_request()).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(header(&res, "ratelimit-limit"), "600");
+}
+
+#[actix_web::test]
+async fn api_keys_and_addresses_are_limited_and_probes_are_not() {
+    let mut state = test_state().await;
+    state.config.rate_limit.requests_per_minute = 1;
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+
+    let hook = || {
+        test::TestRequest::post()
+            .uri("/hooks/after-registration")
+            .insert_header(("X-API-KEY", API_KEY))
+            .set_json(serde_json::json!({}))
+            .to_request()
+    };
+    assert_ne!(test::call_service(&app, hook()).await.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(test::call_service(&app, hook()).await.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let anonymous = |ip: &str| {
+        test::TestRequest::get()
+            .uri("/openapi.json")
+            .peer_addr(format!("{}:40000", ip).parse().unwrap())
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, anonymous("10.0.0.1")).await.status(), StatusCode::OK);
+    assert_eq!(
+        test::call_service(&app, anonymous("10.0.0.1")).await.status(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+    assert_eq!(test::call_service(&app, anonymous("10.0.0.2")).await.status(), StatusCode::OK);
+
+    for _ in 0..3 {
+        let req = test::TestRequest::get()
+            .uri("/health/live")
+            .peer_addr("10.0.0.1:40000".parse().unwrap())
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get("ratelimit-limit").is_none());
+    }
+}
+
+#[actix_web::test]
+async fn credentials_never_buy_a_fresh_budget() {
+    let mut state = test_state().await;
+    state.config.rate_limit.requests_per_minute = 2;
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+    let uri = format!("/organisations/{}", Uuid::new_v4());
+    let from = |req: test::TestRequest, ip: &str| req.peer_addr(format!("{}:40000", ip).parse().unwrap()).to_request();
+
+    for _ in 0..2 {
+        let res = test::call_service(&app, from(get(&uri, Uuid::new_v4()), "10.0.0.1")).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+    let res = test::call_service(&app, from(get(&uri, Uuid::new_v4()), "10.0.0.1")).await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS, "the address is charged whoever the user");
+
+    let hook = |key: &str| {
+        test::TestRequest::post()
+            .uri("/hooks/after-registration")
+            .insert_header(("X-API-KEY", key))
+            .set_json(serde_json::json!({}))
+    };
+    for key in ["guess-1", "guess-2"] {
+        let res = test::call_service(&app, from(hook(key), "10.0.0.2")).await;
+        assert_ne!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+    let res = test::call_service(&app, from(hook("guess-3"), "10.0.0.2")).await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS, "unknown keys get no bucket of their own");
+
+    // A known key's bucket is shared by every address using it
+    for ip in ["10.0.0.3", "10.0.0.4"] {
+        let res = test::call_service(&app, from(hook(API_KEY), ip)).await;
+        assert_ne!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+    let res = test::call_service(&app, from(hook(API_KEY), "10.0.0.5")).await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&res, "ratelimit-remaining"), "0");
+}
+
+#[actix_web::test]
+async fn unreachable_valkey_falls_back_to_memory() {
+    // Nothing listens on the discard port
+    let store = ValkeyStore::new(valkey("redis://127.0.0.1:9/0"));
+    let budget = Budget { per_minute: 1 };
+
+    let decision = store.take("fallback", budget, fixed_time()).await.unwrap();
+    assert!(decision.allowed);
+    let decision = store.take("fallback", budget, fixed_time()).await.unwrap();
+    assert!(!decision.allowed);
+    assert_eq!(decision.retry_after_secs, 60);
+}
+
+#[actix_web::test]
+async fn a_hanging_valkey_is_skipped_after_one_timeout() {
+    // Accepts connections but never answers
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let store = ValkeyStore::new(valkey(&format!("redis://{}/0", listener.local_addr().unwrap())));
+    let budget = Budget { per_minute: 2 };
+
+    let started = Instant::now();
+    assert!(store.take("hanging", budget, fixed_time()).await.unwrap().allowed);
+    assert!(started.elapsed() >= Duration::from_millis(500), "the first command waits out the timeout");
+
+    let started = Instant::now();
+    let decision = store.take("hanging", budget, fixed_time()).await.unwrap();
+    assert!(started.elapsed() < Duration::from_millis(100), "later ones fall back at once");
+    assert_eq!(decision.remaining, 0, "from the same in-memory bucket");
+}
+
+/// Runs against the Valkey or Redis in `TEST_VALKEY_URL`, skipped without one
+#[actix_web::test]
+async fn instances_share_buckets_through_valkey() {
+    let Ok(url) = std::env::var("TEST_VALKEY_URL") else {
+        return;
+    };
+    let (first, second) = (ValkeyStore::new(valkey(&url)), ValkeyStore::new(valkey(&url)));
+    let key = format!("test:{}", Uuid::new_v4());
+    let budget = Budget { per_minute: 2 };
+
+    let decision = first.take(&key, budget, fixed_time()).await.unwrap();
+    assert!(decision.allowed);
+    assert_eq!(decision.remaining, 1);
+    assert!(second.take(&key, budget, fixed_time()).await.unwrap().allowed);
+    let decision = first.take(&key, budget, fixed_time()).await.unwrap();
+    assert!(!decision.allowed);
+    assert_eq!(decision.retry_after_secs, 30);
+
+    let later = fixed_time() + TimeDelta::seconds(30);
+    assert!(second.take(&key, budget, later).await.unwrap().allowed);
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, web};
+use c_plane::create_app;
+use c_plane::models::entities::{
+    Organisation, OrganisationMemberActiveModel, OrganisationRole, ProjectActiveModel,
+};
+use chrono::TimeDelta;
+use sea_orm::{ActiveModelTrait, EntityTrait, IntoActiveModel, Set};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use common::{create_organisation, fixed_time, test_state};
+
+fn create_project(owner: Uuid, organisation_id: Uuid) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/projects")
+        .insert_header(("X-User", owner.to_string()))
+        .set_json(json!({
+            "name": "api",
+            "description": null,
+            "slug": "api",
+            "organisation_id": organisation_id,
+            "owner_id": owner,
+            "is_public": false,
+        }))
+}
+
+fn request(method: &str, user: Uuid, uri: &str) -> test::TestRequest {
+    let req = match method {
+        "GET" => test::TestRequest::get(),
+        "POST" => test::TestRequest::post(),
+        _ => test::TestRequest::delete(),
+    };
+    req.uri(uri).insert_header(("X-User", user.to_string()))
+}
+
+#[actix_web::test]
+async fn deleted_projects_are_hidden_until_an_owner_restores_them() {
+    let state = test_state().await;
+    let db = state.db.clone();
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let owner = Uuid::new_v4();
+    let member = Uuid::new_v4();
+
+    let organisation_id = create_organisation(&state, owner, "Acme").await;
+    OrganisationMemberActiveModel {
+        id: Set(Uuid::new_v4()),
+        organisation_id: Set(organisation_id),
+        identity_id: Set(member),
+        role: Set(OrganisationRole::Member),
+        is_active: Set(true),
+        joined_at: Set(fixed_time()),
+        invited_by: Set(owner),
+        invited_at: Set(fixed_time()),
+        invitation_accepted_at: Set(fixed_time()),
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+    let project: Value =
+        test::call_and_read_body_json(&app, create_project(owner, organisation_id).to_request()).await;
+    let uri = format!("/projects/{}", project["id"].as_str().unwrap());
+
+    let res = test::call_service(&app, request("DELETE", member, &uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN, "deleting takes an admin");
+    let res = test::call_service(&app, request("DELETE", owner, &uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let res = test::call_service(&app, request("GET", owner, &uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let res = test::call_service(&app, request("DELETE", owner, &uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND, "already deleted");
+    let list_uri = format!("/projects?filter[organisation_id]={}", organisation_id);
+    let body: Value = test::call_and_read_body_json(&app, request("GET", owner, &list_uri).to_request()).await;
+    assert_eq!(body["pagination"]["total"], 0);
+
+    let restore_uri = format!("{}/restore", uri);
+    let res = test::call_service(&app, request("POST", member, &restore_uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["code"], "insufficient_role");
+    let res = test::call_service(&app, request("POST", Uuid::new_v4(), &restore_uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = test::call_service(&app, request("POST", owner, &restore_uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, request("GET", member, &uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn projects_past_the_retention_period_cannot_be_restored() {
+    let state = test_state().await;
+    let db = state.db.clone();
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let owner = Uuid::new_v4();
+
+    let organisation_id = create_organisation(&state, owner, "Acme").await;
+    let project = |days_ago: i64| ProjectActiveModel {
+        id: Set(Uuid::new_v4()),
+        name: Set("old".to_string()),
+        description: Set(None),
+        organisation_id: Set(organisation_id),
+        owner_id: Set(owner),
+        is_archived: Set(false),
+        is_public: Set(false),
+        created_at: Set(fixed_time() - TimeDelta::days(60)),
+        updated_at: Set(fixed_time() - TimeDelta::days(days_ago)),
+        deleted_at: Set(Some(fixed_time() - TimeDelta::days(days_ago))),
+        deleted_by: Set(Some(owner)),
+    };
+    let expired = project(31).insert(&db).await.unwrap();
+    let recent = project(29).insert(&db).await.unwrap();
+
+    let uri = format!("/projects/{}/restore", expired.id);
+    let res = test::call_service(&app, request("POST", owner, &uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let uri = format!("/projects/{}/restore", recent.id);
+    let res = test::call_service(&app, request("POST", owner, &uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn deleting_an_organisation_hides_its_projects() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let owner = Uuid::new_v4();
+
+    let organisation_id = create_organisation(&state, owner, "Acme").await;
+    let project: Value =
+        test::call_and_read_body_json(&app, create_project(owner, organisation_id).to_request()).await;
+    let organisation_uri = format!("/organisations/{}", organisation_id);
+    let project_uri = format!("/projects/{}", project["id"].as_str().unwrap());
+
+    let res = test::call_service(&app, request("DELETE", owner, &organisation_uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    for uri in [&organisation_uri, &project_uri] {
+        let res = test::call_service(&app, request("GET", owner, uri).to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND, "{}", uri);
+    }
+    let res = test::call_service(&app, create_project(owner, organisation_id).to_request()).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let uri = format!("{}/restore", organisation_uri);
+    let res = test::call_service(&app, request("POST", owner, &uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["is_active"], true);
+    let res = test::call_service(&app, request("GET", owner, &project_uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn restoring_keeps_an_inactive_organisation_inactive() {
+    let state = test_state().await;
+    let db = state.db.clone();
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let owner = Uuid::new_v4();
+
+    let organisation_id = create_organisation(&state, owner, "Acme").await;
+    let mut organisation = Organisation::find_by_id(organisation_id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_active_model();
+    organisation.is_active = Set(false);
+    organisation.update(&db).await.unwrap();
+
+    let uri = format!("/organisations/{}", organisation_id);
+    let res = test::call_service(&app, request("DELETE", owner, &uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = test::call_service(&app, request("POST", owner, &format!("{}/restore", uri)).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["is_active"], false, "restoring only undoes the delete");
+}
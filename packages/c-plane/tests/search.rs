@@ -0,0 +1,98 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, web};
+use c_plane::create_app;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use common::{create_organisation, test_state};
+
+fn create_project(owner: Uuid, organisation_id: Uuid, name: &str, description: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/projects")
+        .insert_header(("X-User", owner.to_string()))
+        .set_json(json!({
+            "name": name,
+            "description": description,
+            "slug": "search",
+            "organisation_id": organisation_id,
+            "owner_id": owner,
+            "is_public": false,
+        }))
+}
+
+fn search(user: Uuid, q: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(&format!("/search?q={}", q))
+        .insert_header(("X-User", user.to_string()))
+}
+
+fn hits(body: &Value) -> Vec<(String, String)> {
+    body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| (r["type"].as_str().unwrap().to_string(), r["name"].as_str().unwrap().to_string()))
+        .collect()
+}
+
+#[actix_web::test]
+async fn finds_word_prefixes_in_member_organisations() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state.clone()))).await;
+    let member = Uuid::new_v4();
+    let outsider = Uuid::new_v4();
+    // Unique per run, since a shared Postgres database keeps earlier runs' rows
+    let word = format!("zephyr{}", Uuid::new_v4().simple());
+    let prefix = &word[..14];
+
+    let organisation_id = create_organisation(&state, member, &format!("{} labs", word)).await;
+    for (name, description) in [
+        ("gateway".to_string(), format!("Routes traffic for {}", word)),
+        (format!("{} api", word), "Public API".to_string()),
+        ("unrelated".to_string(), "Nothing to see".to_string()),
+    ] {
+        let req = create_project(member, organisation_id, &name, &description).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+    }
+
+    let rival_id = create_organisation(&state, outsider, &format!("{} rivals", word)).await;
+    let req = create_project(outsider, rival_id, &format!("{} secret", word), "").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+
+    let body: Value = test::call_and_read_body_json(&app, search(member, prefix).to_request()).await;
+    let results = hits(&body);
+    assert_eq!(results.len(), 3, "{:?}", results);
+    assert!(results.contains(&("organisation".to_string(), format!("{} labs", word))));
+    assert!(results.contains(&("project".to_string(), format!("{} api", word))));
+    assert_eq!(
+        results[2],
+        ("project".to_string(), "gateway".to_string()),
+        "matches on the description rank last"
+    );
+
+    let req = search(member, &format!("{}+ap", prefix)).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(hits(&body), [("project".to_string(), format!("{} api", word))]);
+
+    let body: Value = test::call_and_read_body_json(&app, search(outsider, prefix).to_request()).await;
+    let results = hits(&body);
+    assert_eq!(results.len(), 2, "{:?}", results);
+    assert!(results.iter().all(|(_, name)| !name.ends_with("labs") && !name.ends_with("api")));
+}
+
+#[actix_web::test]
+async fn rejects_queries_without_words() {
+    let state = test_state().await;
+    let app = test::init_service(create_app(web::Data::new(state))).await;
+
+    let res = test::call_service(&app, search(Uuid::new_v4(), "%26%21%3A*").to_request()).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["errors"][0]["field"], "q");
+    assert_eq!(body["errors"][0]["code"], "blank");
+
+    let res = test::call_service(&app, search(Uuid::new_v4(), "").to_request()).await;
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
@@ -0,0 +1,130 @@
+// A test binary of its own, since telemetry installs process-wide providers
+
+mod common;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use c_plane::state;
+use c_plane::utils::telemetry::{Telemetry, set_remote_parent};
+use sea_orm::{ConnectionTrait, Statement};
+use tracing::Instrument;
+use tracing_subscriber::Registry;
+use tracing_subscriber::layer::SubscriberExt;
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+type Exports = Arc<Mutex<Vec<(String, Vec<u8>)>>>;
+
+/// Stands in for an OTLP/HTTP collector, recording the path and body of every export
+fn collector() -> (String, Exports) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let exports = Exports::default();
+    let recorded = exports.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let recorded = recorded.clone();
+            thread::spawn(move || serve(stream, recorded));
+        }
+    });
+    (endpoint, exports)
+}
+
+fn serve(stream: TcpStream, exports: Exports) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut stream = stream;
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+            return;
+        }
+        let path = request_line.split_whitespace().nth(1).unwrap_or_default().to_string();
+
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':')
+                && name.eq_ignore_ascii_case("content-length")
+            {
+                length = value.trim().parse().unwrap();
+            }
+        }
+
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        exports.lock().unwrap().push((path, body));
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+fn hex(value: &str) -> Vec<u8> {
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn request_and_statement_spans_reach_the_collector() {
+    let (endpoint, exports) = collector();
+    let mut config = common::config("sqlite::memory:");
+    config.telemetry.otlp_endpoint = Some(endpoint);
+    config.telemetry.service_name = "c-plane-telemetry-test".to_string();
+
+    let (telemetry, layer) = Telemetry::init(&config).unwrap().unwrap();
+    let subscriber = Registry::default().with(layer);
+    tracing::subscriber::with_default(subscriber, || {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("traceparent"),
+            HeaderValue::from_str(&format!("00-{}-00f067aa0ba902b7-01", TRACE_ID)).unwrap(),
+        );
+        let span = tracing::info_span!("GET /organisations");
+        set_remote_parent(&span, &headers);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(
+            async {
+                let db = state::connect(&config.database).await.unwrap();
+                db.execute(Statement::from_string(db.get_database_backend(), "SELECT 1 AS probe"))
+                    .await
+                    .unwrap();
+            }
+            .instrument(span),
+        );
+    });
+    telemetry.shutdown();
+
+    let exports = exports.lock().unwrap();
+    let traces: Vec<u8> = exports
+        .iter()
+        .filter(|(path, _)| path == "/v1/traces")
+        .flat_map(|(_, body)| body.clone())
+        .collect();
+    assert!(!traces.is_empty(), "no traces were exported");
+    assert!(contains(&traces, b"c-plane-telemetry-test"), "the service name is missing");
+    assert!(contains(&traces, b"GET /organisations"), "the request span is missing");
+    assert!(
+        contains(&traces, b"db.query.text") && contains(&traces, b"SELECT 1 AS probe"),
+        "the statement span is missing"
+    );
+    assert!(contains(&traces, &hex(TRACE_ID)), "the remote parent was not continued");
+}